@@ -1,6 +1,6 @@
 #![no_std]
 
-use tailcall::{tailcall, Thunk};
+use tailcall::{runtime::Arena, tailcall, Thunk};
 
 #[tailcall]
 pub fn countdown(input: u32) -> u32 {
@@ -24,3 +24,22 @@ fn build_countdown(input: u32) -> Thunk<'static, u32> {
         }
     })
 }
+
+// Each bounce captures more state than fits inline, so it is moved into the caller's arena.
+#[tailcall(arena = scratch)]
+pub fn mix_even(scratch: &Arena<'_>, state: [u32; 6], n: u32) -> u32 {
+    if n == 0 {
+        state.iter().fold(0, |acc, x| acc ^ x)
+    } else {
+        tailcall::call! { mix_odd(scratch, [state[5], state[0], state[1], state[2], state[3], state[4]], n - 1) }
+    }
+}
+
+#[tailcall(arena = scratch)]
+pub fn mix_odd(scratch: &Arena<'_>, state: [u32; 6], n: u32) -> u32 {
+    if n == 0 {
+        state.iter().fold(0, |acc, x| acc ^ x)
+    } else {
+        tailcall::call! { mix_even(scratch, state.map(|x| x.rotate_left(1)), n - 1) }
+    }
+}
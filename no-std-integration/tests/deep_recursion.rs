@@ -1,4 +1,6 @@
-use no_std_integration::{countdown, runtime_countdown};
+use core::mem::MaybeUninit;
+use no_std_integration::{countdown, mix_even, runtime_countdown};
+use tailcall::runtime::Arena;
 
 #[cfg(miri)]
 const DEEP_COUNTDOWN: u32 = 10_000;
@@ -15,3 +17,12 @@ fn macro_runtime_handles_deep_recursion_in_no_std_crate() {
 fn manual_runtime_handles_deep_recursion_in_no_std_crate() {
     assert_eq!(runtime_countdown(DEEP_COUNTDOWN), 0);
 }
+
+#[test]
+fn arena_runtime_handles_deep_recursion_in_no_std_crate() {
+    let mut scratch = [MaybeUninit::uninit(); 64];
+    let arena = Arena::new(&mut scratch);
+
+    assert_eq!(mix_even(&arena, [1, 2, 4, 8, 16, 32], 0), 63);
+    assert_eq!(mix_even(&arena, [1; 6], DEEP_COUNTDOWN), 0);
+}
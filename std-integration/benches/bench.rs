@@ -1,39 +1,11 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use std::boxed::Box;
-use tailcall::*;
+use tailcall::{runtime::BoxThunk, *};
 
 enum OddnessStep {
     Even(u128),
     Odd(u128),
 }
 
-enum BoxThunk<T> {
-    Done(T),
-    Bounce(Box<dyn FnOnce() -> BoxThunk<T>>),
-}
-
-impl<T> BoxThunk<T> {
-    fn value(value: T) -> Self {
-        Self::Done(value)
-    }
-
-    fn bounce<F>(f: F) -> Self
-    where
-        F: FnOnce() -> Self + 'static,
-    {
-        Self::Bounce(Box::new(f))
-    }
-
-    fn call(mut self) -> T {
-        loop {
-            match self {
-                Self::Done(value) => return value,
-                Self::Bounce(next) => self = next(),
-            }
-        }
-    }
-}
-
 #[inline(always)]
 fn scramble_step(state: u64, n: u64) -> u64 {
     state.rotate_left(7) ^ n.wrapping_mul(0x9E37_79B9_7F4A_7C15)
@@ -94,7 +66,31 @@ fn build_is_odd_runtime(x: u128) -> runtime::Thunk<'static, bool> {
     })
 }
 
-fn build_is_even_box_runtime(x: u128) -> BoxThunk<bool> {
+fn is_odd_fn_pointer_runtime(x: u128) -> bool {
+    build_is_odd_fn_pointer_runtime(x).call()
+}
+
+fn build_is_even_fn_pointer_runtime(x: u128) -> runtime::Thunk<'static, bool> {
+    runtime::Thunk::bounce_with(x, |x| {
+        if x > 0 {
+            build_is_odd_fn_pointer_runtime(x - 1)
+        } else {
+            runtime::Thunk::value(true)
+        }
+    })
+}
+
+fn build_is_odd_fn_pointer_runtime(x: u128) -> runtime::Thunk<'static, bool> {
+    runtime::Thunk::bounce_with(x, |x| {
+        if x > 0 {
+            build_is_even_fn_pointer_runtime(x - 1)
+        } else {
+            runtime::Thunk::value(false)
+        }
+    })
+}
+
+fn build_is_even_box_runtime(x: u128) -> BoxThunk<'static, bool> {
     BoxThunk::bounce(move || {
         if x > 0 {
             build_is_odd_box_runtime(x - 1)
@@ -104,7 +100,7 @@ fn build_is_even_box_runtime(x: u128) -> BoxThunk<bool> {
     })
 }
 
-fn build_is_odd_box_runtime(x: u128) -> BoxThunk<bool> {
+fn build_is_odd_box_runtime(x: u128) -> BoxThunk<'static, bool> {
     BoxThunk::bounce(move || {
         if x > 0 {
             build_is_even_box_runtime(x - 1)
@@ -174,6 +170,24 @@ fn is_odd_mutrec(x: u128) -> bool {
     }
 }
 
+#[tailcall(hybrid)]
+fn is_even_hybrid(x: u128) -> bool {
+    if x > 0 {
+        tailcall::call! { is_odd_hybrid(x - 1) }
+    } else {
+        true
+    }
+}
+
+#[tailcall(hybrid)]
+fn is_odd_hybrid(x: u128) -> bool {
+    if x > 0 {
+        tailcall::call! { is_even_hybrid(x - 1) }
+    } else {
+        false
+    }
+}
+
 const ODD_TEST_NUM: u128 = 1000000;
 const SCRAMBLE_TEST_NUM: u64 = 1_000_000;
 
@@ -207,6 +221,16 @@ fn bench_oddness_runtime(c: &mut Criterion) {
     });
 }
 
+fn bench_oddness_fn_pointer_runtime(c: &mut Criterion) {
+    let mut val: u128 = ODD_TEST_NUM;
+    c.bench_function("oddness_fn_pointer_runtime", |b| {
+        b.iter(|| {
+            black_box(is_odd_fn_pointer_runtime(black_box(val)));
+            val += 1;
+        })
+    });
+}
+
 fn bench_oddness_box_runtime(c: &mut Criterion) {
     let mut val: u128 = ODD_TEST_NUM;
     c.bench_function("oddness_box_runtime", |b| {
@@ -217,6 +241,16 @@ fn bench_oddness_box_runtime(c: &mut Criterion) {
     });
 }
 
+fn bench_oddness_hybrid(c: &mut Criterion) {
+    let mut val: u128 = ODD_TEST_NUM;
+    c.bench_function("oddness_hybrid_tailcall", |b| {
+        b.iter(|| {
+            black_box(is_odd_hybrid(black_box(val)));
+            val += 1;
+        })
+    });
+}
+
 fn bench_oddness_tailcall_optimized(c: &mut Criterion) {
     let mut val: u128 = ODD_TEST_NUM;
     c.bench_function("oddness_tailcall_optimized", |b| {
@@ -283,11 +317,39 @@ fn build_scramble_runtime(n: u64, state: u64) -> runtime::Thunk<'static, u64> {
     })
 }
 
+fn scramble_fn_pointer_runtime(n: u64, state: u64) -> u64 {
+    build_scramble_fn_pointer_runtime(n, state).call()
+}
+
+fn build_scramble_fn_pointer_runtime(n: u64, state: u64) -> runtime::Thunk<'static, u64> {
+    runtime::Thunk::bounce_with((n, state), |(n, state)| {
+        if n > 0 {
+            build_scramble_fn_pointer_runtime(n - 1, scramble_step(state, n))
+        } else {
+            runtime::Thunk::value(state)
+        }
+    })
+}
+
+fn scramble_hybrid_runtime(n: u64, state: u64) -> u64 {
+    build_scramble_hybrid_runtime(n, state).call()
+}
+
+fn build_scramble_hybrid_runtime(n: u64, state: u64) -> runtime::Thunk<'static, u64> {
+    runtime::Thunk::call_or_bounce(64, move || {
+        if n > 0 {
+            build_scramble_hybrid_runtime(n - 1, scramble_step(state, n))
+        } else {
+            runtime::Thunk::value(state)
+        }
+    })
+}
+
 fn scramble_box_runtime(n: u64, state: u64) -> u64 {
     build_scramble_box_runtime(n, state).call()
 }
 
-fn build_scramble_box_runtime(n: u64, state: u64) -> BoxThunk<u64> {
+fn build_scramble_box_runtime(n: u64, state: u64) -> BoxThunk<'static, u64> {
     BoxThunk::bounce(move || {
         if n > 0 {
             build_scramble_box_runtime(n - 1, scramble_step(state, n))
@@ -336,6 +398,32 @@ fn bench_scramble_runtime(c: &mut Criterion) {
     });
 }
 
+fn bench_scramble_fn_pointer_runtime(c: &mut Criterion) {
+    let mut val = SCRAMBLE_TEST_NUM;
+    c.bench_function("scramble_fn_pointer_runtime", |b| {
+        b.iter(|| {
+            black_box(scramble_fn_pointer_runtime(
+                black_box(val),
+                black_box(0xDEAD_BEEF_DEAD_BEEF),
+            ));
+            val += 1;
+        })
+    });
+}
+
+fn bench_scramble_hybrid_runtime(c: &mut Criterion) {
+    let mut val = SCRAMBLE_TEST_NUM;
+    c.bench_function("scramble_hybrid_runtime", |b| {
+        b.iter(|| {
+            black_box(scramble_hybrid_runtime(
+                black_box(val),
+                black_box(0xDEAD_BEEF_DEAD_BEEF),
+            ));
+            val += 1;
+        })
+    });
+}
+
 fn bench_scramble_box_runtime(c: &mut Criterion) {
     let mut val = SCRAMBLE_TEST_NUM;
     c.bench_function("scramble_box_runtime", |b| {
@@ -367,13 +455,17 @@ criterion_group!(
     bench_oddness_loop,
     bench_oddness_enum_dispatch,
     bench_oddness_runtime,
+    bench_oddness_fn_pointer_runtime,
     bench_oddness_box_runtime,
+    bench_oddness_hybrid,
     bench_oddness_tailcall_optimized,
     bench_oddness_res_rec,
     bench_oddness_boom,
     bench_oddness_mutrec,
     bench_scramble_loop,
     bench_scramble_runtime,
+    bench_scramble_fn_pointer_runtime,
+    bench_scramble_hybrid_runtime,
     bench_scramble_box_runtime,
     bench_scramble_tailcall_optimized
 );
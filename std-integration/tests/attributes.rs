@@ -0,0 +1,91 @@
+#![deny(deprecated)]
+
+use std::panic::Location;
+use tailcall::tailcall;
+
+#[cfg(not(test))]
+struct MissingType;
+
+struct Counter;
+
+// Items inside an `impl`-level `#[tailcall]` reach the macro before their own `#[cfg]` is
+// evaluated, so the helper has to carry the `#[cfg]` to avoid naming a missing type. `#[cfg_attr]`
+// is carried over in the same way.
+#[tailcall]
+impl Counter {
+    #[cfg(not(test))]
+    #[tailcall]
+    fn uses_cfgd_out_type(&self, n: u32, _missing: &MissingType) -> u32 {
+        let n = n;
+
+        if n == 0 {
+            0
+        } else {
+            tailcall::call! { self.uses_cfgd_out_type(n - 1, _missing) }
+        }
+    }
+
+    #[cfg_attr(test, must_use)]
+    #[tailcall]
+    fn cfg_attr_countdown(&self, n: u32) -> u32 {
+        let n = n;
+
+        if n == 0 {
+            0
+        } else {
+            tailcall::call! { self.cfg_attr_countdown(n - 1) }
+        }
+    }
+}
+
+#[test]
+fn cfg_attributes_follow_the_wrapper_onto_the_helper() {
+    assert_eq!(Counter.cfg_attr_countdown(1_000), 0);
+}
+
+#[track_caller]
+#[tailcall(helper_attrs(track_caller))]
+fn caller_line(n: u32) -> u32 {
+    if n == 0 {
+        Location::caller().line()
+    } else {
+        tailcall::call! { caller_line(n - 1) }
+    }
+}
+
+#[test]
+fn track_caller_is_kept_on_the_wrapper() {
+    let line = line!() + 1;
+    let reported = caller_line(10);
+
+    assert_eq!(reported, line);
+}
+
+#[deprecated = "use something else"]
+#[tailcall(helper_attrs(deprecated))]
+fn deprecated_countdown(n: u32) -> u32 {
+    let n = n;
+
+    if n == 0 {
+        0
+    } else {
+        tailcall::call! { deprecated_countdown(n - 1) }
+    }
+}
+
+#[deprecated = "use something else"]
+#[tailcall]
+fn deprecated_loop_countdown(n: u32) -> u32 {
+    if n == 0 {
+        0
+    } else {
+        tailcall::call! { deprecated_loop_countdown(n - 1) }
+    }
+}
+
+#[test]
+#[allow(deprecated)]
+fn deprecated_items_do_not_warn_inside_generated_glue() {
+    assert_eq!(deprecated_countdown(1_000), 0);
+    assert_eq!(deprecated_loop_countdown(1_000), 0);
+}
@@ -0,0 +1,82 @@
+use tailcall::{runtime::BoxThunk, tailcall, Thunk};
+
+#[cfg(miri)]
+const DEEP: u32 = 1_000;
+
+#[cfg(not(miri))]
+const DEEP: u32 = 100_000;
+
+// Each bounce captures 40 bytes of arguments, more than the default inline budget.
+#[tailcall(runtime = "boxed")]
+fn fold_even(n: u32, lanes: [u64; 4]) -> u64 {
+    if n == 0 {
+        lanes.iter().sum()
+    } else {
+        tailcall::call! { fold_odd(n - 1, [lanes[1], lanes[2], lanes[3], lanes[0] + 1]) }
+    }
+}
+
+#[tailcall(runtime = "boxed")]
+fn fold_odd(n: u32, lanes: [u64; 4]) -> u64 {
+    if n == 0 {
+        lanes.iter().sum()
+    } else {
+        tailcall::call! { fold_even(n - 1, [lanes[3], lanes[0], lanes[1], lanes[2] + 1]) }
+    }
+}
+
+struct Parser {
+    separators: [u8; 24],
+}
+
+#[tailcall]
+impl Parser {
+    #[tailcall(runtime = "boxed")]
+    fn skip<'a>(&self, rest: &'a [u8]) -> &'a [u8] {
+        match rest {
+            [first, tail @ ..] if self.separators.contains(first) => {
+                tailcall::call! { self.skip_twice(tail) }
+            }
+            _ => rest,
+        }
+    }
+
+    #[tailcall(runtime = "boxed")]
+    fn skip_twice<'a>(&self, rest: &'a [u8]) -> &'a [u8] {
+        tailcall::call! { self.skip(rest) }
+    }
+}
+
+#[test]
+fn boxed_runtime_handles_captures_beyond_the_inline_budget() {
+    assert_eq!(fold_even(0, [1, 2, 3, 4]), 10);
+    assert_eq!(fold_even(4, [1, 2, 3, 4]), 14);
+    assert_eq!(fold_even(DEEP, [0; 4]), u64::from(DEEP));
+}
+
+#[test]
+fn boxed_runtime_supports_methods() {
+    let parser = Parser {
+        separators: [b' '; 24],
+    };
+    let input = vec![b' '; DEEP as usize];
+
+    assert_eq!(parser.skip(&input), b"");
+    assert_eq!(parser.skip(b"  abc"), b"abc");
+}
+
+#[test]
+fn boxed_and_inline_thunks_convert_into_each_other() {
+    fn countdown(n: u32) -> Thunk<'static, u32> {
+        Thunk::bounce(move || {
+            if n == 0 {
+                Thunk::value(0)
+            } else {
+                countdown(n - 1)
+            }
+        })
+    }
+
+    assert_eq!(BoxThunk::from(countdown(DEEP)).call(), 0);
+    assert_eq!(Thunk::from(BoxThunk::from(countdown(DEEP))).call(), 0);
+}
@@ -0,0 +1,94 @@
+use std::{cell::Cell, ops::ControlFlow};
+use tailcall::tailcall;
+
+#[cfg(miri)]
+const DEEP: u32 = 1_000;
+
+#[cfg(not(miri))]
+const DEEP: u32 = 100_000;
+
+thread_local! {
+    // The number of logical calls allowed before `out_of_budget` cancels the computation.
+    static BUDGET: Cell<u32> = const { Cell::new(u32::MAX) };
+}
+
+fn out_of_budget<T>() -> ControlFlow<Option<T>> {
+    BUDGET.with(|budget| match budget.get() {
+        0 => ControlFlow::Break(None),
+        remaining => {
+            budget.set(remaining - 1);
+            ControlFlow::Continue(())
+        }
+    })
+}
+
+fn with_budget<T>(budget: u32, f: impl FnOnce() -> T) -> (T, u32) {
+    BUDGET.with(|cell| cell.set(budget));
+    let result = f();
+    (result, BUDGET.with(Cell::get))
+}
+
+#[tailcall(check = out_of_budget)]
+fn countdown(n: u32) -> Option<u32> {
+    if n == 0 {
+        Some(0)
+    } else {
+        tailcall::call! { countdown(n - 1) }
+    }
+}
+
+#[tailcall(check = out_of_budget)]
+fn is_even(n: u32) -> Option<bool> {
+    if n == 0 {
+        Some(true)
+    } else {
+        tailcall::call! { is_odd(n - 1) }
+    }
+}
+
+#[tailcall(check = out_of_budget)]
+fn is_odd(n: u32) -> Option<bool> {
+    if n == 0 {
+        Some(false)
+    } else {
+        tailcall::call! { is_even(n - 1) }
+    }
+}
+
+struct Walker {
+    stride: u32,
+}
+
+impl Walker {
+    #[tailcall(check = out_of_budget)]
+    fn walk(&self, n: u32) -> Option<u32> {
+        if n < self.stride {
+            Some(n)
+        } else {
+            tailcall::call! { self.walk(n - self.stride) }
+        }
+    }
+}
+
+#[test]
+fn loop_lowered_functions_stop_when_the_check_breaks() {
+    assert_eq!(with_budget(10, || countdown(DEEP)), (None, 0));
+    assert_eq!(with_budget(DEEP + 1, || countdown(DEEP)), (Some(0), 0));
+}
+
+#[test]
+fn runtime_backed_functions_stop_when_the_check_breaks() {
+    assert_eq!(with_budget(10, || is_even(DEEP)), (None, 0));
+    assert_eq!(with_budget(DEEP + 5, || is_even(DEEP)), (Some(true), 4));
+}
+
+#[test]
+fn methods_stop_when_the_check_breaks() {
+    let walker = Walker { stride: 2 };
+
+    assert_eq!(with_budget(10, || walker.walk(DEEP)), (None, 0));
+    assert_eq!(
+        with_budget(u32::MAX, || walker.walk(DEEP + 1)),
+        (Some(1), u32::MAX - DEEP / 2 - 1)
+    );
+}
@@ -317,7 +317,7 @@ fn mixed_recursion_sum(n: u64) -> u64 {
     match n {
         0 => 0,
         1 => tailcall::call! { mixed_recursion_sum(0) },
-        _ if n % 2 == 0 => {
+        _ if n.is_multiple_of(2) => {
             let partial = mixed_recursion_sum(n - 1);
             n + partial
         }
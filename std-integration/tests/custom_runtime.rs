@@ -0,0 +1,139 @@
+use std::{cell::Cell, ops::ControlFlow};
+use tailcall::{
+    runtime::{Thunk, Trampoline},
+    tailcall,
+};
+
+#[cfg(miri)]
+const DEEP: u32 = 1_000;
+
+#[cfg(not(miri))]
+const DEEP: u32 = 100_000;
+
+thread_local! {
+    static BOUNCES: Cell<u32> = const { Cell::new(0) };
+    static LIMIT: Cell<u32> = const { Cell::new(u32::MAX) };
+}
+
+// An instrumented runtime that counts the bounces it creates.
+struct Counted<'a, T>(Thunk<'a, T>);
+
+impl<'a, T> Trampoline<'a> for Counted<'a, T> {
+    type Output = T;
+
+    fn value(value: T) -> Self {
+        Counted(Thunk::value(value))
+    }
+
+    fn bounce<F>(fn_once: F) -> Self
+    where
+        F: FnOnce() -> Self + 'a,
+    {
+        BOUNCES.with(|bounces| bounces.set(bounces.get() + 1));
+        Counted(Thunk::bounce(move || fn_once().0))
+    }
+
+    fn run(self) -> T {
+        self.0.call()
+    }
+}
+
+mod runtimes {
+    pub(crate) use super::Counted;
+}
+
+fn bounces() -> u32 {
+    BOUNCES.with(|bounces| bounces.replace(0))
+}
+
+fn within_limit() -> ControlFlow<Option<bool>> {
+    LIMIT.with(|limit| match limit.get() {
+        0 => ControlFlow::Break(None),
+        left => {
+            limit.set(left - 1);
+            ControlFlow::Continue(())
+        }
+    })
+}
+
+#[tailcall(runtime = Counted)]
+fn is_even(x: u32) -> bool {
+    if x == 0 {
+        true
+    } else {
+        tailcall::call! { is_odd(x - 1) }
+    }
+}
+
+#[tailcall(runtime = runtimes::Counted)]
+fn is_odd(x: u32) -> bool {
+    if x == 0 {
+        false
+    } else {
+        tailcall::call! { is_even(x - 1) }
+    }
+}
+
+#[tailcall(runtime = Counted, check = within_limit)]
+fn checked_is_even(x: u32) -> Option<bool> {
+    if x == 0 {
+        Some(true)
+    } else {
+        tailcall::call! { checked_is_odd(x - 1) }
+    }
+}
+
+#[tailcall(runtime = Counted, check = within_limit)]
+fn checked_is_odd(x: u32) -> Option<bool> {
+    if x == 0 {
+        Some(false)
+    } else {
+        tailcall::call! { checked_is_even(x - 1) }
+    }
+}
+
+struct Walker {
+    stride: u32,
+}
+
+impl Walker {
+    #[tailcall(runtime = Counted)]
+    fn walk(&self, n: u32) -> u32 {
+        if n < self.stride {
+            n
+        } else {
+            tailcall::call! { self.walk_back(n - self.stride) }
+        }
+    }
+
+    #[tailcall(runtime = Counted)]
+    fn walk_back(&self, n: u32) -> u32 {
+        tailcall::call! { self.walk(n) }
+    }
+}
+
+#[test]
+fn custom_runtime_drives_mutual_recursion() {
+    bounces();
+
+    assert!(is_even(DEEP));
+    assert!(is_odd(DEEP + 1));
+    assert_eq!(bounces(), 2 * (DEEP + 1) + 1);
+}
+
+#[test]
+fn custom_runtime_supports_methods() {
+    bounces();
+
+    assert_eq!(Walker { stride: 3 }.walk(10), 1);
+    assert_eq!(bounces(), 7);
+}
+
+#[test]
+fn custom_runtime_supports_checks() {
+    LIMIT.with(|limit| limit.set(10));
+    assert_eq!(checked_is_even(DEEP), None);
+
+    LIMIT.with(|limit| limit.set(u32::MAX));
+    assert_eq!(checked_is_even(10), Some(true));
+}
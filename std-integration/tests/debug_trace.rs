@@ -0,0 +1,92 @@
+#![cfg(feature = "debug-trace")]
+
+use std::panic;
+use tailcall::{tailcall, trace};
+
+#[tailcall]
+fn countdown(n: u32) -> u32 {
+    if n == 0 {
+        0
+    } else {
+        tailcall::call! { countdown(n - 1) }
+    }
+}
+
+#[tailcall]
+fn is_even(n: u32) -> bool {
+    if n == 0 {
+        true
+    } else {
+        tailcall::call! { is_odd(n - 1) }
+    }
+}
+
+#[tailcall]
+fn is_odd(n: u32) -> bool {
+    if n == 0 {
+        false
+    } else {
+        tailcall::call! { is_even(n - 1) }
+    }
+}
+
+fn explode() -> u32 {
+    panic!("exploded")
+}
+
+#[tailcall]
+fn explode_after(n: u32) -> u32 {
+    if n == 0 {
+        explode()
+    } else {
+        tailcall::call! { explode_after(n - 1) }
+    }
+}
+
+fn function_names() -> Vec<&'static str> {
+    trace::last_calls()
+        .iter()
+        .map(|call| call.function.rsplit("::").next().unwrap_or_default())
+        .collect()
+}
+
+#[test]
+fn loop_lowered_functions_record_each_iteration() {
+    trace::clear();
+
+    countdown(3);
+
+    let calls = trace::last_calls();
+    assert_eq!(function_names(), ["countdown"; 4]);
+    assert_eq!(calls[0].function, "debug_trace::countdown");
+    assert!(calls[0].file.ends_with("debug_trace.rs"));
+}
+
+#[test]
+fn runtime_backed_functions_record_each_bounce() {
+    trace::clear();
+
+    assert!(!is_even(3));
+
+    assert_eq!(function_names(), ["is_even", "is_odd", "is_even", "is_odd"]);
+}
+
+#[test]
+fn only_the_most_recent_calls_are_kept() {
+    trace::clear();
+
+    countdown(100_000);
+
+    assert_eq!(trace::last_calls().len(), trace::CAPACITY);
+}
+
+#[test]
+fn recorded_calls_survive_a_panic() {
+    trace::clear();
+    trace::install_panic_hook();
+
+    let result = panic::catch_unwind(|| explode_after(2));
+
+    assert!(result.is_err());
+    assert_eq!(function_names(), ["explode_after"; 3]);
+}
@@ -0,0 +1,188 @@
+use std::cell::RefCell;
+use tailcall::tailcall;
+
+thread_local! {
+    static EVENTS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+fn record(event: String) {
+    EVENTS.with(|events| events.borrow_mut().push(event));
+}
+
+fn take_events() -> Vec<String> {
+    EVENTS.with(|events| events.take())
+}
+
+struct Noisy(String);
+
+impl Noisy {
+    // Boxed, so a pending call's arguments fit in the inline thunk slot.
+    fn new(name: String) -> Box<Self> {
+        record(format!("make {name}"));
+        Box::new(Noisy(name))
+    }
+
+    // Builds the next argument while `_temp` is still alive as a temporary of the call site.
+    fn after(_temp: &Noisy, name: String) -> Box<Self> {
+        Noisy::new(name)
+    }
+}
+
+impl Drop for Noisy {
+    fn drop(&mut self) {
+        record(format!("drop {}", self.0));
+    }
+}
+
+#[tailcall]
+fn looped(n: u32, token: Box<Noisy>) -> u32 {
+    record(format!("enter {n}"));
+    let _local = Noisy::new(format!("local{n}"));
+
+    if n == 0 {
+        return n;
+    }
+
+    tailcall::call! {
+        looped(n - 1, Noisy::after(&Noisy::new(format!("temp{n}")), format!("param{}", n - 1)))
+    }
+}
+
+#[tailcall]
+fn ping(n: u32, token: Box<Noisy>) -> u32 {
+    record(format!("enter {n}"));
+    let _local = Noisy::new(format!("local{n}"));
+
+    if n == 0 {
+        return n;
+    }
+
+    tailcall::call! {
+        pong(n - 1, Noisy::after(&Noisy::new(format!("temp{n}")), format!("param{}", n - 1)))
+    }
+}
+
+#[tailcall]
+fn pong(n: u32, token: Box<Noisy>) -> u32 {
+    record(format!("enter {n}"));
+    let _local = Noisy::new(format!("local{n}"));
+
+    if n == 0 {
+        return n;
+    }
+
+    tailcall::call! {
+        ping(n - 1, Noisy::after(&Noisy::new(format!("temp{n}")), format!("param{}", n - 1)))
+    }
+}
+
+fn expected_events() -> Vec<&'static str> {
+    vec![
+        "make param1",
+        "enter 1",
+        "make local1",
+        "make temp1",
+        "make param0",
+        "drop temp1",
+        "drop local1",
+        "drop param1",
+        "enter 0",
+        "make local0",
+        "drop local0",
+        "drop param0",
+    ]
+}
+
+#[test]
+fn loop_lowered_calls_drop_locals_before_the_next_call() {
+    take_events();
+
+    assert_eq!(looped(1, Noisy::new("param1".to_owned())), 0);
+    assert_eq!(take_events(), expected_events());
+}
+
+#[test]
+fn runtime_backed_calls_drop_locals_before_the_next_call() {
+    take_events();
+
+    assert_eq!(ping(1, Noisy::new("param1".to_owned())), 0);
+    assert_eq!(take_events(), expected_events());
+}
+
+// Not loop-lowered itself, since it only calls `looped`, which runs its loop as soon as it is
+// called.
+#[tailcall]
+fn into_loop(n: u32, token: Box<Noisy>) -> u32 {
+    record(format!("enter outer {n}"));
+    let _local = Noisy::new(format!("outer local{n}"));
+
+    tailcall::call! { looped(n, Noisy::new(format!("param{n}"))) }
+}
+
+#[tailcall(hybrid)]
+fn hybrid_ping(n: u32, token: Noisy) -> u32 {
+    record(format!("enter {n}"));
+    let _local = Noisy::new(format!("local{n}"));
+
+    if n == 0 {
+        return n;
+    }
+
+    tailcall::call! { hybrid_pong(n - 1, *Noisy::new(format!("param{}", n - 1))) }
+}
+
+#[tailcall(hybrid)]
+fn hybrid_pong(n: u32, token: Noisy) -> u32 {
+    record(format!("enter {n}"));
+    let _local = Noisy::new(format!("local{n}"));
+
+    if n == 0 {
+        return n;
+    }
+
+    tailcall::call! { hybrid_ping(n - 1, *Noisy::new(format!("param{}", n - 1))) }
+}
+
+#[test]
+fn calls_into_loop_lowered_functions_run_before_the_caller_drops_its_locals() {
+    take_events();
+
+    assert_eq!(into_loop(0, Noisy::new("outer".to_owned())), 0);
+    assert_eq!(
+        take_events(),
+        [
+            "make outer",
+            "enter outer 0",
+            "make outer local0",
+            "make param0",
+            "enter 0",
+            "make local0",
+            "drop local0",
+            "drop param0",
+            "drop outer local0",
+            "drop outer",
+        ]
+    );
+}
+
+#[test]
+fn hybrid_native_calls_run_before_the_caller_drops_its_locals() {
+    take_events();
+
+    assert_eq!(hybrid_ping(1, *Noisy::new("param1".to_owned())), 0);
+    assert_eq!(
+        take_events(),
+        [
+            "make param1",
+            "enter 1",
+            "make local1",
+            "make param0",
+            "enter 0",
+            "make local0",
+            "drop local0",
+            "drop param0",
+            "drop local1",
+            "drop param1",
+        ]
+    );
+}
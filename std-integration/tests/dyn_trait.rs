@@ -0,0 +1,153 @@
+use std::cell::Cell;
+
+use tailcall::tailcall;
+
+#[cfg(miri)]
+const DEEP: u32 = 1_000;
+
+#[cfg(not(miri))]
+const DEEP: u32 = 1_000_000;
+
+#[derive(Default)]
+struct Env {
+    visited: Cell<u32>,
+    sum: Cell<i64>,
+}
+
+// An evaluator whose nodes hand evaluation on to their child through `dyn Node`.
+#[tailcall]
+trait Node {
+    #[tailcall]
+    fn eval(&self, env: &Env) -> i64;
+
+    // A provided method goes through the runtime just the same.
+    #[tailcall]
+    fn eval_fresh(&self, env: &Env) -> i64 {
+        env.sum.set(0);
+        tailcall::call! { self.eval(env) }
+    }
+
+    // Lets a chain be dropped without recursing through every box.
+    fn take_child(&mut self) -> Option<Box<dyn Node>>;
+}
+
+struct Lit(i64);
+
+struct Add {
+    value: i64,
+    child: Option<Box<dyn Node>>,
+}
+
+struct Neg {
+    child: Option<Box<dyn Node>>,
+}
+
+#[tailcall]
+impl Node for Lit {
+    #[tailcall]
+    fn eval(&self, env: &Env) -> i64 {
+        env.visited.set(env.visited.get() + 1);
+        env.sum.get() + self.0
+    }
+
+    fn take_child(&mut self) -> Option<Box<dyn Node>> {
+        None
+    }
+}
+
+#[tailcall]
+impl Node for Add {
+    #[tailcall]
+    fn eval(&self, env: &Env) -> i64 {
+        env.visited.set(env.visited.get() + 1);
+        env.sum.set(env.sum.get() + self.value);
+
+        match &self.child {
+            Some(child) => tailcall::call! { child.eval(env) },
+            None => env.sum.get(),
+        }
+    }
+
+    fn take_child(&mut self) -> Option<Box<dyn Node>> {
+        self.child.take()
+    }
+}
+
+#[tailcall]
+impl Node for Neg {
+    #[tailcall]
+    fn eval(&self, env: &Env) -> i64 {
+        env.visited.set(env.visited.get() + 1);
+        env.sum.set(-env.sum.get());
+
+        match &self.child {
+            Some(child) => tailcall::call! { child.eval(env) },
+            None => env.sum.get(),
+        }
+    }
+
+    fn take_child(&mut self) -> Option<Box<dyn Node>> {
+        self.child.take()
+    }
+}
+
+// `Add(1)`, `Add(2)` and `Neg` nodes in turn, ending in `Lit(leaf)`.
+fn chain(len: u32, leaf: i64) -> Box<dyn Node> {
+    (0..len).fold(Box::new(Lit(leaf)), |child, index| {
+        let child = Some(child);
+
+        match index % 3 {
+            0 => Box::new(Add { value: 1, child }),
+            1 => Box::new(Add { value: 2, child }),
+            _ => Box::new(Neg { child }),
+        }
+    })
+}
+
+fn dismantle(mut node: Box<dyn Node>) {
+    while let Some(child) = node.take_child() {
+        node = child;
+    }
+}
+
+// The same evaluation as a plain loop over the nodes `chain` builds, outermost first.
+fn expected(len: u32, leaf: i64) -> i64 {
+    (0..len).rev().fold(0, |sum, index| match index % 3 {
+        0 => sum + 1,
+        1 => sum + 2,
+        _ => -sum,
+    }) + leaf
+}
+
+#[test]
+fn heterogeneous_chains_run_through_dyn_calls() {
+    let root = chain(DEEP, 7);
+    let env = Env::default();
+
+    assert_eq!(root.eval(&env), expected(DEEP, 7));
+    assert_eq!(env.visited.get(), DEEP + 1);
+
+    dismantle(root);
+}
+
+#[test]
+fn provided_methods_run_through_dyn_calls() {
+    let root = chain(DEEP, -3);
+    let env = Env::default();
+    env.sum.set(100);
+
+    assert_eq!(root.eval_fresh(&env), expected(DEEP, -3));
+
+    dismantle(root);
+}
+
+#[test]
+fn static_dispatch_uses_the_same_helpers() {
+    fn eval_all<N: Node>(nodes: &[N], env: &Env) -> i64 {
+        nodes.iter().map(|node| node.eval(env)).sum()
+    }
+
+    let env = Env::default();
+
+    assert_eq!(eval_all(&[Lit(1), Lit(2)], &env), 3);
+}
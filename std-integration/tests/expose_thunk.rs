@@ -0,0 +1,97 @@
+use tailcall::{runtime::Thunk, tailcall};
+
+#[cfg(miri)]
+const DEEP: u32 = 1_000;
+
+#[cfg(not(miri))]
+const DEEP: u32 = 100_000;
+
+#[tailcall(expose_thunk)]
+pub fn is_even(n: u32) -> bool {
+    if n == 0 {
+        true
+    } else {
+        tailcall::call! { is_odd(n - 1) }
+    }
+}
+
+#[tailcall(expose_thunk)]
+pub fn is_odd(n: u32) -> bool {
+    if n == 0 {
+        false
+    } else {
+        tailcall::call! { is_even(n - 1) }
+    }
+}
+
+// A hand-written builder that takes two steps at a time and hands back to the generated helpers.
+fn is_even_by_twos(n: u32) -> Thunk<'static, bool> {
+    Thunk::bounce(move || match n {
+        0 | 1 => is_even_thunk(n),
+        _ => is_odd_thunk(n - 1),
+    })
+}
+
+#[tailcall(expose_thunk)]
+fn r#type(n: u32, acc: u32) -> u32 {
+    if n == 0 {
+        acc
+    } else {
+        tailcall::call! { r#type(n - 1, acc + 1) }
+    }
+}
+
+struct Counter {
+    step: u32,
+}
+
+#[tailcall]
+impl Counter {
+    #[tailcall(expose_thunk)]
+    fn up(&self, n: u32, acc: u32) -> u32 {
+        if n == 0 {
+            acc
+        } else {
+            tailcall::call! { self.down(n - 1, acc + self.step) }
+        }
+    }
+
+    #[tailcall]
+    fn down(&self, n: u32, acc: u32) -> u32 {
+        if n == 0 {
+            acc
+        } else {
+            tailcall::call! { self.up(n - 1, acc + self.step) }
+        }
+    }
+}
+
+#[test]
+fn exposed_thunks_resolve_like_the_wrapper() {
+    assert!(is_even_thunk(DEEP).call());
+    assert!(!is_odd_thunk(DEEP).call());
+}
+
+#[test]
+fn hand_written_builders_return_exposed_thunks_in_tail_position() {
+    assert!(is_even_by_twos(DEEP).call());
+    assert!(!is_even_by_twos(DEEP + 1).call());
+}
+
+#[test]
+fn exposed_thunks_can_be_stepped() {
+    assert_eq!(is_even_thunk(3).into_steps().count(), 5);
+}
+
+#[test]
+fn raw_identifiers_get_plain_thunk_names() {
+    assert_eq!(r#type(DEEP, 0), DEEP);
+    assert_eq!(type_thunk(DEEP, 0).call(), DEEP);
+}
+
+#[test]
+fn methods_expose_thunks() {
+    let counter = Counter { step: 2 };
+
+    assert_eq!(counter.up_thunk(DEEP, 0).call(), 2 * DEEP);
+}
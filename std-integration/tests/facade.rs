@@ -0,0 +1,119 @@
+// An empty module shadows the `tailcall` crate, as in a crate that only depends on a facade, so
+// every path the macro generates has to go through the `crate = ...` option.
+mod tailcall {}
+
+mod facade {
+    pub use ::tailcall::*;
+}
+
+mod rpc {
+    // An unrelated macro that happens to be called `call!`.
+    macro_rules! call {
+        ($value:expr) => {
+            $value + 1
+        };
+    }
+
+    pub(crate) use call;
+}
+
+use facade::call as tail;
+
+#[cfg(miri)]
+const DEEP: u32 = 1_000;
+
+#[cfg(not(miri))]
+const DEEP: u32 = 100_000;
+
+#[facade::tailcall(crate = facade)]
+fn is_even(n: u32) -> bool {
+    if n == 0 {
+        true
+    } else {
+        facade::call! { is_odd(n - 1) }
+    }
+}
+
+#[facade::tailcall(crate = facade)]
+fn is_odd(n: u32) -> bool {
+    if n == 0 {
+        false
+    } else {
+        facade::call! { is_even(n - 1) }
+    }
+}
+
+#[facade::tailcall(crate = facade, call_macro = tail)]
+fn countdown(n: u32) -> u32 {
+    if n == 0 {
+        0
+    } else {
+        tail! { countdown(n - 1) }
+    }
+}
+
+#[facade::tailcall(crate = facade, call_macro = tail)]
+fn ping(n: u32) -> u32 {
+    if n == 0 {
+        0
+    } else {
+        tail! { pong(n - 1) }
+    }
+}
+
+#[facade::tailcall(crate = facade, call_macro = tail)]
+fn pong(n: u32) -> u32 {
+    match n {
+        0 => 1,
+        _ => tail! { ping(n - 1) },
+    }
+}
+
+#[facade::tailcall(crate = facade, call_macro = facade::call)]
+fn count_replies(n: u32, replies: u32) -> u32 {
+    if n == 0 {
+        replies
+    } else {
+        let reply = rpc::call!(replies);
+        facade::call! { count_replies(n - 1, reply) }
+    }
+}
+
+// A facade's own macros can hand their `$crate` to the attribute.
+macro_rules! stack_safe_countdown {
+    ($name:ident) => {
+        #[$crate::facade::tailcall(crate = $crate::facade)]
+        fn $name(n: u32, steps: u32) -> u32 {
+            if n == 0 {
+                steps
+            } else {
+                $crate::facade::call! { $name(n - 1, steps + 1) }
+            }
+        }
+    };
+}
+
+stack_safe_countdown!(countdown_steps);
+
+#[test]
+fn runtime_paths_go_through_the_crate_option() {
+    assert!(is_even(DEEP));
+    assert!(!is_odd(DEEP));
+}
+
+#[test]
+fn renamed_call_macros_are_recognized() {
+    assert_eq!(countdown(DEEP), 0);
+    assert_eq!(ping(DEEP), 0);
+    assert_eq!(pong(DEEP), 1);
+}
+
+#[test]
+fn other_call_macros_are_left_alone() {
+    assert_eq!(count_replies(DEEP, 0), DEEP);
+}
+
+#[test]
+fn crate_paths_from_macro_rules_resolve() {
+    assert_eq!(countdown_steps(DEEP, 0), DEEP);
+}
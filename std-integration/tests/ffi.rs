@@ -0,0 +1,147 @@
+#![deny(unsafe_op_in_unsafe_fn, unused_unsafe)]
+
+use tailcall::tailcall;
+
+#[cfg(miri)]
+const DEEP: u32 = 1_000;
+
+#[cfg(not(miri))]
+const DEEP: u32 = 100_000;
+
+#[tailcall]
+extern "C" fn c_countdown(n: u32, acc: u32) -> u32 {
+    if n == 0 {
+        acc
+    } else {
+        tailcall::call! { c_countdown(n - 1, acc + 1) }
+    }
+}
+
+#[tailcall]
+extern "C" fn c_is_even(n: u32) -> bool {
+    if n == 0 {
+        true
+    } else {
+        tailcall::call! { c_is_odd(n - 1) }
+    }
+}
+
+#[tailcall]
+extern "C" fn c_is_odd(n: u32) -> bool {
+    if n == 0 {
+        false
+    } else {
+        tailcall::call! { c_is_even(n - 1) }
+    }
+}
+
+#[test]
+fn extern_c_wrappers_keep_their_abi() {
+    let countdown: extern "C" fn(u32, u32) -> u32 = c_countdown;
+    let is_even: extern "C" fn(u32) -> bool = c_is_even;
+
+    assert_eq!(countdown(DEEP, 0), DEEP);
+    assert!(is_even(DEEP));
+}
+
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` consecutive values.
+#[tailcall]
+unsafe fn sum_raw(ptr: *const u32, len: u32, acc: u32) -> u32 {
+    if len == 0 {
+        acc
+    } else {
+        // SAFETY: `len > 0`, so `ptr` points at a readable value and `ptr.add(1)` stays within
+        // (or one past) the same allocation.
+        unsafe {
+            tailcall::call! { sum_raw(ptr.add(1), len - 1, acc + *ptr) }
+        }
+    }
+}
+
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` consecutive values.
+#[tailcall]
+unsafe fn sum_even_positions(ptr: *const u32, len: u32, acc: u32) -> u32 {
+    if len == 0 {
+        acc
+    } else {
+        // SAFETY: as in `sum_raw`.
+        let value = unsafe { *ptr };
+        unsafe {
+            tailcall::call! { skip_odd_position(ptr.add(1), len - 1, acc + value) }
+        }
+    }
+}
+
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` consecutive values.
+#[tailcall]
+unsafe fn skip_odd_position(ptr: *const u32, len: u32, acc: u32) -> u32 {
+    if len == 0 {
+        acc
+    } else {
+        // SAFETY: as in `sum_raw`.
+        unsafe {
+            tailcall::call! { sum_even_positions(ptr.add(1), len - 1, acc) }
+        }
+    }
+}
+
+#[test]
+fn unsafe_functions_are_loop_lowered() {
+    let values = vec![1; DEEP as usize];
+
+    // SAFETY: `values` holds `DEEP` readable values.
+    let sum = unsafe { sum_raw(values.as_ptr(), DEEP, 0) };
+
+    assert_eq!(sum, DEEP);
+}
+
+#[test]
+fn unsafe_functions_work_through_the_thunk_runtime() {
+    let values = vec![1; DEEP as usize];
+
+    // SAFETY: `values` holds `DEEP` readable values.
+    let sum = unsafe { sum_even_positions(values.as_ptr(), DEEP, 0) };
+
+    assert_eq!(sum, DEEP / 2);
+}
+
+struct Cursor {
+    step: u32,
+}
+
+impl Cursor {
+    /// # Safety
+    ///
+    /// `ptr` must be valid for reads of `len` consecutive values.
+    #[tailcall]
+    unsafe fn stride_sum(&self, ptr: *const u32, len: u32, acc: u32) -> u32 {
+        if len < self.step {
+            acc
+        } else {
+            // SAFETY: `len >= step`, so the next `step` values are readable.
+            let value = unsafe { *ptr };
+            unsafe {
+                tailcall::call! {
+                    self.stride_sum(ptr.add(self.step as usize), len - self.step, acc + value)
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn unsafe_methods_keep_their_unsafety() {
+    let values = vec![1; DEEP as usize];
+    let cursor = Cursor { step: 2 };
+
+    // SAFETY: `values` holds `DEEP` readable values.
+    let sum = unsafe { cursor.stride_sum(values.as_ptr(), DEEP, 0) };
+
+    assert_eq!(sum, DEEP / 2);
+}
@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+use tailcall::{fix, Thunk};
+
+#[cfg(miri)]
+const DEEP: u32 = 1_000;
+
+#[cfg(not(miri))]
+const DEEP: u32 = 100_000;
+
+#[test]
+fn recursive_closures_handle_deep_inputs() {
+    // `Recurse` is one pointer, which leaves 8 bytes of the default capture budget for arguments.
+    let sum_to = fix(|rec, (n, acc): (u32, u32)| {
+        if n == 0 {
+            Thunk::value(acc)
+        } else {
+            rec.call((n - 1, acc.wrapping_add(n)))
+        }
+    });
+
+    assert_eq!(
+        sum_to((DEEP, 0)),
+        (1..=DEEP).fold(0_u32, |acc, n| acc.wrapping_add(n))
+    );
+}
+
+#[test]
+fn recursive_closures_capture_large_environments_once() {
+    // Far larger than the inline capture budget, but only borrowed by the closure.
+    let next: HashMap<u32, u32> = (0..DEEP).map(|n| (n, n + 1)).collect();
+    let labels = [String::from("start"), String::from("end")];
+
+    let follow = fix(|rec, (node, hops): (u32, u32)| match next.get(&node) {
+        Some(&node) => rec.call((node, hops + 1)),
+        None => Thunk::value((labels[1].as_str(), hops)),
+    });
+
+    assert_eq!(follow((0, 0)), ("end", DEEP));
+    assert_eq!(follow((DEEP - 1, 0)), ("end", 1));
+    assert_eq!(labels[0], "start");
+}
+
+#[test]
+fn recursive_closures_can_be_called_repeatedly() {
+    let collatz_steps = fix(|rec, (n, steps): (u32, u32)| match n {
+        1 => Thunk::value(steps),
+        n if n % 2 == 0 => rec.call((n / 2, steps + 1)),
+        n => rec.call((3 * n + 1, steps + 1)),
+    });
+
+    assert_eq!(collatz_steps((1, 0)), 0);
+    assert_eq!(collatz_steps((6, 0)), 8);
+    assert_eq!(collatz_steps((27, 0)), 111);
+}
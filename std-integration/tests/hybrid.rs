@@ -0,0 +1,82 @@
+use tailcall::tailcall;
+
+#[cfg(miri)]
+const DEEP: u32 = 1_000;
+
+#[cfg(not(miri))]
+const DEEP: u32 = 100_000;
+
+#[tailcall(hybrid)]
+fn is_even(x: u32) -> bool {
+    if x == 0 {
+        true
+    } else {
+        tailcall::call! { is_odd(x - 1) }
+    }
+}
+
+#[tailcall(hybrid = 8)]
+fn is_odd(x: u32) -> bool {
+    if x == 0 {
+        false
+    } else {
+        tailcall::call! { is_even(x - 1) }
+    }
+}
+
+// A zero depth never calls natively, which is the same as the plain runtime.
+#[tailcall(hybrid = 0)]
+fn count_down(x: u32) -> u32 {
+    if x == 0 {
+        0
+    } else {
+        tailcall::call! { count_down_again(x - 1) }
+    }
+}
+
+#[tailcall(hybrid = 0)]
+fn count_down_again(x: u32) -> u32 {
+    tailcall::call! { count_down(x) }
+}
+
+struct Walker {
+    stride: u32,
+}
+
+#[tailcall]
+impl Walker {
+    #[tailcall(hybrid)]
+    fn walk(&self, n: u32) -> u32 {
+        if n < self.stride {
+            n
+        } else {
+            tailcall::call! { self.walk_back(n - self.stride) }
+        }
+    }
+
+    #[tailcall(hybrid)]
+    fn walk_back(&self, n: u32) -> u32 {
+        tailcall::call! { self.walk(n) }
+    }
+}
+
+#[test]
+fn hybrid_functions_stay_stack_safe_past_their_depth() {
+    assert!(is_even(0));
+    assert!(is_odd(7));
+    assert!(is_even(DEEP));
+    assert!(is_odd(DEEP + 1));
+}
+
+#[test]
+fn hybrid_functions_work_with_a_zero_depth() {
+    assert_eq!(count_down(DEEP), 0);
+}
+
+#[test]
+fn hybrid_methods_stay_stack_safe() {
+    let walker = Walker { stride: 3 };
+
+    assert_eq!(walker.walk(10), 1);
+    assert_eq!(walker.walk(DEEP), DEEP % 3);
+}
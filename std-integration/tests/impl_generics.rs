@@ -0,0 +1,113 @@
+use tailcall::tailcall;
+
+#[cfg(miri)]
+const DEEP: usize = 1_000;
+
+#[cfg(not(miri))]
+const DEEP: usize = 100_000;
+
+// A generic type that borrows its data, whose methods run on the runtime through mutual recursion.
+struct Cursor<'a, T> {
+    items: &'a Vec<T>,
+}
+
+#[tailcall]
+impl<'a, T: PartialEq> Cursor<'a, T> {
+    // Without a receiver, nothing but the impl block's generics ties `'a` and `T` to the thunk.
+    #[tailcall]
+    fn even_len(items: &'a Vec<T>, index: usize) -> bool {
+        if index == items.len() {
+            true
+        } else {
+            tailcall::call! { Self::odd_len(items, index + 1) }
+        }
+    }
+
+    #[tailcall]
+    fn odd_len(items: &'a Vec<T>, index: usize) -> bool {
+        if index == items.len() {
+            false
+        } else {
+            tailcall::call! { Self::even_len(items, index + 1) }
+        }
+    }
+
+    #[tailcall]
+    fn count_from(&self, index: u32, found: u32) -> u32 {
+        match self.items.get(index as usize) {
+            Some(item) if *item == self.items[0] => {
+                tailcall::call! { self.skip_from(index + 1, found + 1) }
+            }
+            Some(_) => tailcall::call! { self.skip_from(index + 1, found) },
+            None => found,
+        }
+    }
+
+    #[tailcall]
+    fn skip_from(&self, index: u32, found: u32) -> u32 {
+        match self.items.get(index as usize) {
+            Some(_) => tailcall::call! { self.count_from(index + 1, found) },
+            None => found,
+        }
+    }
+
+    #[tailcall]
+    fn last(items: &'a Vec<T>, index: usize) -> Option<&'a T> {
+        match items.get(index + 1) {
+            Some(_) => tailcall::call! { Self::last_again(items, index + 1) },
+            None => items.get(index),
+        }
+    }
+
+    #[tailcall]
+    fn last_again(items: &'a Vec<T>, index: usize) -> Option<&'a T> {
+        tailcall::call! { Self::last(items, index) }
+    }
+}
+
+struct Tagged<T> {
+    tag: T,
+}
+
+#[tailcall]
+impl<T: Copy> Tagged<T> {
+    #[tailcall]
+    fn ping(n: u32, tag: T) -> Tagged<T> {
+        if n == 0 {
+            Tagged { tag }
+        } else {
+            tailcall::call! { Self::pong(n - 1, tag) }
+        }
+    }
+
+    #[tailcall]
+    fn pong(n: u32, tag: T) -> Tagged<T> {
+        if n == 0 {
+            Tagged { tag }
+        } else {
+            tailcall::call! { Self::ping(n - 1, tag) }
+        }
+    }
+}
+
+#[test]
+fn associated_functions_of_borrowing_generic_types_run() {
+    let items = vec![1_u8; DEEP];
+
+    assert!(Cursor::even_len(&items, 0));
+    assert!(!Cursor::odd_len(&items, 0));
+    assert_eq!(Cursor::last(&items, 0), Some(&1));
+}
+
+#[test]
+fn methods_of_borrowing_generic_types_run() {
+    let items: Vec<u8> = (0..DEEP).map(|index| (index % 4) as u8).collect();
+    let cursor = Cursor { items: &items };
+
+    assert_eq!(cursor.count_from(0, 0) as usize, DEEP / 4);
+}
+
+#[test]
+fn associated_functions_of_owning_generic_types_run() {
+    assert_eq!(Tagged::ping(DEEP as u32 + 1, 'x').tag, 'x');
+}
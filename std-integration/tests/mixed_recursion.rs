@@ -0,0 +1,128 @@
+use tailcall::tailcall;
+
+#[cfg(miri)]
+const DEEP: u32 = 1_000;
+
+#[cfg(not(miri))]
+const DEEP: u32 = 100_000;
+
+struct Parser {
+    bytes: Vec<u8>,
+}
+
+#[tailcall]
+impl Parser {
+    fn new(input: &str) -> Self {
+        Self {
+            bytes: input.as_bytes().to_vec(),
+        }
+    }
+
+    #[tailcall]
+    fn sum_digits(&self, pos: u32, total: u32) -> u32 {
+        match self.bytes.get(pos as usize) {
+            Some(byte) if byte.is_ascii_digit() => {
+                tailcall::call! { lex_digit(self, pos, total) }
+            }
+            Some(_) => tailcall::call! { Self::skip_separator(self, pos, total) },
+            None => total,
+        }
+    }
+
+    #[tailcall]
+    fn skip_separator(parser: &Parser, pos: u32, total: u32) -> u32 {
+        tailcall::call! { parser.sum_digits(pos + 1, total) }
+    }
+
+    #[tailcall]
+    fn count_separators(bytes: &[u8], count: u32) -> u32 {
+        match bytes.split_first() {
+            Some((byte, rest)) if byte.is_ascii_digit() => {
+                tailcall::call! { Self::count_separators(rest, count) }
+            }
+            Some((_, rest)) => tailcall::call! { Self::count_separators(rest, count + 1) },
+            None => count,
+        }
+    }
+}
+
+#[tailcall]
+fn lex_digit(parser: &Parser, pos: u32, total: u32) -> u32 {
+    let digit = u32::from(parser.bytes[pos as usize] - b'0');
+
+    tailcall::call! { parser.sum_digits(pos + 1, total + digit) }
+}
+
+#[test]
+fn free_functions_methods_and_associated_functions_share_a_recursion_group() {
+    let parser = Parser::new(&"1,2;3 ".repeat(DEEP as usize));
+
+    assert_eq!(parser.sum_digits(0, 0), 6 * DEEP);
+}
+
+#[test]
+fn associated_functions_recurse_through_self_paths() {
+    let parser = Parser::new(&"1,2;3 ".repeat(DEEP as usize));
+
+    assert_eq!(Parser::count_separators(&parser.bytes, 0), 3 * DEEP);
+}
+
+struct Ping {
+    hits: u32,
+    remaining: u32,
+}
+
+struct Pong {
+    hits: u32,
+}
+
+#[tailcall]
+impl Ping {
+    #[tailcall]
+    fn serve(&mut self, pong: &mut Pong) -> u32 {
+        self.hits += 1;
+
+        if self.remaining == 0 {
+            self.hits + pong.hits
+        } else {
+            self.remaining -= 1;
+            tailcall::call! { rally(pong, self) }
+        }
+    }
+
+    #[tailcall]
+    fn bounce(ping: &mut Ping, pong: &mut Pong) -> u32 {
+        tailcall::call! { ping.serve(pong) }
+    }
+}
+
+impl Pong {
+    #[tailcall]
+    fn serve(&mut self, ping: &mut Ping) -> u32 {
+        self.hits += 1;
+        ping.remaining -= 1;
+
+        if ping.remaining == 0 {
+            ping.hits + self.hits
+        } else {
+            tailcall::call! { Ping::bounce(ping, self) }
+        }
+    }
+}
+
+#[tailcall]
+fn rally(pong: &mut Pong, ping: &mut Ping) -> u32 {
+    tailcall::call! { pong.serve(ping) }
+}
+
+#[test]
+fn methods_on_different_receivers_bounce_through_free_and_associated_functions() {
+    let mut ping = Ping {
+        hits: 0,
+        remaining: DEEP,
+    };
+    let mut pong = Pong { hits: 0 };
+
+    assert_eq!(ping.serve(&mut pong), DEEP);
+    assert_eq!(ping.hits, pong.hits);
+}
@@ -0,0 +1,87 @@
+use tailcall::tailcall;
+
+#[cfg(miri)]
+const DEEP: u32 = 1_000;
+
+#[cfg(not(miri))]
+const DEEP: u32 = 100_000;
+
+struct Node {
+    value: u32,
+    next: Option<Box<Node>>,
+}
+
+impl Node {
+    fn list(len: u32) -> Option<Box<Node>> {
+        (0..len).fold(None, |next, value| Some(Box::new(Node { value, next })))
+    }
+
+    #[tailcall]
+    fn bump_rest(&mut self, bumped: u32) -> u32 {
+        self.value += 1;
+        match &mut self.next {
+            Some(next) => tailcall::call! { Self::bump_rest(next, bumped + 1) },
+            None => bumped + 1,
+        }
+    }
+}
+
+// Dropping a long list recursively would overflow the stack on its own.
+fn drop_list(mut list: Option<Box<Node>>) {
+    while let Some(mut node) = list {
+        list = node.next.take();
+    }
+}
+
+#[tailcall]
+fn bump_all(rest: &mut [u32], bumped: usize) -> usize {
+    if rest.is_empty() {
+        bumped
+    } else {
+        rest[0] += 1;
+        tailcall::call! { bump_all(&mut rest[1..], bumped + 1) }
+    }
+}
+
+#[tailcall]
+fn bump_pairs(rest: &mut [u32], bumped: usize) -> usize {
+    match rest {
+        [first, second, tail @ ..] => {
+            *first += 1;
+            *second += 1;
+            tailcall::call! { bump_pairs(tail, bumped + 2) }
+        }
+        _ => bumped,
+    }
+}
+
+#[tailcall]
+fn bump_links(link: &mut Option<Box<Node>>, bumped: u32) -> u32 {
+    match link {
+        Some(node) => {
+            node.value += 1;
+            tailcall::call! { bump_links(&mut node.next, bumped + 1) }
+        }
+        None => bumped,
+    }
+}
+
+#[test]
+fn slice_reborrow_cursor_walks_are_stack_safe() {
+    let mut values = vec![0; DEEP as usize];
+
+    assert_eq!(bump_all(&mut values, 0), DEEP as usize);
+    assert_eq!(bump_pairs(&mut values, 0), DEEP as usize);
+    assert!(values.iter().all(|&value| value == 2));
+}
+
+#[test]
+fn field_reborrow_cursor_walks_are_stack_safe() {
+    let mut list = Node::list(DEEP);
+
+    assert_eq!(bump_links(&mut list, 0), DEEP);
+    assert_eq!(list.as_mut().map(|head| head.bump_rest(0)), Some(DEEP));
+    assert_eq!(list.as_ref().map(|head| head.value), Some(DEEP + 1));
+
+    drop_list(list);
+}
@@ -0,0 +1,115 @@
+use tailcall::tailcall;
+
+#[cfg(miri)]
+const DEEP: u32 = 1_000;
+
+#[cfg(not(miri))]
+const DEEP: u32 = 100_000;
+
+// The arguments below are larger than the runtime's inline slot, so these functions only run if
+// their tail calls were recognized as self calls and lowered to a loop.
+type Wide = [u64; 4];
+
+// `self::module_qualified` could name a different function than this one if it were nested in
+// another function's body, so the call runs on the runtime and only carries a small argument.
+#[tailcall]
+fn module_qualified(n: u32, total: u64) -> u64 {
+    if n == 0 {
+        return total;
+    }
+
+    tailcall::call! { self::module_qualified(n - 1, total + 1) }
+}
+
+#[tailcall]
+fn turbofish<T: Copy>(n: u32, wide: Wide, value: T) -> (Wide, T) {
+    if n == 0 {
+        return (wide, value);
+    }
+
+    tailcall::call! { turbofish::<T>(n - 1, [wide[0], wide[1] + 1, wide[2], wide[3]], value) }
+}
+
+#[tailcall]
+fn const_turbofish<const N: usize>(n: u32, wide: [u64; N]) -> [u64; N] {
+    if n == 0 {
+        return wide;
+    }
+
+    let mut next = wide;
+    next[N - 1] += 1;
+    tailcall::call! { const_turbofish::<N>(n - 1, next) }
+}
+
+struct Walker {
+    step: u64,
+}
+
+impl Walker {
+    #[tailcall]
+    fn walk<T: Copy>(&self, n: u32, wide: Wide, value: T) -> (Wide, T) {
+        if n == 0 {
+            return (wide, value);
+        }
+
+        tailcall::call! { Self::walk::<T>(self, n - 1, [wide[0] + self.step, wide[1], wide[2], wide[3]], value) }
+    }
+}
+
+// A call that changes the generic arguments is a call to another instantiation, which still runs
+// on the runtime.
+#[tailcall]
+fn settle<T: Copy + Into<u64>>(n: u32, value: T) -> u64 {
+    if n == 0 {
+        return value.into();
+    }
+
+    tailcall::call! { settle::<u64>(n - 1, value.into() + 1) }
+}
+
+mod nested {
+    use tailcall::tailcall;
+
+    #[tailcall]
+    pub fn crate_qualified(n: u32, total: u64) -> u64 {
+        if n == 0 {
+            return total;
+        }
+
+        tailcall::call! { crate::nested::crate_qualified(n - 1, total + 1) }
+    }
+}
+
+#[test]
+fn module_qualified_self_calls_run_on_the_runtime() {
+    assert_eq!(module_qualified(DEEP, 0), u64::from(DEEP));
+}
+
+#[test]
+fn turbofish_self_calls_are_lowered() {
+    assert_eq!(
+        turbofish(DEEP, [0; 4], 'x'),
+        ([0, u64::from(DEEP), 0, 0], 'x')
+    );
+    assert_eq!(const_turbofish(DEEP, [0; 4]), [0, 0, 0, u64::from(DEEP)]);
+}
+
+#[test]
+fn turbofish_self_method_paths_are_lowered() {
+    let walker = Walker { step: 2 };
+
+    assert_eq!(
+        walker.walk(DEEP, [0; 4], 7_u8),
+        ([2 * u64::from(DEEP), 0, 0, 0], 7)
+    );
+}
+
+#[test]
+fn calls_with_changed_generics_run_on_the_runtime() {
+    assert_eq!(settle(DEEP, 1_u8), u64::from(DEEP) + 1);
+}
+
+#[test]
+fn crate_qualified_self_calls_run_on_the_runtime() {
+    assert_eq!(nested::crate_qualified(DEEP, 0), u64::from(DEEP));
+}
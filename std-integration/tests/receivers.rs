@@ -0,0 +1,387 @@
+use std::{cell::RefCell, marker::PhantomPinned, pin::Pin, rc::Rc};
+use tailcall::tailcall;
+
+#[cfg(miri)]
+const DEEP: u32 = 1_000;
+
+#[cfg(not(miri))]
+const DEEP: u32 = 100_000;
+
+struct List {
+    value: u32,
+    next: Option<Box<List>>,
+}
+
+impl List {
+    fn from_range(len: u32) -> Box<Self> {
+        let mut list = Box::new(List {
+            value: 0,
+            next: None,
+        });
+
+        for value in 1..len {
+            list = Box::new(List {
+                value,
+                next: Some(list),
+            });
+        }
+
+        list
+    }
+
+    #[tailcall]
+    fn sum(mut self: Box<Self>, total: u64) -> u64 {
+        let total = total + u64::from(self.value);
+
+        match self.next.take() {
+            Some(next) => {
+                self = next;
+                tailcall::call! { self.sum(total) }
+            }
+            None => total,
+        }
+    }
+
+    #[tailcall]
+    fn count_even(mut self: Box<Self>, count: u32) -> u32 {
+        match self.next.take() {
+            Some(next) => {
+                self = next;
+                tailcall::call! { self.count_odd(count + 1) }
+            }
+            None => count,
+        }
+    }
+
+    #[tailcall]
+    fn count_odd(mut self: Box<Self>, count: u32) -> u32 {
+        match self.next.take() {
+            Some(next) => {
+                self = next;
+                tailcall::call! { self.count_even(count + 1) }
+            }
+            None => count,
+        }
+    }
+}
+
+impl Drop for List {
+    fn drop(&mut self) {
+        let mut next = self.next.take();
+
+        while let Some(mut node) = next {
+            next = node.next.take();
+        }
+    }
+}
+
+#[test]
+fn boxed_receivers_are_loop_lowered() {
+    let expected: u64 = (0..u64::from(DEEP)).sum();
+
+    assert_eq!(List::from_range(DEEP).sum(0), expected);
+}
+
+#[test]
+fn boxed_receivers_work_through_the_thunk_runtime() {
+    assert_eq!(List::from_range(DEEP).count_even(1), DEEP);
+}
+
+struct Countdown {
+    remaining: u32,
+    ticks: u32,
+    _pinned: PhantomPinned,
+}
+
+impl Countdown {
+    fn new(remaining: u32) -> Self {
+        Self {
+            remaining,
+            ticks: 0,
+            _pinned: PhantomPinned,
+        }
+    }
+
+    fn tick(self: Pin<&mut Self>) -> u32 {
+        // SAFETY: No field of `Countdown` is structurally pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        this.remaining -= 1;
+        this.ticks += 1;
+        this.remaining
+    }
+
+    #[tailcall]
+    fn run(mut self: Pin<&mut Self>) -> u32 {
+        if self.remaining == 0 {
+            self.ticks
+        } else {
+            self.as_mut().tick();
+            tailcall::call! { self.run() }
+        }
+    }
+
+    #[tailcall]
+    fn run_even(mut self: Pin<&mut Self>) -> u32 {
+        if self.as_mut().tick() == 0 {
+            self.ticks
+        } else {
+            tailcall::call! { self.run_odd() }
+        }
+    }
+
+    #[tailcall]
+    fn run_odd(mut self: Pin<&mut Self>) -> u32 {
+        if self.as_mut().tick() == 0 {
+            self.ticks
+        } else {
+            tailcall::call! { self.run_even() }
+        }
+    }
+}
+
+#[test]
+fn pinned_receivers_are_loop_lowered() {
+    let mut countdown = Box::pin(Countdown::new(DEEP));
+
+    assert_eq!(countdown.as_mut().run(), DEEP);
+}
+
+#[test]
+fn pinned_receivers_work_through_the_thunk_runtime() {
+    let mut countdown = Box::pin(Countdown::new(DEEP));
+
+    assert_eq!(countdown.as_mut().run_even(), DEEP);
+}
+
+struct Shared {
+    step: u64,
+}
+
+impl Shared {
+    #[tailcall]
+    fn advance(self: Rc<Self>, remaining: u64, total: u64) -> u64 {
+        if remaining == 0 {
+            total
+        } else {
+            let step = self.step;
+            tailcall::call! { self.advance(remaining - 1, total + step) }
+        }
+    }
+
+    #[tailcall]
+    fn ping(self: Rc<Self>, remaining: u64) -> usize {
+        if remaining == 0 {
+            Rc::strong_count(&self)
+        } else {
+            tailcall::call! { self.pong(remaining - 1) }
+        }
+    }
+
+    #[tailcall]
+    fn pong(self: Rc<Self>, remaining: u64) -> usize {
+        if remaining == 0 {
+            Rc::strong_count(&self)
+        } else {
+            tailcall::call! { self.ping(remaining - 1) }
+        }
+    }
+}
+
+#[test]
+fn rc_receivers_are_loop_lowered() {
+    let shared = Rc::new(Shared { step: 3 });
+
+    assert_eq!(shared.advance(u64::from(DEEP), 0), 3 * u64::from(DEEP));
+}
+
+#[test]
+fn rc_receivers_work_through_the_thunk_runtime() {
+    let shared = Rc::new(Shared { step: 3 });
+    let extra = Rc::clone(&shared);
+
+    assert_eq!(shared.ping(u64::from(DEEP)), 2);
+    assert_eq!(Rc::strong_count(&extra), 1);
+}
+
+struct Chain {
+    weight: u64,
+    next: Option<Box<Chain>>,
+}
+
+impl Chain {
+    fn with_len(len: u64) -> Chain {
+        let mut chain = Chain {
+            weight: 0,
+            next: None,
+        };
+
+        for weight in 1..len {
+            chain = Chain {
+                weight,
+                next: Some(Box::new(chain)),
+            };
+        }
+
+        chain
+    }
+
+    #[tailcall]
+    fn total(&self, acc: u64) -> u64 {
+        match &self.next {
+            Some(next) => tailcall::call! { Self::total(next, acc + self.weight) },
+            None => acc + self.weight,
+        }
+    }
+
+    #[tailcall]
+    fn total_via_methods(&self, acc: u64) -> u64 {
+        match &self.next {
+            Some(next) => tailcall::call! { next.total_via_methods(acc + self.weight) },
+            None => acc + self.weight,
+        }
+    }
+
+    #[tailcall]
+    fn bump_all(&mut self, by: u64) -> u64 {
+        self.weight += by;
+
+        match &mut self.next {
+            Some(next) => tailcall::call! { Self::bump_all(next, by) },
+            None => self.weight,
+        }
+    }
+
+    #[tailcall]
+    fn last_weight_even(&self) -> u64 {
+        match &self.next {
+            Some(next) => tailcall::call! { next.last_weight_odd() },
+            None => self.weight,
+        }
+    }
+
+    #[tailcall]
+    fn last_weight_odd(&self) -> u64 {
+        match &self.next {
+            Some(next) => tailcall::call! { Self::last_weight_even(next) },
+            None => self.weight,
+        }
+    }
+}
+
+impl Drop for Chain {
+    fn drop(&mut self) {
+        let mut next = self.next.take();
+
+        while let Some(mut node) = next {
+            next = node.next.take();
+        }
+    }
+}
+
+#[test]
+fn associated_path_tail_calls_walk_other_receivers_in_a_loop() {
+    let chain = Chain::with_len(u64::from(DEEP));
+    let expected: u64 = (0..u64::from(DEEP)).sum();
+
+    assert_eq!(chain.total(0), expected);
+}
+
+#[test]
+fn method_tail_calls_on_other_receivers_use_the_thunk_runtime() {
+    let chain = Chain::with_len(u64::from(DEEP));
+    let expected: u64 = (0..u64::from(DEEP)).sum();
+
+    assert_eq!(chain.total_via_methods(0), expected);
+}
+
+#[test]
+fn mutable_reborrowed_receivers_become_loop_state() {
+    let mut chain = Chain::with_len(u64::from(DEEP));
+
+    assert_eq!(chain.bump_all(2), 2);
+    assert_eq!(chain.weight, u64::from(DEEP) + 1);
+    assert_eq!(
+        chain.total(0),
+        (0..u64::from(DEEP)).sum::<u64>() + 2 * u64::from(DEEP)
+    );
+}
+
+#[test]
+fn mutual_recursion_mixes_method_and_associated_paths() {
+    let chain = Chain::with_len(u64::from(DEEP));
+
+    assert_eq!(chain.last_weight_even(), 0);
+}
+
+thread_local! {
+    static EVALUATED: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+}
+
+fn evaluated<T>(what: &'static str, value: T) -> T {
+    EVALUATED.with(|evaluated| evaluated.borrow_mut().push(what));
+    value
+}
+
+#[derive(Clone, Copy)]
+struct Widener {
+    step: u32,
+}
+
+impl Widener {
+    #[tailcall]
+    fn widen_even<T: Copy + Into<u64>>(self, value: T, n: u32) -> u64 {
+        if n == 0 {
+            value.into()
+        } else {
+            tailcall::call! {
+                evaluated("receiver", self).widen_odd(evaluated("argument", value), n - 1)
+            }
+        }
+    }
+
+    #[tailcall]
+    fn widen_odd<T: Copy + Into<u64>>(self, value: T, n: u32) -> u64 {
+        if n == 0 {
+            value.into() + u64::from(self.step)
+        } else {
+            tailcall::call! { self.widen_even(value, n - 1) }
+        }
+    }
+
+    // Without the turbofish, the literal argument would be an `i32`, which has no `Into<u64>`.
+    #[tailcall]
+    fn size_even<T: Into<u64>>(self, value: T, n: u32) -> u64 {
+        if n == 0 {
+            value.into() * core::mem::size_of::<T>() as u64
+        } else {
+            tailcall::call! { self.size_odd::<u8>(1, n - 1) }
+        }
+    }
+
+    #[tailcall]
+    fn size_odd<T: Into<u64>>(self, value: T, n: u32) -> u64 {
+        if n == 0 {
+            value.into() * core::mem::size_of::<T>() as u64
+        } else {
+            tailcall::call! { self.size_even::<u16>(1, n - 1) }
+        }
+    }
+}
+
+#[test]
+fn method_tail_calls_evaluate_the_receiver_before_the_arguments() {
+    EVALUATED.with(|evaluated| evaluated.take());
+
+    assert_eq!(Widener { step: 1 }.widen_even(7_u8, 1), 8);
+    assert_eq!(
+        EVALUATED.with(|evaluated| evaluated.take()),
+        ["receiver", "argument"]
+    );
+}
+
+#[test]
+fn method_tail_calls_keep_their_turbofish() {
+    assert_eq!(Widener { step: 1 }.size_even(3_u32, 0), 12);
+    assert_eq!(Widener { step: 1 }.size_even(3_u32, DEEP), 2);
+    assert_eq!(Widener { step: 1 }.size_even(3_u32, DEEP + 1), 1);
+}
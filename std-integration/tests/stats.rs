@@ -0,0 +1,57 @@
+#![cfg(feature = "stats")]
+
+use tailcall::{stats, tailcall};
+
+#[tailcall]
+fn countdown(n: u32) -> u32 {
+    if n == 0 {
+        0
+    } else {
+        tailcall::call! { countdown(n - 1) }
+    }
+}
+
+#[tailcall]
+fn is_even(n: u32) -> bool {
+    if n == 0 {
+        true
+    } else {
+        tailcall::call! { is_odd(n - 1) }
+    }
+}
+
+#[tailcall]
+fn is_odd(n: u32) -> bool {
+    if n == 0 {
+        false
+    } else {
+        tailcall::call! { is_even(n - 1) }
+    }
+}
+
+fn calls(function: &str) -> usize {
+    stats::report()
+        .find(|stats| stats.function == function)
+        .map_or(0, |stats| stats.calls)
+}
+
+// Each test uses its own functions because counters are shared by every test in the binary.
+#[test]
+fn loop_lowered_functions_count_each_iteration() {
+    countdown(9);
+
+    let countdown_stats = stats::report()
+        .find(|stats| stats.function == "stats::countdown")
+        .expect("countdown should have been counted");
+
+    assert_eq!(countdown_stats.calls, 10);
+    assert!(countdown_stats.file.ends_with("stats.rs"));
+}
+
+#[test]
+fn runtime_backed_functions_count_each_bounce() {
+    assert!(!is_even(5));
+
+    assert_eq!(calls("stats::is_even"), 3);
+    assert_eq!(calls("stats::is_odd"), 3);
+}
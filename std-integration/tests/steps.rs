@@ -0,0 +1,83 @@
+use tailcall::tailcall;
+
+/// Euclid's algorithm.
+#[tailcall(trace)]
+pub fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        tailcall::call! { gcd(b, a % b) }
+    }
+}
+
+#[tailcall(trace)]
+fn binary_search(items: &[i32], target: i32, lo: usize, hi: usize) -> Option<usize> {
+    if lo >= hi {
+        return None;
+    }
+
+    let mid = lo + (hi - lo) / 2;
+    match items[mid].cmp(&target) {
+        std::cmp::Ordering::Equal => Some(mid),
+        std::cmp::Ordering::Less => tailcall::call! { binary_search(items, target, mid + 1, hi) },
+        std::cmp::Ordering::Greater => tailcall::call! { binary_search(items, target, lo, mid) },
+    }
+}
+
+#[tailcall(trace)]
+fn last<T: Clone>(mut items: Vec<T>, fallback: T) -> T {
+    match items.pop() {
+        Some(item) if items.is_empty() => item,
+        Some(_) => tailcall::call! { last(items, fallback) },
+        None => fallback,
+    }
+}
+
+#[test]
+fn steps_list_each_call_then_the_result() {
+    let steps: Vec<_> = gcd_steps(12, 18)
+        .map(|step| match step {
+            GcdStep::Call { a, b } => (a, b),
+            GcdStep::Done(value) => (value, 0),
+        })
+        .collect();
+
+    assert_eq!(steps, [(12, 18), (18, 12), (12, 6), (6, 0), (6, 0)]);
+    assert_eq!(gcd(12, 18), 6);
+}
+
+#[test]
+fn steps_borrow_arguments_with_elided_lifetimes() {
+    let items = [1, 3, 5, 7, 9, 11];
+    let steps: Vec<_> = binary_search_steps(&items, 9, 0, items.len()).collect();
+
+    assert!(matches!(
+        steps.as_slice(),
+        [
+            BinarySearchStep::Call { lo: 0, hi: 6, .. },
+            BinarySearchStep::Call { lo: 4, hi: 6, .. },
+            BinarySearchStep::Call { lo: 4, hi: 5, .. },
+            BinarySearchStep::Done(Some(4)),
+        ]
+    ));
+}
+
+#[test]
+fn early_returns_end_the_steps() {
+    let items = [1, 3];
+    let last_step = binary_search_steps(&items, 2, 0, items.len()).last();
+
+    assert!(matches!(last_step, Some(BinarySearchStep::Done(None))));
+}
+
+#[test]
+fn generic_functions_have_generic_steps() {
+    let steps: Vec<_> = last_steps(vec!["a", "b", "c"], "none").collect();
+
+    assert_eq!(steps.len(), 4);
+    assert!(matches!(steps[3], LastStep::Done("a")));
+    assert_eq!(
+        format!("{:?}", steps[1]),
+        r#"Call { items: ["a", "b"], fallback: "none" }"#
+    );
+}
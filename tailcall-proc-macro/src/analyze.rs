@@ -1,25 +1,36 @@
+use proc_macro2::TokenStream;
 use syn::{
     parse2,
     visit::{self, Visit},
-    Expr, ExprCall, ExprMacro, ExprMethodCall, ExprPath, FnArg, Ident, ImplItemFn, ItemFn,
-    ItemMacro, Pat, PatIdent, PatType, Path, StmtMacro,
+    Expr, ExprCall, ExprMacro, ExprMethodCall, ExprPath, ExprReturn, FnArg, Generics, Ident,
+    ImplItemFn, ItemFn, ItemMacro, Pat, PatIdent, PatType, Path, StmtMacro,
 };
 
-use crate::call_syntax::is_tailcall_macro;
+use crate::call_syntax::{is_self_function_path, is_self_method_path, CallMacro};
 
-pub fn is_simple_self_tail_recursive(item_fn: &ItemFn) -> bool {
-    let (eligible, saw_self_tailcall) = analyze(item_fn);
+pub fn is_simple_self_tail_recursive(item_fn: &ItemFn, call_macro: &CallMacro) -> bool {
+    let (eligible, saw_self_tailcall) = analyze(item_fn, false, call_macro);
     eligible && saw_self_tailcall
 }
 
-pub fn is_simple_self_tail_recursive_method(method: &ImplItemFn) -> bool {
-    let (eligible, saw_self_tailcall) = analyze_method(method);
+// Associated functions without a receiver recurse through `Self::name(...)`, since a bare `name`
+// would resolve to a free function instead.
+pub fn is_simple_self_tail_recursive_associated(item_fn: &ItemFn, call_macro: &CallMacro) -> bool {
+    let (eligible, saw_self_tailcall) = analyze(item_fn, true, call_macro);
     eligible && saw_self_tailcall
 }
 
-fn analyze(item_fn: &ItemFn) -> (bool, bool) {
+pub fn is_simple_self_tail_recursive_method(method: &ImplItemFn, call_macro: &CallMacro) -> bool {
+    let (eligible, saw_self_tailcall) = analyze_method(method, call_macro);
+    eligible && saw_self_tailcall
+}
+
+fn analyze(item_fn: &ItemFn, associated: bool, call_macro: &CallMacro) -> (bool, bool) {
     let mut analyzer = SelfTailAnalyzer {
+        call_macro,
         fn_ident: &item_fn.sig.ident,
+        generics: &item_fn.sig.generics,
+        associated,
         arg_idents: function_arg_idents(&item_fn.sig.inputs),
         eligible: true,
         saw_self_tailcall: false,
@@ -28,9 +39,11 @@ fn analyze(item_fn: &ItemFn) -> (bool, bool) {
     (analyzer.eligible, analyzer.saw_self_tailcall)
 }
 
-fn analyze_method(method: &ImplItemFn) -> (bool, bool) {
+fn analyze_method(method: &ImplItemFn, call_macro: &CallMacro) -> (bool, bool) {
     let mut analyzer = SelfTailMethodAnalyzer {
+        call_macro,
         method_ident: &method.sig.ident,
+        generics: &method.sig.generics,
         arg_idents: function_arg_idents(&method.sig.inputs),
         eligible: true,
         saw_self_tailcall: false,
@@ -40,14 +53,19 @@ fn analyze_method(method: &ImplItemFn) -> (bool, bool) {
 }
 
 struct SelfTailAnalyzer<'a> {
+    call_macro: &'a CallMacro,
     fn_ident: &'a Ident,
+    generics: &'a Generics,
+    associated: bool,
     arg_idents: Vec<Ident>,
     eligible: bool,
     saw_self_tailcall: bool,
 }
 
 struct SelfTailMethodAnalyzer<'a> {
+    call_macro: &'a CallMacro,
     method_ident: &'a Ident,
+    generics: &'a Generics,
     arg_idents: Vec<Ident>,
     eligible: bool,
     saw_self_tailcall: bool,
@@ -55,7 +73,11 @@ struct SelfTailMethodAnalyzer<'a> {
 
 impl SelfTailAnalyzer<'_> {
     fn is_self_path(&self, path: &Path) -> bool {
-        path.is_ident(self.fn_ident)
+        if self.associated {
+            is_self_method_path(path, self.fn_ident, self.generics)
+        } else {
+            is_self_function_path(path, self.fn_ident, self.generics)
+        }
     }
 
     fn is_argument_ident(&self, ident: &Ident) -> bool {
@@ -68,6 +90,34 @@ impl SelfTailMethodAnalyzer<'_> {
         matches!(expr, Expr::Path(ExprPath { path, .. }) if path.is_ident("self"))
     }
 
+    // Method syntax on any receiver other than `self` may autoref, autoderef, or dispatch through
+    // a trait object, so only `self.method(...)` and `Self::method(receiver, ...)` are treated as
+    // direct self recursion.
+    fn is_self_tailcall(&self, tokens: &TokenStream) -> bool {
+        if let Ok(expr_method_call) = parse2::<ExprMethodCall>(tokens.clone()) {
+            return self.is_self_receiver(&expr_method_call.receiver)
+                && expr_method_call.method == *self.method_ident;
+        }
+
+        match parse2::<ExprCall>(tokens.clone()) {
+            Ok(expr_call) => matches!(
+                &*expr_call.func,
+                Expr::Path(ExprPath { path, .. })
+                    if is_self_method_path(path, self.method_ident, self.generics)
+                        && !expr_call.args.is_empty()
+            ),
+            Err(_) => false,
+        }
+    }
+
+    fn visit_tailcall(&mut self, tokens: &TokenStream) {
+        if self.is_self_tailcall(tokens) {
+            self.saw_self_tailcall = true;
+        } else {
+            self.eligible = false;
+        }
+    }
+
     fn is_argument_ident(&self, ident: &Ident) -> bool {
         self.arg_idents.iter().any(|arg_ident| arg_ident == ident)
     }
@@ -107,7 +157,7 @@ impl<'ast> Visit<'ast> for SelfTailAnalyzer<'_> {
             return;
         }
 
-        if is_tailcall_macro(&item_macro.mac.path) {
+        if self.call_macro.matches(&item_macro.mac.path) {
             match parse2::<ExprCall>(item_macro.mac.tokens.clone()) {
                 Ok(expr_call) => match &*expr_call.func {
                     Expr::Path(ExprPath { path, .. }) if self.is_self_path(path) => {
@@ -128,7 +178,7 @@ impl<'ast> Visit<'ast> for SelfTailAnalyzer<'_> {
             return;
         }
 
-        if is_tailcall_macro(&expr_macro.mac.path) {
+        if self.call_macro.matches(&expr_macro.mac.path) {
             match parse2::<ExprCall>(expr_macro.mac.tokens.clone()) {
                 Ok(expr_call) => match &*expr_call.func {
                     Expr::Path(ExprPath { path, .. }) if self.is_self_path(path) => {
@@ -149,7 +199,7 @@ impl<'ast> Visit<'ast> for SelfTailAnalyzer<'_> {
             return;
         }
 
-        if is_tailcall_macro(&stmt_macro.mac.path) {
+        if self.call_macro.matches(&stmt_macro.mac.path) {
             match parse2::<ExprCall>(stmt_macro.mac.tokens.clone()) {
                 Ok(expr_call) => match &*expr_call.func {
                     Expr::Path(ExprPath { path, .. }) if self.is_self_path(path) => {
@@ -180,6 +230,20 @@ impl<'ast> Visit<'ast> for SelfTailAnalyzer<'_> {
         visit::visit_expr_call(self, expr_call);
     }
 
+    // Loop lowering only handles tail calls in tail position, so a function that returns one
+    // early keeps running on the runtime, which handles any `return`.
+    fn visit_expr_return(&mut self, expr_return: &'ast ExprReturn) {
+        if matches!(
+            expr_return.expr.as_deref(),
+            Some(Expr::Macro(expr_macro)) if self.call_macro.matches(&expr_macro.mac.path)
+        ) {
+            self.eligible = false;
+            return;
+        }
+
+        visit::visit_expr_return(self, expr_return);
+    }
+
     fn visit_expr_closure(&mut self, _expr_closure: &'ast syn::ExprClosure) {}
 
     fn visit_item_fn(&mut self, _item_fn: &'ast ItemFn) {}
@@ -204,16 +268,8 @@ impl<'ast> Visit<'ast> for SelfTailMethodAnalyzer<'_> {
             return;
         }
 
-        if is_tailcall_macro(&item_macro.mac.path) {
-            match parse2::<ExprMethodCall>(item_macro.mac.tokens.clone()) {
-                Ok(expr_method_call)
-                    if self.is_self_receiver(&expr_method_call.receiver)
-                        && expr_method_call.method == *self.method_ident =>
-                {
-                    self.saw_self_tailcall = true;
-                }
-                Ok(_) | Err(_) => self.eligible = false,
-            }
+        if self.call_macro.matches(&item_macro.mac.path) {
+            self.visit_tailcall(&item_macro.mac.tokens);
             return;
         }
 
@@ -225,16 +281,8 @@ impl<'ast> Visit<'ast> for SelfTailMethodAnalyzer<'_> {
             return;
         }
 
-        if is_tailcall_macro(&expr_macro.mac.path) {
-            match parse2::<ExprMethodCall>(expr_macro.mac.tokens.clone()) {
-                Ok(expr_method_call)
-                    if self.is_self_receiver(&expr_method_call.receiver)
-                        && expr_method_call.method == *self.method_ident =>
-                {
-                    self.saw_self_tailcall = true;
-                }
-                Ok(_) | Err(_) => self.eligible = false,
-            }
+        if self.call_macro.matches(&expr_macro.mac.path) {
+            self.visit_tailcall(&expr_macro.mac.tokens);
             return;
         }
 
@@ -246,16 +294,8 @@ impl<'ast> Visit<'ast> for SelfTailMethodAnalyzer<'_> {
             return;
         }
 
-        if is_tailcall_macro(&stmt_macro.mac.path) {
-            match parse2::<ExprMethodCall>(stmt_macro.mac.tokens.clone()) {
-                Ok(expr_method_call)
-                    if self.is_self_receiver(&expr_method_call.receiver)
-                        && expr_method_call.method == *self.method_ident =>
-                {
-                    self.saw_self_tailcall = true;
-                }
-                Ok(_) | Err(_) => self.eligible = false,
-            }
+        if self.call_macro.matches(&stmt_macro.mac.path) {
+            self.visit_tailcall(&stmt_macro.mac.tokens);
             return;
         }
 
@@ -277,6 +317,33 @@ impl<'ast> Visit<'ast> for SelfTailMethodAnalyzer<'_> {
         visit::visit_expr_method_call(self, expr_method_call);
     }
 
+    fn visit_expr_call(&mut self, expr_call: &'ast ExprCall) {
+        if !self.eligible {
+            return;
+        }
+
+        if let Expr::Path(ExprPath { path, .. }) = &*expr_call.func {
+            if is_self_method_path(path, self.method_ident, self.generics) {
+                self.eligible = false;
+                return;
+            }
+        }
+
+        visit::visit_expr_call(self, expr_call);
+    }
+
+    fn visit_expr_return(&mut self, expr_return: &'ast ExprReturn) {
+        if matches!(
+            expr_return.expr.as_deref(),
+            Some(Expr::Macro(expr_macro)) if self.call_macro.matches(&expr_macro.mac.path)
+        ) {
+            self.eligible = false;
+            return;
+        }
+
+        visit::visit_expr_return(self, expr_return);
+    }
+
     fn visit_expr_closure(&mut self, _expr_closure: &'ast syn::ExprClosure) {}
 
     fn visit_item_fn(&mut self, _item_fn: &'ast ItemFn) {}
@@ -288,9 +355,9 @@ mod tests {
 
     use super::{
         analyze, analyze_method, is_simple_self_tail_recursive,
-        is_simple_self_tail_recursive_method,
+        is_simple_self_tail_recursive_associated, is_simple_self_tail_recursive_method,
     };
-    use crate::call_syntax::is_tailcall_macro;
+    use crate::call_syntax::CallMacro;
 
     #[test]
     fn accepts_simple_self_tail_recursion() {
@@ -304,8 +371,14 @@ mod tests {
             }
         };
 
-        assert_eq!(analyze(&item_fn), (true, true));
-        assert!(is_simple_self_tail_recursive(&item_fn));
+        assert_eq!(
+            analyze(&item_fn, false, &CallMacro::default()),
+            (true, true)
+        );
+        assert!(is_simple_self_tail_recursive(
+            &item_fn,
+            &CallMacro::default()
+        ));
     }
 
     #[test]
@@ -320,8 +393,14 @@ mod tests {
             }
         };
 
-        assert_eq!(analyze(&item_fn), (true, true));
-        assert!(is_simple_self_tail_recursive(&item_fn));
+        assert_eq!(
+            analyze(&item_fn, false, &CallMacro::default()),
+            (true, true)
+        );
+        assert!(is_simple_self_tail_recursive(
+            &item_fn,
+            &CallMacro::default()
+        ));
     }
 
     #[test]
@@ -342,8 +421,14 @@ mod tests {
             }
         };
 
-        assert_eq!(analyze(&item_fn), (false, false));
-        assert!(!is_simple_self_tail_recursive(&item_fn));
+        assert_eq!(
+            analyze(&item_fn, false, &CallMacro::default()),
+            (false, false)
+        );
+        assert!(!is_simple_self_tail_recursive(
+            &item_fn,
+            &CallMacro::default()
+        ));
     }
 
     #[test]
@@ -352,7 +437,44 @@ mod tests {
             tailcall::call! { countdown(n - 1) }
         };
 
-        assert!(is_tailcall_macro(&expr_macro.mac.path));
+        assert!(CallMacro::default().matches(&expr_macro.mac.path));
+    }
+
+    #[test]
+    fn recognizes_renamed_and_reexported_call_macro_paths() {
+        let call_macro = CallMacro::default();
+
+        for path in [
+            quote::quote!(call),
+            quote::quote!(::tailcall::call),
+            quote::quote!(tc::call),
+            quote::quote!(facade::tailcall::call),
+        ] {
+            assert!(call_macro.matches(&syn::parse2(path).unwrap()));
+        }
+        assert!(!call_macro.matches(&parse_quote!(tailcall::calls)));
+        assert!(!call_macro.matches(&parse_quote!(call::inner)));
+    }
+
+    #[test]
+    fn ignores_other_macros_when_the_call_macro_is_given() {
+        let item_fn: syn::ItemFn = parse_quote! {
+            fn countdown(n: u64) -> u64 {
+                let reply = rpc::call!(n);
+                if n > 0 {
+                    tailcall::call! { countdown(n - reply) }
+                } else {
+                    0
+                }
+            }
+        };
+        let call_macro = CallMacro::new(Some(parse_quote!(tailcall::call)));
+
+        assert!(!is_simple_self_tail_recursive(
+            &item_fn,
+            &CallMacro::default()
+        ));
+        assert!(is_simple_self_tail_recursive(&item_fn, &call_macro));
     }
 
     #[test]
@@ -376,7 +498,7 @@ mod tests {
             _ => panic!("expected tailcall macro statement in then branch"),
         };
 
-        assert!(is_tailcall_macro(&stmt_macro.mac.path));
+        assert!(CallMacro::default().matches(&stmt_macro.mac.path));
     }
 
     #[test]
@@ -391,8 +513,11 @@ mod tests {
             }
         };
 
-        assert_eq!(analyze_method(&method), (true, true));
-        assert!(is_simple_self_tail_recursive_method(&method));
+        assert_eq!(analyze_method(&method, &CallMacro::default()), (true, true));
+        assert!(is_simple_self_tail_recursive_method(
+            &method,
+            &CallMacro::default()
+        ));
     }
 
     #[test]
@@ -404,8 +529,14 @@ mod tests {
             }
         };
 
-        assert_eq!(analyze_method(&method), (false, false));
-        assert!(!is_simple_self_tail_recursive_method(&method));
+        assert_eq!(
+            analyze_method(&method, &CallMacro::default()),
+            (false, false)
+        );
+        assert!(!is_simple_self_tail_recursive_method(
+            &method,
+            &CallMacro::default()
+        ));
     }
 
     #[test]
@@ -420,8 +551,14 @@ mod tests {
             }
         };
 
-        assert_eq!(analyze(&item_fn), (true, true));
-        assert!(is_simple_self_tail_recursive(&item_fn));
+        assert_eq!(
+            analyze(&item_fn, false, &CallMacro::default()),
+            (true, true)
+        );
+        assert!(is_simple_self_tail_recursive(
+            &item_fn,
+            &CallMacro::default()
+        ));
     }
 
     #[test]
@@ -436,8 +573,14 @@ mod tests {
             }
         };
 
-        assert_eq!(analyze(&item_fn), (true, true));
-        assert!(is_simple_self_tail_recursive(&item_fn));
+        assert_eq!(
+            analyze(&item_fn, false, &CallMacro::default()),
+            (true, true)
+        );
+        assert!(is_simple_self_tail_recursive(
+            &item_fn,
+            &CallMacro::default()
+        ));
     }
 
     #[test]
@@ -452,8 +595,11 @@ mod tests {
             }
         };
 
-        assert_eq!(analyze_method(&method), (true, true));
-        assert!(is_simple_self_tail_recursive_method(&method));
+        assert_eq!(analyze_method(&method, &CallMacro::default()), (true, true));
+        assert!(is_simple_self_tail_recursive_method(
+            &method,
+            &CallMacro::default()
+        ));
     }
 
     #[test]
@@ -468,7 +614,205 @@ mod tests {
             }
         };
 
-        assert_eq!(analyze_method(&method), (true, true));
-        assert!(is_simple_self_tail_recursive_method(&method));
+        assert_eq!(analyze_method(&method, &CallMacro::default()), (true, true));
+        assert!(is_simple_self_tail_recursive_method(
+            &method,
+            &CallMacro::default()
+        ));
+    }
+
+    #[test]
+    fn accepts_self_method_path_tail_calls_on_other_receivers() {
+        let method: syn::ImplItemFn = parse_quote! {
+            fn total(&self, acc: u64) -> u64 {
+                match &self.next {
+                    Some(next) => tailcall::call! { Self::total(next, acc + self.weight) },
+                    None => acc + self.weight,
+                }
+            }
+        };
+
+        assert_eq!(analyze_method(&method, &CallMacro::default()), (true, true));
+        assert!(is_simple_self_tail_recursive_method(
+            &method,
+            &CallMacro::default()
+        ));
+    }
+
+    #[test]
+    fn rejects_method_syntax_tail_calls_on_other_receivers() {
+        let method: syn::ImplItemFn = parse_quote! {
+            fn total(&self, acc: u64) -> u64 {
+                match &self.next {
+                    Some(next) => tailcall::call! { next.total(acc + self.weight) },
+                    None => acc + self.weight,
+                }
+            }
+        };
+
+        assert_eq!(
+            analyze_method(&method, &CallMacro::default()),
+            (false, false)
+        );
+        assert!(!is_simple_self_tail_recursive_method(
+            &method,
+            &CallMacro::default()
+        ));
+    }
+
+    #[test]
+    fn rejects_non_tail_self_method_path_calls() {
+        let method: syn::ImplItemFn = parse_quote! {
+            fn total(&self, acc: u64) -> u64 {
+                match &self.next {
+                    Some(next) => Self::total(next, acc) + self.weight,
+                    None => acc + self.weight,
+                }
+            }
+        };
+
+        assert!(!is_simple_self_tail_recursive_method(
+            &method,
+            &CallMacro::default()
+        ));
+    }
+
+    #[test]
+    fn accepts_associated_self_tail_recursion_through_self_paths() {
+        let item_fn: syn::ItemFn = parse_quote! {
+            fn countdown(n: u32) -> u32 {
+                if n > 0 {
+                    tailcall::call! { Self::countdown(n - 1) }
+                } else {
+                    0
+                }
+            }
+        };
+
+        assert_eq!(analyze(&item_fn, true, &CallMacro::default()), (true, true));
+        assert!(is_simple_self_tail_recursive_associated(
+            &item_fn,
+            &CallMacro::default()
+        ));
+        assert!(!is_simple_self_tail_recursive(
+            &item_fn,
+            &CallMacro::default()
+        ));
+    }
+
+    #[test]
+    fn accepts_turbofish_self_tail_calls() {
+        for call in [
+            quote::quote! { walk(n - 1, value) },
+            quote::quote! { walk::<T, N>(n - 1, value) },
+        ] {
+            let item_fn: syn::ItemFn = parse_quote! {
+                fn walk<'a, T: Copy, const N: usize>(n: u32, value: &'a T) -> &'a T {
+                    if n > 0 {
+                        tailcall::call! { #call }
+                    } else {
+                        value
+                    }
+                }
+            };
+
+            assert!(
+                is_simple_self_tail_recursive(&item_fn, &CallMacro::default()),
+                "{call}"
+            );
+        }
+    }
+
+    #[test]
+    fn keeps_early_returned_self_tail_calls_on_the_runtime() {
+        let item_fn: syn::ItemFn = parse_quote! {
+            fn countdown(n: u64) -> u64 {
+                if n > 0 {
+                    return tailcall::call! { countdown(n - 1) };
+                }
+
+                n
+            }
+        };
+        let method: syn::ImplItemFn = parse_quote! {
+            fn countdown(&self, n: u64) -> u64 {
+                if n > 0 {
+                    return tailcall::call! { self.countdown(n - 1) };
+                }
+
+                n
+            }
+        };
+
+        assert!(!is_simple_self_tail_recursive(
+            &item_fn,
+            &CallMacro::default()
+        ));
+        assert!(!is_simple_self_tail_recursive_method(
+            &method,
+            &CallMacro::default()
+        ));
+    }
+
+    #[test]
+    fn rejects_changed_generics_and_unresolvable_paths() {
+        for call in [
+            quote::quote! { walk::<u32, N>(n - 1, value) },
+            quote::quote! { walk::<T, 4>(n - 1, value) },
+            quote::quote! { walk::<T>(n - 1, value) },
+            quote::quote! { walk::<'static, T, N>(n - 1, value) },
+            quote::quote! { self::walk(n - 1, value) },
+            quote::quote! { self::walk::<T, N>(n - 1, value) },
+            quote::quote! { crate::walk(n - 1, value) },
+            quote::quote! { super::walk(n - 1, value) },
+            quote::quote! { ::walk(n - 1, value) },
+            quote::quote! { other::walk(n - 1, value) },
+        ] {
+            let item_fn: syn::ItemFn = parse_quote! {
+                fn walk<'a, T: Copy, const N: usize>(n: u32, value: &'a T) -> &'a T {
+                    if n > 0 {
+                        tailcall::call! { #call }
+                    } else {
+                        value
+                    }
+                }
+            };
+
+            assert!(
+                !is_simple_self_tail_recursive(&item_fn, &CallMacro::default()),
+                "{call}"
+            );
+        }
+    }
+
+    #[test]
+    fn accepts_turbofish_self_method_path_tail_calls() {
+        let method: syn::ImplItemFn = parse_quote! {
+            fn walk<T: Copy>(&self, n: u32, value: T) -> T {
+                if n > 0 {
+                    tailcall::call! { Self::walk::<T>(self, n - 1, value) }
+                } else {
+                    value
+                }
+            }
+        };
+        let changed: syn::ImplItemFn = parse_quote! {
+            fn walk<T: Copy>(&self, n: u32, value: T) -> T {
+                if n > 0 {
+                    tailcall::call! { Self::walk::<Option<T>>(self, n - 1, Some(value)) }
+                } else {
+                    value
+                }
+            }
+        };
+
+        assert!(is_simple_self_tail_recursive_method(
+            &method,
+            &CallMacro::default()
+        ));
+        assert!(!is_simple_self_tail_recursive_method(
+            &changed,
+            &CallMacro::default()
+        ));
     }
 }
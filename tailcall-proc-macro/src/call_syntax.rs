@@ -1,15 +1,26 @@
 use proc_macro2::{Span, TokenStream};
 use quote::quote;
-use syn::{parse2, parse_quote, Error, Expr, ExprCall, ExprMethodCall, ExprPath, Path};
+use syn::{
+    parse2, parse_quote, punctuated::Punctuated, token::Comma, Error, Expr, ExprCall,
+    ExprMethodCall, ExprPath, GenericArgument, GenericParam, Generics, Ident, Path, PathArguments,
+    PathSegment, Stmt, Type, TypePath, UnOp,
+};
 
 use crate::naming::helper_ident;
 
+// The arguments are evaluated into locals first, so the temporaries they create are dropped before
+// the helper is called, in the same order as in loop-lowered functions.
 pub fn expand_call_macro(tokens: TokenStream) -> TokenStream {
     if let Ok(expr_call) = parse2::<ExprCall>(tokens.clone()) {
         return match helper_path_from_call(&expr_call) {
             Ok(func) => {
-                let args = expr_call.args;
-                quote! { #func(#args) }
+                let (temps, temp_idents) = argument_temps(&expr_call.args);
+                quote! {
+                    {
+                        #(#temps)*
+                        #func(#(#temp_idents),*)
+                    }
+                }
             }
             Err(error) => error.to_compile_error(),
         };
@@ -22,9 +33,9 @@ pub fn expand_call_macro(tokens: TokenStream) -> TokenStream {
         };
     }
 
-    Error::new(
-        Span::call_site(),
-        "tailcall::call! expects either `path(args...)` or `self.method(args...)`",
+    Error::new_spanned(
+        tokens,
+        "tailcall::call! expects either `path(args...)` or `receiver.method(args...)`",
     )
     .to_compile_error()
 }
@@ -39,33 +50,148 @@ pub fn helper_path_from_call(expr_call: &ExprCall) -> Result<Path, Error> {
     }
 }
 
+// The receiver is evaluated before the arguments, as in a plain method call. A place receiver such
+// as `self` or `self.child` has nothing to evaluate and may need to be auto-referenced, so it stays
+// where it is, while any other receiver is moved into a temporary first.
 pub fn helper_method_call_tokens(expr_method_call: &ExprMethodCall) -> Result<TokenStream, Error> {
-    if !matches!(
-        &*expr_method_call.receiver,
-        Expr::Path(ExprPath { path, .. }) if path.is_ident("self")
-    ) {
-        return Err(Error::new_spanned(
-            &expr_method_call.receiver,
-            "tailcall::call! only supports method syntax on `self`; use `Self::method(self, ...)` for other receivers",
-        ));
+    let helper = helper_ident(&expr_method_call.method);
+    let turbofish = &expr_method_call.turbofish;
+    let (receiver_temp, receiver): (Option<Stmt>, Expr) = match &*expr_method_call.receiver {
+        receiver if is_place(receiver) => (None, receiver.clone()),
+        receiver => {
+            let receiver_ident = Ident::new("__tailcall_receiver", Span::call_site());
+
+            (
+                Some(parse_quote! { let #receiver_ident = #receiver; }),
+                parse_quote!(#receiver_ident),
+            )
+        }
+    };
+    let (temps, temp_idents) = argument_temps(&expr_method_call.args);
+
+    Ok(parse_quote! {
+        {
+            #receiver_temp
+            #(#temps)*
+            #receiver.#helper #turbofish(#(#temp_idents),*)
+        }
+    })
+}
+
+fn is_place(expr: &Expr) -> bool {
+    match expr {
+        Expr::Path(_) => true,
+        Expr::Field(field) => is_place(&field.base),
+        Expr::Index(index) => is_place(&index.expr),
+        Expr::Unary(unary) => matches!(unary.op, UnOp::Deref(_)) && is_place(&unary.expr),
+        Expr::Paren(paren) => is_place(&paren.expr),
+        Expr::Group(group) => is_place(&group.expr),
+        _ => false,
     }
+}
 
-    let helper = helper_ident(&expr_method_call.method);
-    let args = &expr_method_call.args;
+// The arguments of a `call!` site, or none if it is not a call.
+pub fn call_arguments(tokens: &TokenStream) -> Vec<Expr> {
+    if let Ok(expr_call) = parse2::<ExprCall>(tokens.clone()) {
+        return expr_call.args.into_iter().collect();
+    }
+
+    match parse2::<ExprMethodCall>(tokens.clone()) {
+        Ok(expr_method_call) => expr_method_call.args.into_iter().collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn argument_temps(args: &Punctuated<Expr, Comma>) -> (Vec<Stmt>, Vec<Ident>) {
+    args.iter()
+        .enumerate()
+        .map(|(index, arg)| {
+            let temp_ident = Ident::new(&format!("__tailcall_next_{index}"), Span::call_site());
+            (parse_quote! { let #temp_ident = #arg; }, temp_ident)
+        })
+        .unzip()
+}
 
-    Ok(parse_quote! { self.#helper(#args) })
+// `Self::method(receiver, ...)` names the method being transformed directly, unlike method syntax,
+// which may autoref or dispatch to a different implementation.
+pub fn is_self_method_path(path: &Path, method_ident: &Ident, generics: &Generics) -> bool {
+    path.leading_colon.is_none()
+        && path.segments.len() == 2
+        && path.segments[0].ident == "Self"
+        && path.segments[0].arguments.is_none()
+        && is_self_segment(&path.segments[1], method_ident, generics)
 }
 
-pub fn is_tailcall_macro(path: &Path) -> bool {
-    match path.segments.last() {
-        Some(last) if last.ident == "call" => {}
-        _ => return false,
+// A free function names itself as `name`. Any longer path may name another function with the same
+// name, which the macro cannot tell apart, so those calls are left to the runtime. That includes
+// `self::name`, which names the module's item rather than a function nested in another function's
+// body.
+pub fn is_self_function_path(path: &Path, fn_ident: &Ident, generics: &Generics) -> bool {
+    path.leading_colon.is_none()
+        && path.segments.len() == 1
+        && is_self_segment(&path.segments[0], fn_ident, generics)
+}
+
+// A turbofish only keeps the call on the same instantiation if it repeats the function's own
+// generic parameters in order. Lifetimes may be left out, as they usually must be.
+fn is_self_segment(segment: &PathSegment, fn_ident: &Ident, generics: &Generics) -> bool {
+    if segment.ident != *fn_ident {
+        return false;
     }
 
-    match path.segments.len() {
-        1 => true,
-        2 => path.segments[0].ident == "tailcall",
-        _ => false,
+    let args = match &segment.arguments {
+        PathArguments::None => return true,
+        PathArguments::AngleBracketed(angle_bracketed) => &angle_bracketed.args,
+        PathArguments::Parenthesized(_) => return false,
+    };
+    let with_lifetimes = args
+        .iter()
+        .any(|arg| matches!(arg, GenericArgument::Lifetime(_)));
+    let params: Vec<String> = generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Lifetime(param) if with_lifetimes => Some(param.lifetime.to_string()),
+            GenericParam::Lifetime(_) => None,
+            GenericParam::Type(param) => Some(param.ident.to_string()),
+            GenericParam::Const(param) => Some(param.ident.to_string()),
+        })
+        .collect();
+    let args: Option<Vec<String>> = args
+        .iter()
+        .map(|arg| match arg {
+            GenericArgument::Lifetime(lifetime) => Some(lifetime.to_string()),
+            GenericArgument::Type(Type::Path(TypePath { qself: None, path }))
+            | GenericArgument::Const(Expr::Path(ExprPath {
+                qself: None, path, ..
+            })) => path.get_ident().map(Ident::to_string),
+            _ => None,
+        })
+        .collect();
+
+    args.is_some_and(|args| args == params)
+}
+
+// Which macro invocations are tail call sites. By default any path ending in `call` is one, so the
+// macro is recognized through a renamed crate (`tc::call!`) or a facade that re-exports it.
+// `call_macro = path` recognizes exactly that path instead, which covers renamed imports such as
+// `use tailcall::call as tail;` and functions that also use an unrelated `call!` macro.
+#[derive(Clone, Default)]
+pub struct CallMacro(Option<Path>);
+
+impl CallMacro {
+    pub fn new(path: Option<Path>) -> Self {
+        Self(path)
+    }
+
+    pub fn matches(&self, path: &Path) -> bool {
+        match &self.0 {
+            Some(call_macro) => quote!(#path).to_string() == quote!(#call_macro).to_string(),
+            None => path
+                .segments
+                .last()
+                .is_some_and(|last| last.ident == "call" && last.arguments.is_none()),
+        }
     }
 }
 
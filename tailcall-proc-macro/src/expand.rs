@@ -1,55 +1,195 @@
-use proc_macro2::TokenStream;
+use proc_macro2::{Span, TokenStream};
 use quote::quote;
-use syn::{Error, ImplItemFn, ItemFn, Signature};
+use syn::{
+    parse_quote, Attribute, Block, Error, Expr, FnArg, GenericParam, Generics, ImplItem,
+    ImplItemFn, ItemFn, ItemImpl, ItemTrait, Pat, PatType, Signature, TraitItem, TraitItemFn, Type,
+    Visibility,
+};
 
 use crate::{
-    analyze::{is_simple_self_tail_recursive, is_simple_self_tail_recursive_method},
-    loop_lower::{lower_self_tail_loop, lower_self_tail_method_loop},
+    analyze::{
+        is_simple_self_tail_recursive, is_simple_self_tail_recursive_associated,
+        is_simple_self_tail_recursive_method,
+    },
+    instrument::instrumentation_stmts,
+    loop_lower::{
+        lower_self_tail_associated_loop, lower_self_tail_loop, lower_self_tail_method_loop,
+    },
+    naming::thunk_fn_ident,
+    options::TailcallOptions,
+    recover::plain_calls,
     rewrite::TailPositionRewriter,
-    signature::{function_argument_exprs, helper_signature, method_helper_signature},
+    signature::{
+        function_argument_exprs, helper_signature, method_helper_signature, wrapper_signature,
+    },
+    steps::steps_items,
 };
 
-pub fn apply_fn_tailcall_transform(item_fn: ItemFn) -> TokenStream {
-    match TailcallTransform::new(item_fn).expand() {
+pub fn apply_fn_tailcall_transform(item_fn: ItemFn, options: TailcallOptions) -> TokenStream {
+    let transform = TailcallTransform::new(item_fn, options);
+
+    match transform.expand() {
         Ok(output) => output,
-        Err(error) => error.to_compile_error(),
+        Err(error) => transform.recover(error),
     }
 }
 
-pub fn apply_method_tailcall_transform(method: ImplItemFn) -> TokenStream {
-    match TailcallMethodTransform::new(method).expand() {
-        Ok(output) => output,
-        Err(error) => error.to_compile_error(),
+// Emits `item_fn` unexpanded next to `error`, for options that could not be parsed.
+pub fn recover_fn_tailcall_transform(item_fn: ItemFn, error: Error) -> TokenStream {
+    TailcallTransform::new(item_fn, TailcallOptions::default()).recover(error)
+}
+
+pub fn apply_method_tailcall_transform(
+    method: ImplItemFn,
+    options: TailcallOptions,
+) -> TokenStream {
+    expand_method(method, Generics::default(), Ok(options))
+}
+
+pub fn recover_method_tailcall_transform(method: ImplItemFn, error: Error) -> TokenStream {
+    expand_method(method, Generics::default(), Err(error))
+}
+
+fn expand_method(
+    method: ImplItemFn,
+    impl_generics: Generics,
+    options: Result<TailcallOptions, Error>,
+) -> TokenStream {
+    match options {
+        Ok(options) => {
+            let transform = TailcallMethodTransform::new(method, impl_generics, options);
+
+            match transform.expand() {
+                Ok(output) => output,
+                Err(error) => transform.recover(error),
+            }
+        }
+        Err(error) => {
+            TailcallMethodTransform::new(method, impl_generics, TailcallOptions::default())
+                .recover(error)
+        }
+    }
+}
+
+// A receiver-less associated function looks exactly like a free function to an item-level
+// attribute, so it can only be expanded with `Self::`-qualified helper paths when `#[tailcall]` is
+// also placed on the surrounding `impl` block. In a trait impl, only the helpers are generated, as
+// the trait provides the methods themselves, see `apply_trait_tailcall_transform`.
+pub fn apply_impl_tailcall_transform(mut item_impl: ItemImpl) -> TokenStream {
+    let trait_impl = item_impl.trait_.is_some();
+
+    item_impl.items = item_impl
+        .items
+        .into_iter()
+        .map(|item| match item {
+            ImplItem::Fn(mut method) if method.attrs.iter().any(is_tailcall_attribute) => {
+                let options = take_tailcall_options(&mut method.attrs);
+
+                ImplItem::Verbatim(if trait_impl {
+                    expand_trait_impl_method(method, options)
+                } else {
+                    expand_method(method, item_impl.generics.clone(), options)
+                })
+            }
+            item => item,
+        })
+        .collect();
+
+    quote! { #item_impl }
+}
+
+// `#[tailcall]` on a trait turns each `#[tailcall]` method into a provided method that runs a
+// hidden helper, declared next to it. The helper only adds the `'tailcall` lifetime to the method's
+// signature, so it stays callable through `dyn Trait`, and `call!` sites dispatch to it like to any
+// other method. Implementors define the helpers by placing `#[tailcall]` on their impl blocks.
+pub fn apply_trait_tailcall_transform(mut item_trait: ItemTrait) -> TokenStream {
+    item_trait.items = item_trait
+        .items
+        .into_iter()
+        .map(|item| match item {
+            TraitItem::Fn(mut method) if method.attrs.iter().any(is_tailcall_attribute) => {
+                let options = take_tailcall_options(&mut method.attrs);
+
+                TraitItem::Verbatim(expand_trait_method(method, options))
+            }
+            item => item,
+        })
+        .collect();
+
+    quote! { #item_trait }
+}
+
+fn take_tailcall_options(attrs: &mut Vec<Attribute>) -> Result<TailcallOptions, Error> {
+    let options = attrs
+        .iter()
+        .find(|attr| is_tailcall_attribute(attr))
+        .map(TailcallOptions::from_attribute)
+        .expect("a #[tailcall] attribute is present");
+    attrs.retain(|attr| !is_tailcall_attribute(attr));
+
+    options
+}
+
+fn is_tailcall_attribute(attr: &Attribute) -> bool {
+    let path = attr.path();
+
+    match path.segments.len() {
+        1 => path.is_ident("tailcall"),
+        2 => path
+            .segments
+            .iter()
+            .all(|segment| segment.ident == "tailcall"),
+        _ => false,
     }
 }
 
 struct TailcallTransform {
     item_fn: ItemFn,
+    options: TailcallOptions,
 }
 
 struct TailcallMethodTransform {
     method: ImplItemFn,
+    // The generics of the surrounding `impl` block, when `#[tailcall]` is placed on it.
+    impl_generics: Generics,
+    options: TailcallOptions,
 }
 
 impl TailcallMethodTransform {
-    fn new(method: ImplItemFn) -> Self {
-        Self { method }
+    fn new(method: ImplItemFn, impl_generics: Generics, options: TailcallOptions) -> Self {
+        Self {
+            method,
+            impl_generics,
+            options,
+        }
     }
 
-    fn expand(self) -> Result<TokenStream, Error> {
+    fn expand(&self) -> Result<TokenStream, Error> {
         let ImplItemFn {
             attrs,
             vis,
             defaultness,
             sig,
             block,
-        } = self.method;
+        } = self.method.clone();
 
         reject_unsupported_signature(&sig)?;
+        self.options.check_arena(&sig)?;
+
+        if self.options.trace() {
+            return Err(Error::new_spanned(
+                &sig.ident,
+                "`trace` is only supported on free functions",
+            ));
+        }
 
-        let helper_sig = method_helper_signature(&sig)?;
+        let value_fn = self.options.value_fn();
+        let call_macro = self.options.call_macro();
+        let helper_sig =
+            method_helper_signature(&sig, &self.impl_generics, &self.options.thunk_path())?;
         let helper_fn_ident = &helper_sig.ident;
         let helper_args = function_argument_exprs(&sig)?;
+        let helper_attrs = self.options.helper_attributes(&attrs)?;
         let original_method = ImplItemFn {
             attrs: attrs.clone(),
             vis: vis.clone(),
@@ -57,96 +197,644 @@ impl TailcallMethodTransform {
             sig: sig.clone(),
             block: block.clone(),
         };
-        let optimized = is_simple_self_tail_recursive_method(&original_method);
-        let wrapper_body = if optimized {
-            lower_self_tail_method_loop(&original_method)?
-        } else {
-            quote! { Self::#helper_fn_ident(#(#helper_args),*).call() }
+        // Associated functions without a receiver are analyzed like free functions whose self
+        // calls are spelled `Self::name(...)`.
+        let associated_fn =
+            (!matches!(sig.inputs.first(), Some(FnArg::Receiver(_)))).then(|| ItemFn {
+                attrs: attrs.clone(),
+                vis: vis.clone(),
+                sig: sig.clone(),
+                block: Box::new(block.clone()),
+            });
+        let optimized = match &associated_fn {
+            Some(item_fn) => is_simple_self_tail_recursive_associated(item_fn, &call_macro),
+            None => is_simple_self_tail_recursive_method(&original_method, &call_macro),
+        };
+        let wrapper_sig = wrapper_signature(&sig);
+        let wrapper_body = match &associated_fn {
+            Some(item_fn) if optimized => lower_self_tail_associated_loop(item_fn, &self.options)?,
+            None if optimized => lower_self_tail_method_loop(&original_method, &self.options)?,
+            _ => forward_unsafety(
+                &sig,
+                self.options
+                    .run_tokens(quote! { Self::#helper_fn_ident(#(#helper_args),*) }),
+            ),
         };
         let helper_body = if optimized {
             let method_ident = &sig.ident;
-            quote! {
-                tailcall::runtime::Thunk::value(Self::#method_ident(#(#helper_args),*))
-            }
+            forward_unsafety(
+                &sig,
+                quote! {
+                    #value_fn(Self::#method_ident(#(#helper_args),*))
+                },
+            )
         } else {
-            let helper_block = TailPositionRewriter::rewrite(block)?;
-            quote! {
-                tailcall::runtime::Thunk::bounce(move || #helper_block)
-            }
+            let mut helper_block =
+                TailPositionRewriter::rewrite(&sig, block, &value_fn, &call_macro)?;
+            helper_block.stmts.splice(
+                0..0,
+                instrumentation_stmts(&sig.ident, &self.options.crate_path())
+                    .into_iter()
+                    .chain(self.options.bounce_check_stmt()),
+            );
+            bounce_tokens(&sig, &helper_args, helper_block, &self.options)
         };
+        let exposed_thunk = self.options.expose_thunk().then(|| {
+            exposed_thunk_tokens(
+                &vis,
+                &sig,
+                &helper_sig,
+                format!("Self::{}", sig.ident),
+                quote! { Self::#helper_fn_ident(#(#helper_args),*) },
+                &helper_attrs,
+            )
+        });
+        let (wrapper_lint_attrs, helper_lint_attrs) =
+            deprecation_allowances(&attrs, &helper_attrs, optimized);
 
         Ok(quote! {
             #(#attrs)*
-            #defaultness #vis #sig {
+            #wrapper_lint_attrs
+            #defaultness #vis #wrapper_sig {
                 #wrapper_body
             }
 
+            #(#helper_attrs)*
+            #helper_lint_attrs
             #[doc(hidden)]
             #[allow(unused)]
             #[inline(always)]
             #helper_sig {
                 #helper_body
             }
+
+            #exposed_thunk
         })
     }
+
+    // The method is emitted with plain calls next to the error, so it still resolves for its
+    // callers and keeps being analyzed by IDEs.
+    fn recover(&self, error: Error) -> TokenStream {
+        let mut method = self.method.clone();
+        method.attrs.retain(|attr| !is_tailcall_attribute(attr));
+        method.block = plain_calls(method.block, &self.options.call_macro());
+        let helper =
+            method_helper_signature(&method.sig, &self.impl_generics, &self.options.thunk_path())
+                .ok()
+                .and_then(|helper_sig| {
+                    recovery_helper_tokens(
+                        &method.attrs,
+                        &method.vis,
+                        &method.sig,
+                        helper_sig,
+                        true,
+                        self.options.expose_thunk(),
+                        &self.options,
+                    )
+                });
+        let error = error.to_compile_error();
+
+        quote! {
+            #error
+            #method
+            #helper
+        }
+    }
 }
 
 impl TailcallTransform {
-    fn new(item_fn: ItemFn) -> Self {
-        Self { item_fn }
+    fn new(item_fn: ItemFn, options: TailcallOptions) -> Self {
+        Self { item_fn, options }
     }
 
-    fn expand(self) -> Result<TokenStream, Error> {
+    fn expand(&self) -> Result<TokenStream, Error> {
         let ItemFn {
             attrs,
             vis,
             sig,
             block,
-        } = self.item_fn;
+        } = self.item_fn.clone();
 
         reject_unsupported_signature(&sig)?;
+        self.options.check_arena(&sig)?;
 
-        let helper_sig = helper_signature(&sig);
+        let value_fn = self.options.value_fn();
+        let call_macro = self.options.call_macro();
+        let helper_sig = helper_signature(&sig, &self.options.thunk_path());
         let helper_fn_ident = &helper_sig.ident;
         let helper_args = function_argument_exprs(&sig)?;
+        let helper_attrs = self.options.helper_attributes(&attrs)?;
         let original_item_fn = ItemFn {
             attrs: attrs.clone(),
             vis: vis.clone(),
             sig: sig.clone(),
             block: block.clone(),
         };
-        let optimized = is_simple_self_tail_recursive(&original_item_fn);
+        let optimized = is_simple_self_tail_recursive(&original_item_fn, &call_macro);
+        let steps_items = match self.options.trace() {
+            true if optimized => Some(steps_items(&original_item_fn, &call_macro)?),
+            true => {
+                return Err(Error::new_spanned(
+                    &sig.ident,
+                    "`trace` requires a function whose tail calls only call itself",
+                ))
+            }
+            false => None,
+        };
+        let wrapper_sig = wrapper_signature(&sig);
         let wrapper_body = if optimized {
-            lower_self_tail_loop(&original_item_fn)?
+            lower_self_tail_loop(&original_item_fn, &self.options)?
         } else {
-            quote! { #helper_fn_ident(#(#helper_args),*).call() }
+            forward_unsafety(
+                &sig,
+                self.options
+                    .run_tokens(quote! { #helper_fn_ident(#(#helper_args),*) }),
+            )
         };
         let helper_body = if optimized {
             let fn_ident = &sig.ident;
-            quote! {
-                tailcall::runtime::Thunk::value(#fn_ident(#(#helper_args),*))
-            }
+            forward_unsafety(
+                &sig,
+                quote! {
+                    #value_fn(#fn_ident(#(#helper_args),*))
+                },
+            )
         } else {
-            let helper_block = TailPositionRewriter::rewrite(*block)?;
-            quote! {
-                tailcall::runtime::Thunk::bounce(move || #helper_block)
-            }
+            let mut helper_block =
+                TailPositionRewriter::rewrite(&sig, *block, &value_fn, &call_macro)?;
+            helper_block.stmts.splice(
+                0..0,
+                instrumentation_stmts(&sig.ident, &self.options.crate_path())
+                    .into_iter()
+                    .chain(self.options.bounce_check_stmt()),
+            );
+            bounce_tokens(&sig, &helper_args, helper_block, &self.options)
         };
+        let exposed_thunk = self.options.expose_thunk().then(|| {
+            exposed_thunk_tokens(
+                &vis,
+                &sig,
+                &helper_sig,
+                sig.ident.to_string(),
+                quote! { #helper_fn_ident(#(#helper_args),*) },
+                &helper_attrs,
+            )
+        });
+        let (wrapper_lint_attrs, helper_lint_attrs) =
+            deprecation_allowances(&attrs, &helper_attrs, optimized);
 
         Ok(quote! {
             #(#attrs)*
-            #vis #sig {
+            #wrapper_lint_attrs
+            #vis #wrapper_sig {
                 #wrapper_body
             }
 
+            #(#helper_attrs)*
+            #helper_lint_attrs
             #[doc(hidden)]
             #[allow(unused)]
             #[inline(always)]
             #helper_sig {
                 #helper_body
             }
+
+            #exposed_thunk
+
+            #steps_items
         })
     }
+
+    // The function is emitted with plain calls next to the error, so it still resolves for its
+    // callers and keeps being analyzed by IDEs.
+    fn recover(&self, error: Error) -> TokenStream {
+        let mut item_fn = self.item_fn.clone();
+        *item_fn.block = plain_calls(*item_fn.block, &self.options.call_macro());
+        let helper_sig = helper_signature(&item_fn.sig, &self.options.thunk_path());
+        let helper = recovery_helper_tokens(
+            &item_fn.attrs,
+            &item_fn.vis,
+            &item_fn.sig,
+            helper_sig,
+            false,
+            self.options.expose_thunk(),
+            &self.options,
+        );
+        let error = error.to_compile_error();
+
+        quote! {
+            #error
+            #item_fn
+            #helper
+        }
+    }
+}
+
+fn expand_trait_method(
+    method: TraitItemFn,
+    options: Result<TailcallOptions, Error>,
+) -> TokenStream {
+    let (options, error) = match options {
+        Ok(options) => match trait_method_tokens(&method, &options) {
+            Ok(output) => return output,
+            Err(error) => (options, error),
+        },
+        Err(error) => (TailcallOptions::default(), error),
+    };
+
+    // Without a default body, only the helper's declaration is needed for the impls to resolve.
+    let TraitItemFn {
+        mut attrs,
+        sig,
+        default,
+        semi_token,
+    } = method;
+    let helper_sig =
+        method_helper_signature(&sig, &Generics::default(), &options.thunk_path()).ok();
+    let helper = match (&default, helper_sig) {
+        (Some(_), Some(helper_sig)) => recovery_helper_tokens(
+            &attrs,
+            &Visibility::Inherited,
+            &sig,
+            helper_sig,
+            true,
+            false,
+            &options,
+        ),
+        (None, Some(helper_sig)) => Some(quote! {
+            #[doc(hidden)]
+            #helper_sig;
+        }),
+        (_, None) => None,
+    };
+    attrs.retain(|attr| !is_tailcall_attribute(attr));
+    let method = TraitItemFn {
+        attrs,
+        sig,
+        default: default.map(|block| plain_calls(block, &options.call_macro())),
+        semi_token,
+    };
+    let error = error.to_compile_error();
+
+    quote! {
+        #error
+        #method
+        #helper
+    }
+}
+
+fn trait_method_tokens(
+    method: &TraitItemFn,
+    options: &TailcallOptions,
+) -> Result<TokenStream, Error> {
+    let TraitItemFn {
+        attrs,
+        sig,
+        default,
+        ..
+    } = method;
+
+    reject_unsupported_signature(sig)?;
+    reject_undispatchable_method(sig, options)?;
+    options.check_arena(sig)?;
+
+    let helper_sig = method_helper_signature(sig, &Generics::default(), &options.thunk_path())?;
+    let helper_fn_ident = &helper_sig.ident;
+    let helper_args = function_argument_exprs(sig)?;
+    let helper_attrs = options.helper_attributes(attrs)?;
+    let wrapper_sig = wrapper_signature(sig);
+    let wrapper_body = forward_unsafety(
+        sig,
+        options.run_tokens(quote! { Self::#helper_fn_ident(#(#helper_args),*) }),
+    );
+    let helper = match default {
+        Some(block) => {
+            let helper_body = trait_helper_body(sig, block.clone(), &helper_args, options)?;
+            quote! {
+                #helper_sig {
+                    #helper_body
+                }
+            }
+        }
+        None => quote! { #helper_sig; },
+    };
+    let (wrapper_lint_attrs, _) = deprecation_allowances(attrs, &helper_attrs, false);
+
+    Ok(quote! {
+        #(#attrs)*
+        #wrapper_lint_attrs
+        #wrapper_sig {
+            #wrapper_body
+        }
+
+        #(#helper_attrs)*
+        #[doc(hidden)]
+        #helper
+    })
+}
+
+fn expand_trait_impl_method(
+    method: ImplItemFn,
+    options: Result<TailcallOptions, Error>,
+) -> TokenStream {
+    let (options, error) = match options {
+        Ok(options) => match trait_impl_method_tokens(&method, &options) {
+            Ok(output) => return output,
+            Err(error) => (options, error),
+        },
+        Err(error) => (TailcallOptions::default(), error),
+    };
+
+    // The method overrides the one the trait provides, and the helper calls it.
+    let mut method = method;
+    method.block = plain_calls(method.block, &options.call_macro());
+    let helper = method_helper_signature(&method.sig, &Generics::default(), &options.thunk_path())
+        .ok()
+        .and_then(|helper_sig| {
+            recovery_helper_tokens(
+                &method.attrs,
+                &method.vis,
+                &method.sig,
+                helper_sig,
+                true,
+                false,
+                &options,
+            )
+        });
+    let error = error.to_compile_error();
+
+    quote! {
+        #error
+        #method
+        #helper
+    }
+}
+
+fn trait_impl_method_tokens(
+    method: &ImplItemFn,
+    options: &TailcallOptions,
+) -> Result<TokenStream, Error> {
+    let ImplItemFn {
+        attrs, sig, block, ..
+    } = method;
+
+    reject_unsupported_signature(sig)?;
+    reject_undispatchable_method(sig, options)?;
+    options.check_arena(sig)?;
+
+    let helper_sig = method_helper_signature(sig, &Generics::default(), &options.thunk_path())?;
+    let helper_args = function_argument_exprs(sig)?;
+    let helper_attrs = options.helper_attributes(attrs)?;
+    let helper_body = trait_helper_body(sig, block.clone(), &helper_args, options)?;
+
+    Ok(quote! {
+        #(#helper_attrs)*
+        #[doc(hidden)]
+        #[inline(always)]
+        #helper_sig {
+            #helper_body
+        }
+    })
+}
+
+// Trait helpers always bounce through the runtime, since a call through `self` may reach any
+// implementation.
+fn trait_helper_body(
+    sig: &Signature,
+    block: Block,
+    helper_args: &[Expr],
+    options: &TailcallOptions,
+) -> Result<TokenStream, Error> {
+    let mut helper_block =
+        TailPositionRewriter::rewrite(sig, block, &options.value_fn(), &options.call_macro())?;
+    helper_block.stmts.splice(
+        0..0,
+        instrumentation_stmts(&sig.ident, &options.crate_path())
+            .into_iter()
+            .chain(options.bounce_check_stmt()),
+    );
+
+    Ok(bounce_tokens(sig, helper_args, helper_block, options))
+}
+
+// A trait helper has to stay callable through `dyn Trait`, so it borrows its receiver and adds
+// nothing to the method's generics but a lifetime.
+fn reject_undispatchable_method(sig: &Signature, options: &TailcallOptions) -> Result<(), Error> {
+    match sig.inputs.first() {
+        Some(FnArg::Receiver(receiver)) if matches!(&*receiver.ty, Type::Reference(_)) => {}
+        Some(FnArg::Receiver(receiver)) => {
+            return Err(Error::new_spanned(
+                receiver,
+                "#[tailcall] trait methods must take `&self` or `&mut self`",
+            ))
+        }
+        _ => {
+            return Err(Error::new_spanned(
+                &sig.ident,
+                "#[tailcall] trait methods must take `&self` or `&mut self`",
+            ))
+        }
+    }
+
+    if let Some(param) = sig
+        .generics
+        .params
+        .iter()
+        .find(|param| !matches!(param, GenericParam::Lifetime(_)))
+    {
+        return Err(Error::new_spanned(
+            param,
+            "#[tailcall] trait methods cannot have type or const parameters, so that they can be called through `dyn Trait`",
+        ));
+    }
+
+    if options.trace() {
+        return Err(Error::new_spanned(
+            &sig.ident,
+            "`trace` is only supported on free functions",
+        ));
+    }
+
+    if options.expose_thunk() {
+        return Err(Error::new_spanned(
+            &sig.ident,
+            "`expose_thunk` is not supported on trait methods",
+        ));
+    }
+
+    Ok(())
+}
+
+// Helpers without a receiver bounce through `Thunk::bounce_with`: the arguments become the state
+// tuple and the body a non-capturing closure, so every helper with the same argument types shares
+// its dispatch code. A closure parameter cannot bind `self`, so methods keep a capturing closure,
+// as do helpers for runtimes other than the inline `Thunk` and helpers that may move their
+// closure into an arena.
+fn bounce_tokens(
+    sig: &Signature,
+    args: &[Expr],
+    helper_block: Block,
+    options: &TailcallOptions,
+) -> TokenStream {
+    let crate_path = options.crate_path();
+
+    if let Some(arena) = options.arena() {
+        return quote! {
+            #crate_path::runtime::Arena::bounce(#arena, move || #helper_block)
+        };
+    }
+
+    if let Some(depth) = options.hybrid() {
+        return quote! {
+            #crate_path::runtime::Thunk::call_or_bounce(#depth, move || #helper_block)
+        };
+    }
+
+    if matches!(sig.inputs.first(), Some(FnArg::Receiver(_))) || !options.supports_bounce_with() {
+        let bounce_fn = options.bounce_fn();
+
+        return quote! {
+            #bounce_fn(move || #helper_block)
+        };
+    }
+
+    // The closure parameters replace what used to be captures, so they are spanned at the call
+    // site to keep lints such as `clippy::redundant_locals` from flagging a `let x = x;` that
+    // rebinds one of them.
+    let pats = sig.inputs.iter().filter_map(|input| match input {
+        FnArg::Typed(PatType { pat, .. }) => {
+            let mut pat = (**pat).clone();
+            if let Pat::Ident(pat_ident) = &mut pat {
+                pat_ident.ident.set_span(Span::call_site());
+            }
+            Some(pat)
+        }
+        FnArg::Receiver(_) => None,
+    });
+
+    quote! {
+        #crate_path::runtime::Thunk::bounce_with((#(#args,)*), |(#(#pats,)*)| #helper_block)
+    }
+}
+
+// With `expose_thunk`, `{name}_thunk` hands out the helper under a stable name and with the
+// wrapper's visibility, so hand-written thunk builders can return it in tail position.
+fn exposed_thunk_tokens(
+    vis: &Visibility,
+    sig: &Signature,
+    helper_sig: &Signature,
+    fn_path: String,
+    helper_call: TokenStream,
+    helper_attrs: &[Attribute],
+) -> TokenStream {
+    let mut thunk_sig = wrapper_signature(helper_sig);
+    thunk_sig.ident = thunk_fn_ident(&sig.ident);
+    let doc = format!(
+        "Returns a call to [`{fn_path}`] as a thunk.\n\n\
+         A hand-written thunk builder can return it to make the call in tail position. If \
+         `{fn_path}` only calls itself, it is lowered to a loop, which runs as soon as the thunk \
+         is built."
+    );
+    let body = forward_unsafety(sig, helper_call);
+    let allow_deprecated = helper_attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("deprecated"))
+        .then(|| quote!(#[allow(deprecated)]));
+
+    quote! {
+        #(#helper_attrs)*
+        #allow_deprecated
+        #[doc = #doc]
+        #[inline(always)]
+        #vis #thunk_sig {
+            #body
+        }
+    }
+}
+
+// The wrapper and helper of an `unsafe fn` call each other, which needs an `unsafe` block under
+// `unsafe_op_in_unsafe_fn` but is redundant without it.
+fn forward_unsafety(sig: &Signature, call: TokenStream) -> TokenStream {
+    if sig.unsafety.is_some() {
+        quote! {
+            #[allow(unused_unsafe)]
+            unsafe { #call }
+        }
+    } else {
+        call
+    }
+}
+
+// Stands in for the helper, and the exposed thunk, of an item that could not be expanded, so
+// `call!` sites naming the item elsewhere still resolve and only the original error is reported.
+// Nothing is emitted for signatures the expansion could not have handled either.
+fn recovery_helper_tokens(
+    attrs: &[Attribute],
+    vis: &Visibility,
+    sig: &Signature,
+    helper_sig: Signature,
+    in_impl: bool,
+    expose_thunk: bool,
+    options: &TailcallOptions,
+) -> Option<TokenStream> {
+    reject_unsupported_signature(sig).ok()?;
+    let args = function_argument_exprs(sig).ok()?;
+    let helper_attrs = options.helper_attributes(attrs).ok()?;
+    let (_, helper_lint_attrs) = deprecation_allowances(attrs, &helper_attrs, true);
+    let value_fn = options.value_fn();
+    let fn_ident = &sig.ident;
+    let helper_fn_ident = &helper_sig.ident;
+    let qualifier = in_impl.then(|| quote!(Self::));
+    let fn_path = match in_impl {
+        true => format!("Self::{fn_ident}"),
+        false => fn_ident.to_string(),
+    };
+    let helper_body = forward_unsafety(sig, quote! { #value_fn(#qualifier #fn_ident(#(#args),*)) });
+    let exposed_thunk = expose_thunk.then(|| {
+        exposed_thunk_tokens(
+            vis,
+            sig,
+            &helper_sig,
+            fn_path,
+            quote! { #qualifier #helper_fn_ident(#(#args),*) },
+            &helper_attrs,
+        )
+    });
+
+    Some(quote! {
+        #(#helper_attrs)*
+        #helper_lint_attrs
+        #[doc(hidden)]
+        #[allow(unused)]
+        #[inline(always)]
+        #helper_sig {
+            #helper_body
+        }
+
+        #exposed_thunk
+    })
+}
+
+// When only generated glue calls a `#[deprecated]` wrapper or helper, the lint would point at code
+// the user never wrote. That is the optimized helper forwarding to its wrapper, and the
+// runtime-backed wrapper forwarding to its helper.
+fn deprecation_allowances(
+    attrs: &[Attribute],
+    helper_attrs: &[Attribute],
+    optimized: bool,
+) -> (Option<Attribute>, Option<Attribute>) {
+    let is_deprecated =
+        |attrs: &[Attribute]| attrs.iter().any(|attr| attr.path().is_ident("deprecated"));
+    let allow_deprecated: Attribute = parse_quote!(#[allow(deprecated)]);
+
+    if optimized {
+        (None, is_deprecated(attrs).then_some(allow_deprecated))
+    } else {
+        (
+            is_deprecated(helper_attrs).then_some(allow_deprecated),
+            None,
+        )
+    }
 }
 
 fn reject_unsupported_signature(sig: &Signature) -> Result<(), Error> {
@@ -169,11 +857,15 @@ fn reject_unsupported_signature(sig: &Signature) -> Result<(), Error> {
 
 #[cfg(test)]
 mod tests {
-    use proc_macro2::TokenStream;
+    use proc_macro2::{Span, TokenStream};
     use quote::quote;
-    use syn::parse_quote;
+    use syn::{parse_quote, Error};
 
-    use super::{apply_fn_tailcall_transform, apply_method_tailcall_transform};
+    use super::{
+        apply_fn_tailcall_transform, apply_impl_tailcall_transform,
+        apply_method_tailcall_transform, apply_trait_tailcall_transform,
+    };
+    use crate::options::TailcallOptions;
 
     fn assert_expansion_eq(actual: TokenStream, expected: TokenStream) {
         assert_eq!(actual.to_string(), expected.to_string());
@@ -191,7 +883,7 @@ mod tests {
             }
         };
 
-        let actual = apply_fn_tailcall_transform(item_fn);
+        let actual = apply_fn_tailcall_transform(item_fn, TailcallOptions::default());
         let expected = quote! {
             fn is_even(x: u32) -> bool {
                 __tailcall_build_is_even_thunk(x).call()
@@ -201,7 +893,7 @@ mod tests {
             #[allow(unused)]
             #[inline(always)]
             fn __tailcall_build_is_even_thunk<'tailcall>(x: u32) -> tailcall::runtime::Thunk<'tailcall, bool> {
-                tailcall::runtime::Thunk::bounce(move || {
+                tailcall::runtime::Thunk::bounce_with((x,), |(x,)| {
                     if x == 0 {
                         tailcall::runtime::Thunk::value(true)
                     } else {
@@ -226,20 +918,22 @@ mod tests {
             }
         };
 
-        let actual = apply_fn_tailcall_transform(item_fn);
+        let actual = apply_fn_tailcall_transform(item_fn, TailcallOptions::default());
         let expected = quote! {
             fn countdown(n: u32) -> u32 {
                 let mut n = n;
                 loop {
-                    if n > 0 {
-                        {
-                            let __tailcall_next_0 = n - 1;
-                            n = __tailcall_next_0;
-                            continue;
+                    let __tailcall_next = '__tailcall_body: {
+                        if n > 0 {
+                            {
+                                let __tailcall_next_0 = n - 1;
+                                break '__tailcall_body (__tailcall_next_0,);
+                            }
+                        } else {
+                            return 0
                         }
-                    } else {
-                        return 0
-                    }
+                    };
+                    n = __tailcall_next.0;
                 }
             }
 
@@ -268,22 +962,24 @@ mod tests {
             }
         };
 
-        let actual = apply_method_tailcall_transform(method);
+        let actual = apply_method_tailcall_transform(method, TailcallOptions::default());
         let expected = quote! {
             fn countdown(&mut self, n: u32) -> u32 {
                 let __tailcall_self = self;
                 let mut n = n;
                 loop {
-                    __tailcall_self.steps += 1;
-                    if n > 0 {
-                        {
-                            let __tailcall_next_0 = n - 1;
-                            n = __tailcall_next_0;
-                            continue;
+                    let __tailcall_next = '__tailcall_body: {
+                        __tailcall_self.steps += 1;
+                        if n > 0 {
+                            {
+                                let __tailcall_next_0 = n - 1;
+                                break '__tailcall_body (__tailcall_next_0,);
+                            }
+                        } else {
+                            return __tailcall_self.steps as u32
                         }
-                    } else {
-                        return __tailcall_self.steps as u32
-                    }
+                    };
+                    n = __tailcall_next.0;
                 }
             }
 
@@ -297,4 +993,1126 @@ mod tests {
 
         assert_expansion_eq(actual, expected);
     }
+
+    #[test]
+    fn expands_loop_lowered_boxed_receiver_as_expected() {
+        let method: syn::ImplItemFn = parse_quote! {
+            fn sum(mut self: Box<Self>, total: u64) -> u64 {
+                match self.next.take() {
+                    Some(next) => {
+                        self = next;
+                        tailcall::call! { self.sum(total + 1) }
+                    }
+                    None => total,
+                }
+            }
+        };
+
+        let actual = apply_method_tailcall_transform(method, TailcallOptions::default());
+        let expected = quote! {
+            fn sum(self: Box<Self>, total: u64) -> u64 {
+                let mut __tailcall_self = self;
+                let mut total = total;
+                loop {
+                    let __tailcall_next = '__tailcall_body: {
+                        match __tailcall_self.next.take() {
+                            Some(next) => {
+                                __tailcall_self = next;
+                                {
+                                    let __tailcall_next_0 = total + 1;
+                                    break '__tailcall_body (__tailcall_next_0,);
+                                }
+                            }
+                            None => return total,
+                        }
+                    };
+                    total = __tailcall_next.0;
+                }
+            }
+
+            #[doc(hidden)]
+            #[allow(unused)]
+            #[inline(always)]
+            fn __tailcall_build_sum_thunk<'tailcall>(mut self: Box<Self>, total: u64) -> tailcall::runtime::Thunk<'tailcall, u64>
+            where
+                Self: 'tailcall
+            {
+                tailcall::runtime::Thunk::value(Self::sum(self, total))
+            }
+        };
+
+        assert_expansion_eq(actual, expected);
+    }
+
+    #[test]
+    fn expands_loop_lowered_self_method_path_as_expected() {
+        let method: syn::ImplItemFn = parse_quote! {
+            fn total(&self, acc: u64) -> u64 {
+                match &self.next {
+                    Some(next) => tailcall::call! { Self::total(next, acc + self.weight) },
+                    None => acc + self.weight,
+                }
+            }
+        };
+
+        let actual = apply_method_tailcall_transform(method, TailcallOptions::default());
+        let expected = quote! {
+            fn total(&self, acc: u64) -> u64 {
+                let mut __tailcall_self = self;
+                let mut acc = acc;
+                loop {
+                    let __tailcall_next = '__tailcall_body: {
+                        match &__tailcall_self.next {
+                            Some(next) => {
+                                let __tailcall_next_0 = next;
+                                let __tailcall_next_1 = acc + __tailcall_self.weight;
+                                break '__tailcall_body (__tailcall_next_0, __tailcall_next_1,);
+                            },
+                            None => return acc + __tailcall_self.weight,
+                        }
+                    };
+                    acc = __tailcall_next.1;
+                    __tailcall_self = __tailcall_next.0;
+                }
+            }
+
+            #[doc(hidden)]
+            #[allow(unused)]
+            #[inline(always)]
+            fn __tailcall_build_total_thunk<'tailcall>(&'tailcall self, acc: u64) -> tailcall::runtime::Thunk<'tailcall, u64> {
+                tailcall::runtime::Thunk::value(Self::total(self, acc))
+            }
+        };
+
+        assert_expansion_eq(actual, expected);
+    }
+
+    #[test]
+    fn expands_loop_lowered_turbofish_self_call_as_expected() {
+        let item_fn: syn::ItemFn = parse_quote! {
+            fn walk<T: Copy>(n: u32, value: T) -> T {
+                if n > 0 {
+                    tailcall::call! { walk::<T>(n - 1, value) }
+                } else {
+                    value
+                }
+            }
+        };
+
+        let actual = apply_fn_tailcall_transform(item_fn, TailcallOptions::default());
+        let expected = quote! {
+            fn walk<T: Copy>(n: u32, value: T) -> T {
+                let mut n = n;
+                let mut value = value;
+                loop {
+                    let __tailcall_next = '__tailcall_body: {
+                        if n > 0 {
+                            {
+                                let __tailcall_next_0 = n - 1;
+                                let __tailcall_next_1 = value;
+                                break '__tailcall_body (__tailcall_next_0, __tailcall_next_1,);
+                            }
+                        } else {
+                            return value
+                        }
+                    };
+                    value = __tailcall_next.1;
+                    n = __tailcall_next.0;
+                }
+            }
+
+            #[doc(hidden)]
+            #[allow(unused)]
+            #[inline(always)]
+            fn __tailcall_build_walk_thunk<'tailcall, T: Copy + 'tailcall,>(n: u32, value: T) -> tailcall::runtime::Thunk<'tailcall, T> {
+                tailcall::runtime::Thunk::value(walk(n, value))
+            }
+        };
+
+        assert_expansion_eq(actual, expected);
+    }
+
+    #[test]
+    fn expands_runtime_backed_pinned_receiver_as_expected() {
+        let method: syn::ImplItemFn = parse_quote! {
+            fn poll_even(self: Pin<&mut Self>, n: u32) -> bool {
+                if n == 0 {
+                    true
+                } else {
+                    tailcall::call! { self.poll_odd(n - 1) }
+                }
+            }
+        };
+
+        let actual = apply_method_tailcall_transform(method, TailcallOptions::default());
+        let expected = quote! {
+            fn poll_even(self: Pin<&mut Self>, n: u32) -> bool {
+                Self::__tailcall_build_poll_even_thunk(self, n).call()
+            }
+
+            #[doc(hidden)]
+            #[allow(unused)]
+            #[inline(always)]
+            fn __tailcall_build_poll_even_thunk<'tailcall>(self: Pin<&'tailcall mut Self>, n: u32) -> tailcall::runtime::Thunk<'tailcall, bool>
+            where
+                Self: 'tailcall
+            {
+                tailcall::runtime::Thunk::bounce(move || {
+                    if n == 0 {
+                        tailcall::runtime::Thunk::value(true)
+                    } else {
+                        tailcall::call! { self.poll_odd(n - 1) }
+                    }
+                })
+            }
+        };
+
+        assert_expansion_eq(actual, expected);
+    }
+
+    #[test]
+    fn expands_associated_functions_in_impl_blocks_as_expected() {
+        let item_impl: syn::ItemImpl = parse_quote! {
+            impl Parser {
+                fn new() -> Self {
+                    Self
+                }
+
+                #[tailcall]
+                fn countdown(n: u32) -> u32 {
+                    if n > 0 {
+                        tailcall::call! { Self::countdown(n - 1) }
+                    } else {
+                        0
+                    }
+                }
+
+                #[tailcall::tailcall]
+                fn skip(parser: &Parser, n: u32) -> u32 {
+                    tailcall::call! { parser.parse(n) }
+                }
+            }
+        };
+
+        let actual = apply_impl_tailcall_transform(item_impl);
+        let expected = quote! {
+            impl Parser {
+                fn new() -> Self {
+                    Self
+                }
+
+                fn countdown(n: u32) -> u32 {
+                    let mut n = n;
+                    loop {
+                        let __tailcall_next = '__tailcall_body: {
+                            if n > 0 {
+                                {
+                                    let __tailcall_next_0 = n - 1;
+                                    break '__tailcall_body (__tailcall_next_0,);
+                                }
+                            } else {
+                                return 0
+                            }
+                        };
+                        n = __tailcall_next.0;
+                    }
+                }
+
+                #[doc(hidden)]
+                #[allow(unused)]
+                #[inline(always)]
+                fn __tailcall_build_countdown_thunk<'tailcall>(n: u32) -> tailcall::runtime::Thunk<'tailcall, u32> {
+                    tailcall::runtime::Thunk::value(Self::countdown(n))
+                }
+
+                fn skip(parser: &Parser, n: u32) -> u32 {
+                    Self::__tailcall_build_skip_thunk(parser, n).call()
+                }
+
+                #[doc(hidden)]
+                #[allow(unused)]
+                #[inline(always)]
+                fn __tailcall_build_skip_thunk<'tailcall>(parser: &'tailcall Parser, n: u32) -> tailcall::runtime::Thunk<'tailcall, u32> {
+                    tailcall::runtime::Thunk::bounce_with((parser, n,), |(parser, n,)| {
+                        tailcall::call! { parser.parse(n) }
+                    })
+                }
+            }
+        };
+
+        assert_expansion_eq(actual, expected);
+    }
+
+    #[test]
+    fn expands_impl_generics_bounds_as_expected() {
+        let item_impl: syn::ItemImpl = parse_quote! {
+            impl<'a, T: Copy, const N: usize> Parser<'a, T, N> {
+                #[tailcall]
+                fn skip<U>(input: &'a [T; N], extra: U) -> usize {
+                    tailcall::call! { Self::parse(input, extra) }
+                }
+            }
+        };
+
+        let actual = apply_impl_tailcall_transform(item_impl);
+        let expected = quote! {
+            impl<'a, T: Copy, const N: usize> Parser<'a, T, N> {
+                fn skip<U>(input: &'a [T; N], extra: U) -> usize {
+                    Self::__tailcall_build_skip_thunk(input, extra).call()
+                }
+
+                #[doc(hidden)]
+                #[allow(unused)]
+                #[inline(always)]
+                fn __tailcall_build_skip_thunk<'tailcall, U,>(input: &'a [T; N], extra: U) -> tailcall::runtime::Thunk<'tailcall, usize>
+                where
+                    'a: 'tailcall,
+                    T: 'tailcall,
+                    U: 'tailcall
+                {
+                    tailcall::runtime::Thunk::bounce_with((input, extra,), |(input, extra,)| {
+                        tailcall::call! { Self::parse(input, extra) }
+                    })
+                }
+            }
+        };
+
+        assert_expansion_eq(actual, expected);
+    }
+
+    #[test]
+    fn expands_traits_and_trait_impls_as_expected() {
+        let item_trait: syn::ItemTrait = parse_quote! {
+            trait Node {
+                #[tailcall]
+                fn eval(&self, env: &Env) -> i64;
+
+                #[tailcall]
+                fn eval_fresh(&self, env: &Env) -> i64 {
+                    tailcall::call! { self.eval(env) }
+                }
+            }
+        };
+        let item_impl: syn::ItemImpl = parse_quote! {
+            impl Node for Neg {
+                #[tailcall]
+                fn eval(&self, env: &Env) -> i64 {
+                    match &self.child {
+                        Some(child) => tailcall::call! { child.eval(env) },
+                        None => 0,
+                    }
+                }
+            }
+        };
+
+        let item_trait = apply_trait_tailcall_transform(item_trait);
+        let item_impl = apply_impl_tailcall_transform(item_impl);
+        let expected_trait = quote! {
+            trait Node {
+                fn eval(&self, env: &Env) -> i64 {
+                    Self::__tailcall_build_eval_thunk(self, env).call()
+                }
+
+                #[doc(hidden)]
+                fn __tailcall_build_eval_thunk<'tailcall>(&'tailcall self, env: &'tailcall Env) -> tailcall::runtime::Thunk<'tailcall, i64>;
+
+                fn eval_fresh(&self, env: &Env) -> i64 {
+                    Self::__tailcall_build_eval_fresh_thunk(self, env).call()
+                }
+
+                #[doc(hidden)]
+                fn __tailcall_build_eval_fresh_thunk<'tailcall>(&'tailcall self, env: &'tailcall Env) -> tailcall::runtime::Thunk<'tailcall, i64> {
+                    tailcall::runtime::Thunk::bounce(move || {
+                        tailcall::call! { self.eval(env) }
+                    })
+                }
+            }
+        };
+        let expected_impl = quote! {
+            impl Node for Neg {
+                #[doc(hidden)]
+                #[inline(always)]
+                fn __tailcall_build_eval_thunk<'tailcall>(&'tailcall self, env: &'tailcall Env) -> tailcall::runtime::Thunk<'tailcall, i64> {
+                    tailcall::runtime::Thunk::bounce(move || {
+                        match &self.child {
+                            Some(child) => {
+                                let __tailcall_next_0 = env;
+                                child.__tailcall_build_eval_thunk(__tailcall_next_0)
+                            },
+                            None => tailcall::runtime::Thunk::value(0),
+                        }
+                    })
+                }
+            }
+        };
+
+        assert_expansion_eq(item_trait, expected_trait);
+        assert_expansion_eq(item_impl, expected_impl);
+    }
+
+    #[test]
+    fn rejects_trait_methods_that_cannot_be_called_through_dyn() {
+        let item_trait: syn::ItemTrait = parse_quote! {
+            trait Node {
+                #[tailcall]
+                fn eval(self, depth: u32) -> i64;
+
+                #[tailcall]
+                fn visit<V: Visitor>(&self, visitor: V) -> i64;
+
+                #[tailcall(expose_thunk)]
+                fn count(&self, depth: u32) -> u32;
+            }
+        };
+
+        let actual = apply_trait_tailcall_transform(item_trait).to_string();
+
+        assert!(actual.contains("#[tailcall] trait methods must take `&self` or `&mut self`"));
+        assert!(actual.contains("#[tailcall] trait methods cannot have type or const parameters"));
+        assert!(actual.contains("`expose_thunk` is not supported on trait methods"));
+    }
+
+    #[test]
+    fn recovers_failed_expansions_with_plain_calls() {
+        let item_fn: syn::ItemFn = parse_quote! {
+            fn sum(n: u64) -> u64 {
+                if n == 0 {
+                    0
+                } else {
+                    n + tailcall::call! { sum(n - 1) }
+                }
+            }
+        };
+
+        let actual = apply_fn_tailcall_transform(item_fn, TailcallOptions::default());
+        let error = Error::new(
+            Span::call_site(),
+            "tailcall::call! must be used in tail position",
+        )
+        .to_compile_error();
+        let expected = quote! {
+            #error
+            fn sum(n: u64) -> u64 {
+                if n == 0 {
+                    0
+                } else {
+                    n + sum(n - 1)
+                }
+            }
+
+            #[doc(hidden)]
+            #[allow(unused)]
+            #[inline(always)]
+            fn __tailcall_build_sum_thunk<'tailcall>(n: u64) -> tailcall::runtime::Thunk<'tailcall, u64> {
+                tailcall::runtime::Thunk::value(sum(n))
+            }
+        };
+
+        assert_expansion_eq(actual, expected);
+    }
+
+    #[test]
+    fn recovers_failed_method_and_impl_expansions() {
+        let method: syn::ImplItemFn = parse_quote! {
+            fn walk(&self, (a, b): (u32, u32)) -> u32 {
+                tailcall::call! { self.walk((b, a)) }
+            }
+        };
+        let item_impl: syn::ItemImpl = parse_quote! {
+            impl Iterator for Parser {
+                type Item = u32;
+
+                #[tailcall]
+                fn next(&mut self) -> Option<u32> {
+                    tailcall::call! { self.next() }.map(|n| n + 1)
+                }
+            }
+        };
+
+        let method = apply_method_tailcall_transform(method, TailcallOptions::default());
+        let item_impl = apply_impl_tailcall_transform(item_impl);
+        let error = |message: &str| Error::new(Span::call_site(), message).to_compile_error();
+        let expected_method = {
+            let error = error("#[tailcall] only supports simple identifier arguments");
+            quote! {
+                #error
+                fn walk(&self, (a, b): (u32, u32)) -> u32 {
+                    self.walk((b, a))
+                }
+            }
+        };
+        let expected_impl = {
+            let error = error("tailcall::call! must be used in tail position");
+            quote! {
+                impl Iterator for Parser {
+                    type Item = u32;
+
+                    #error
+                    fn next(&mut self) -> Option<u32> {
+                        self.next().map(|n| n + 1)
+                    }
+
+                    #[doc(hidden)]
+                    #[allow(unused)]
+                    #[inline(always)]
+                    fn __tailcall_build_next_thunk<'tailcall>(&'tailcall mut self) -> tailcall::runtime::Thunk<'tailcall, Option<u32> > {
+                        tailcall::runtime::Thunk::value(Self::next(self))
+                    }
+                }
+            }
+        };
+
+        assert_expansion_eq(method, expected_method);
+        assert_expansion_eq(item_impl, expected_impl);
+    }
+
+    #[test]
+    fn rejects_runtime_tail_call_arguments_that_borrow_locals() {
+        let item_fn: syn::ItemFn = parse_quote! {
+            fn is_even(x: u32, name: &str) -> bool {
+                let label = format!("{name}!");
+                if x == 0 {
+                    true
+                } else {
+                    tailcall::call! { is_odd(x - 1, &label) }
+                }
+            }
+        };
+
+        let actual = apply_fn_tailcall_transform(item_fn, TailcallOptions::default()).to_string();
+
+        assert!(actual.contains("borrows `label`, which is dropped before the call runs"));
+    }
+
+    #[test]
+    fn expands_helper_attributes_as_expected() {
+        let item_fn: syn::ItemFn = parse_quote! {
+            #[deprecated]
+            #[cfg(feature = "parity")]
+            #[track_caller]
+            fn is_even(x: u32) -> bool {
+                if x == 0 {
+                    true
+                } else {
+                    tailcall::call! { is_odd(x - 1) }
+                }
+            }
+        };
+        let options =
+            TailcallOptions::parse(quote!(helper_attrs(deprecated))).expect("options should parse");
+
+        let actual = apply_fn_tailcall_transform(item_fn, options);
+        let expected = quote! {
+            #[deprecated]
+            #[cfg(feature = "parity")]
+            #[track_caller]
+            #[allow(deprecated)]
+            fn is_even(x: u32) -> bool {
+                __tailcall_build_is_even_thunk(x).call()
+            }
+
+            #[deprecated]
+            #[cfg(feature = "parity")]
+            #[doc(hidden)]
+            #[allow(unused)]
+            #[inline(always)]
+            fn __tailcall_build_is_even_thunk<'tailcall>(x: u32) -> tailcall::runtime::Thunk<'tailcall, bool> {
+                tailcall::runtime::Thunk::bounce_with((x,), |(x,)| {
+                    if x == 0 {
+                        tailcall::runtime::Thunk::value(true)
+                    } else {
+                        tailcall::call! { is_odd(x - 1) }
+                    }
+                })
+            }
+        };
+
+        assert_expansion_eq(actual, expected);
+    }
+
+    #[test]
+    fn expands_runtime_backed_extern_c_function_as_expected() {
+        let item_fn: syn::ItemFn = parse_quote! {
+            pub extern "C" fn is_even(x: u32) -> bool {
+                if x == 0 {
+                    true
+                } else {
+                    tailcall::call! { is_odd(x - 1) }
+                }
+            }
+        };
+
+        let actual = apply_fn_tailcall_transform(item_fn, TailcallOptions::default());
+        let expected = quote! {
+            pub extern "C" fn is_even(x: u32) -> bool {
+                __tailcall_build_is_even_thunk(x).call()
+            }
+
+            #[doc(hidden)]
+            #[allow(unused)]
+            #[inline(always)]
+            fn __tailcall_build_is_even_thunk<'tailcall>(x: u32) -> tailcall::runtime::Thunk<'tailcall, bool> {
+                tailcall::runtime::Thunk::bounce_with((x,), |(x,)| {
+                    if x == 0 {
+                        tailcall::runtime::Thunk::value(true)
+                    } else {
+                        tailcall::call! { is_odd(x - 1) }
+                    }
+                })
+            }
+        };
+
+        assert_expansion_eq(actual, expected);
+    }
+
+    #[test]
+    fn expands_runtime_backed_unsafe_function_as_expected() {
+        let item_fn: syn::ItemFn = parse_quote! {
+            unsafe extern "C" fn skip(ptr: *const u8, len: usize) -> usize {
+                if len == 0 {
+                    0
+                } else {
+                    unsafe { tailcall::call! { take(ptr.add(1), len - 1) } }
+                }
+            }
+        };
+
+        let actual = apply_fn_tailcall_transform(item_fn, TailcallOptions::default());
+        let expected = quote! {
+            unsafe extern "C" fn skip(ptr: *const u8, len: usize) -> usize {
+                #[allow(unused_unsafe)]
+                unsafe { __tailcall_build_skip_thunk(ptr, len).call() }
+            }
+
+            #[doc(hidden)]
+            #[allow(unused)]
+            #[inline(always)]
+            unsafe fn __tailcall_build_skip_thunk<'tailcall>(ptr: *const u8, len: usize) -> tailcall::runtime::Thunk<'tailcall, usize> {
+                tailcall::runtime::Thunk::bounce_with((ptr, len,), |(ptr, len,)| {
+                    if len == 0 {
+                        tailcall::runtime::Thunk::value(0)
+                    } else {
+                        unsafe { tailcall::call! { take(ptr.add(1), len - 1) } }
+                    }
+                })
+            }
+        };
+
+        assert_expansion_eq(actual, expected);
+    }
+
+    #[test]
+    fn expands_loop_lowered_mutable_reborrow_as_expected() {
+        let item_fn: syn::ItemFn = parse_quote! {
+            fn bump_all(rest: &mut [u32], bumped: usize) -> usize {
+                if rest.is_empty() {
+                    bumped
+                } else {
+                    rest[0] += 1;
+                    tailcall::call! { bump_all(&mut rest[1..], bumped + 1) }
+                }
+            }
+        };
+
+        let actual = apply_fn_tailcall_transform(item_fn, TailcallOptions::default());
+        let expected = quote! {
+            fn bump_all(rest: &mut [u32], bumped: usize) -> usize {
+                let mut rest = rest;
+                let mut bumped = bumped;
+                loop {
+                    let __tailcall_next = '__tailcall_body: {
+                        if rest.is_empty() {
+                            return bumped
+                        } else {
+                            rest[0] += 1;
+                            {
+                                let __tailcall_next_0 = &mut rest[1..];
+                                let __tailcall_next_1 = bumped + 1;
+                                break '__tailcall_body (__tailcall_next_0, __tailcall_next_1,);
+                            }
+                        }
+                    };
+                    bumped = __tailcall_next.1;
+                    rest = __tailcall_next.0;
+                }
+            }
+
+            #[doc(hidden)]
+            #[allow(unused)]
+            #[inline(always)]
+            fn __tailcall_build_bump_all_thunk<'tailcall>(rest: &'tailcall mut [u32], bumped: usize) -> tailcall::runtime::Thunk<'tailcall, usize> {
+                tailcall::runtime::Thunk::value(bump_all(rest, bumped))
+            }
+        };
+
+        assert_expansion_eq(actual, expected);
+    }
+
+    #[test]
+    fn expands_loop_lowered_unsafe_function_as_expected() {
+        let item_fn: syn::ItemFn = parse_quote! {
+            unsafe fn skip(ptr: *const u8, len: usize) -> *const u8 {
+                if len == 0 {
+                    ptr
+                } else {
+                    unsafe { tailcall::call! { skip(ptr.add(1), len - 1) } }
+                }
+            }
+        };
+
+        let actual = apply_fn_tailcall_transform(item_fn, TailcallOptions::default());
+        let expected = quote! {
+            unsafe fn skip(ptr: *const u8, len: usize) -> *const u8 {
+                let mut ptr = ptr;
+                let mut len = len;
+                loop {
+                    let __tailcall_next = '__tailcall_body: {
+                        if len == 0 {
+                            return ptr
+                        } else {
+                            {
+                                #[allow(unused_unsafe)]
+                                unsafe {
+                                    {
+                                        let __tailcall_next_0 = ptr.add(1);
+                                        let __tailcall_next_1 = len - 1;
+                                        break '__tailcall_body (__tailcall_next_0, __tailcall_next_1,);
+                                    }
+                                }
+                            }
+                        }
+                    };
+                    len = __tailcall_next.1;
+                    ptr = __tailcall_next.0;
+                }
+            }
+
+            #[doc(hidden)]
+            #[allow(unused)]
+            #[inline(always)]
+            unsafe fn __tailcall_build_skip_thunk<'tailcall>(ptr: *const u8, len: usize) -> tailcall::runtime::Thunk<'tailcall, *const u8> {
+                #[allow(unused_unsafe)]
+                unsafe { tailcall::runtime::Thunk::value(skip(ptr, len)) }
+            }
+        };
+
+        assert_expansion_eq(actual, expected);
+    }
+
+    #[test]
+    fn expands_check_option_as_expected() {
+        let loop_fn: syn::ItemFn = parse_quote! {
+            fn countdown(n: u64) -> Option<u64> {
+                if n == 0 {
+                    Some(0)
+                } else {
+                    tailcall::call! { countdown(n - 1) }
+                }
+            }
+        };
+        let runtime_fn: syn::ItemFn = parse_quote! {
+            fn is_even(x: u32) -> Option<bool> {
+                if x == 0 {
+                    Some(true)
+                } else {
+                    tailcall::call! { is_odd(x - 1) }
+                }
+            }
+        };
+        let options = || TailcallOptions::parse(quote!(check = cancel::check)).unwrap();
+
+        let actual = apply_fn_tailcall_transform(loop_fn, options());
+        let expected = quote! {
+            fn countdown(n: u64) -> Option<u64> {
+                let mut n = n;
+                loop {
+                    let __tailcall_next = '__tailcall_body: {
+                        if let ::core::ops::ControlFlow::Break(__tailcall_value) = cancel::check() {
+                            return __tailcall_value;
+                        }
+                        if n == 0 {
+                            return Some(0)
+                        } else {
+                            {
+                                let __tailcall_next_0 = n - 1;
+                                break '__tailcall_body (__tailcall_next_0,);
+                            }
+                        }
+                    };
+                    n = __tailcall_next.0;
+                }
+            }
+
+            #[doc(hidden)]
+            #[allow(unused)]
+            #[inline(always)]
+            fn __tailcall_build_countdown_thunk<'tailcall>(n: u64) -> tailcall::runtime::Thunk<'tailcall, Option<u64> > {
+                tailcall::runtime::Thunk::value(countdown(n))
+            }
+        };
+
+        assert_expansion_eq(actual, expected);
+
+        let actual = apply_fn_tailcall_transform(runtime_fn, options());
+        let expected = quote! {
+            fn is_even(x: u32) -> Option<bool> {
+                __tailcall_build_is_even_thunk(x).call()
+            }
+
+            #[doc(hidden)]
+            #[allow(unused)]
+            #[inline(always)]
+            fn __tailcall_build_is_even_thunk<'tailcall>(x: u32) -> tailcall::runtime::Thunk<'tailcall, Option<bool> > {
+                tailcall::runtime::Thunk::bounce_with((x,), |(x,)| {
+                    if let ::core::ops::ControlFlow::Break(__tailcall_value) = cancel::check() {
+                        return tailcall::runtime::Thunk::value(__tailcall_value);
+                    }
+                    if x == 0 {
+                        tailcall::runtime::Thunk::value(Some(true))
+                    } else {
+                        tailcall::call! { is_odd(x - 1) }
+                    }
+                })
+            }
+        };
+
+        assert_expansion_eq(actual, expected);
+    }
+
+    #[test]
+    fn expands_boxed_runtime_option_as_expected() {
+        let item_fn: syn::ItemFn = parse_quote! {
+            fn is_even(x: u32) -> bool {
+                if x == 0 {
+                    true
+                } else {
+                    tailcall::call! { is_odd(x - 1) }
+                }
+            }
+        };
+
+        let actual = apply_fn_tailcall_transform(
+            item_fn,
+            TailcallOptions::parse(quote!(runtime = "boxed")).expect("options should parse"),
+        );
+        let expected = quote! {
+            fn is_even(x: u32) -> bool {
+                __tailcall_build_is_even_thunk(x).call()
+            }
+
+            #[doc(hidden)]
+            #[allow(unused)]
+            #[inline(always)]
+            fn __tailcall_build_is_even_thunk<'tailcall>(x: u32) -> tailcall::runtime::BoxThunk<'tailcall, bool> {
+                tailcall::runtime::BoxThunk::bounce(move || {
+                    if x == 0 {
+                        tailcall::runtime::BoxThunk::value(true)
+                    } else {
+                        tailcall::call! { is_odd(x - 1) }
+                    }
+                })
+            }
+        };
+
+        assert_expansion_eq(actual, expected);
+    }
+
+    #[test]
+    fn expands_custom_runtime_option_as_expected() {
+        let item_fn: syn::ItemFn = parse_quote! {
+            fn is_even(x: u32) -> bool {
+                if x == 0 {
+                    true
+                } else {
+                    tailcall::call! { is_odd(x - 1) }
+                }
+            }
+        };
+
+        let actual = apply_fn_tailcall_transform(
+            item_fn,
+            TailcallOptions::parse(quote!(runtime = arena::ArenaThunk))
+                .expect("options should parse"),
+        );
+        let expected = quote! {
+            fn is_even(x: u32) -> bool {
+                tailcall::runtime::Trampoline::run(__tailcall_build_is_even_thunk(x))
+            }
+
+            #[doc(hidden)]
+            #[allow(unused)]
+            #[inline(always)]
+            fn __tailcall_build_is_even_thunk<'tailcall>(x: u32) -> arena::ArenaThunk<'tailcall, bool> {
+                tailcall::runtime::Trampoline::bounce(move || {
+                    if x == 0 {
+                        tailcall::runtime::Trampoline::value(true)
+                    } else {
+                        tailcall::call! { is_odd(x - 1) }
+                    }
+                })
+            }
+        };
+
+        assert_expansion_eq(actual, expected);
+    }
+
+    #[test]
+    fn expands_arena_option_as_expected() {
+        let item_fn: syn::ItemFn = parse_quote! {
+            fn parse(scratch: &Arena, state: [u64; 4]) -> u64 {
+                tailcall::call! { parse_more(scratch, state) }
+            }
+        };
+
+        let actual = apply_fn_tailcall_transform(
+            item_fn,
+            TailcallOptions::parse(quote!(arena = scratch)).expect("options should parse"),
+        );
+        let expected = quote! {
+            fn parse(scratch: &Arena, state: [u64; 4]) -> u64 {
+                __tailcall_build_parse_thunk(scratch, state).call()
+            }
+
+            #[doc(hidden)]
+            #[allow(unused)]
+            #[inline(always)]
+            fn __tailcall_build_parse_thunk<'tailcall>(scratch: &'tailcall Arena, state: [u64; 4]) -> tailcall::runtime::Thunk<'tailcall, u64> {
+                tailcall::runtime::Arena::bounce(scratch, move || {
+                    tailcall::call! { parse_more(scratch, state) }
+                })
+            }
+        };
+
+        assert_expansion_eq(actual, expected);
+    }
+
+    #[test]
+    fn expands_crate_and_call_macro_options_as_expected() {
+        let item_fn: syn::ItemFn = parse_quote! {
+            fn is_even(x: u32) -> bool {
+                match x {
+                    0 => true,
+                    _ => tail! { is_odd(x - 1) },
+                }
+            }
+        };
+        let options = TailcallOptions::parse(quote!(crate = facade::tc, call_macro = tail))
+            .expect("options should parse");
+
+        let actual = apply_fn_tailcall_transform(item_fn, options);
+        let expected = quote! {
+            fn is_even(x: u32) -> bool {
+                __tailcall_build_is_even_thunk(x).call()
+            }
+
+            #[doc(hidden)]
+            #[allow(unused)]
+            #[inline(always)]
+            fn __tailcall_build_is_even_thunk<'tailcall>(x: u32) -> facade::tc::runtime::Thunk<'tailcall, bool> {
+                facade::tc::runtime::Thunk::bounce_with((x,), |(x,)| {
+                    match x {
+                        0 => facade::tc::runtime::Thunk::value(true),
+                        _ => {
+                            let __tailcall_next_0 = x - 1;
+                            __tailcall_build_is_odd_thunk(__tailcall_next_0)
+                        },
+                    }
+                })
+            }
+        };
+
+        assert_expansion_eq(actual, expected);
+    }
+
+    #[test]
+    fn expands_expose_thunk_option_as_expected() {
+        let item_fn: syn::ItemFn = parse_quote! {
+            pub fn r#type(x: u32) -> bool {
+                match x {
+                    0 => true,
+                    _ => tailcall::call! { is_odd(x - 1) },
+                }
+            }
+        };
+        let options = TailcallOptions::parse(quote!(expose_thunk)).expect("options should parse");
+
+        let actual = apply_fn_tailcall_transform(item_fn, options);
+        let expected = quote! {
+            pub fn r#type(x: u32) -> bool {
+                __tailcall_build_type_thunk(x).call()
+            }
+
+            #[doc(hidden)]
+            #[allow(unused)]
+            #[inline(always)]
+            fn __tailcall_build_type_thunk<'tailcall>(x: u32) -> tailcall::runtime::Thunk<'tailcall, bool> {
+                tailcall::runtime::Thunk::bounce_with((x,), |(x,)| {
+                    match x {
+                        0 => tailcall::runtime::Thunk::value(true),
+                        _ => {
+                            let __tailcall_next_0 = x - 1;
+                            __tailcall_build_is_odd_thunk(__tailcall_next_0)
+                        },
+                    }
+                })
+            }
+
+            #[doc = "Returns a call to [`r#type`] as a thunk.\n\nA hand-written thunk builder can return it to make the call in tail position. If `r#type` only calls itself, it is lowered to a loop, which runs as soon as the thunk is built."]
+            #[inline(always)]
+            pub fn type_thunk<'tailcall>(x: u32) -> tailcall::runtime::Thunk<'tailcall, bool> {
+                __tailcall_build_type_thunk(x)
+            }
+        };
+
+        assert_expansion_eq(actual, expected);
+    }
+
+    #[test]
+    fn expands_hybrid_option_as_expected() {
+        let item_fn: syn::ItemFn = parse_quote! {
+            fn is_even(x: u32) -> bool {
+                if x == 0 {
+                    true
+                } else {
+                    tailcall::call! { is_odd(x - 1) }
+                }
+            }
+        };
+
+        let actual = apply_fn_tailcall_transform(
+            item_fn,
+            TailcallOptions::parse(quote!(hybrid = 32)).expect("options should parse"),
+        );
+        let expected = quote! {
+            fn is_even(x: u32) -> bool {
+                __tailcall_build_is_even_thunk(x).call()
+            }
+
+            #[doc(hidden)]
+            #[allow(unused)]
+            #[inline(always)]
+            fn __tailcall_build_is_even_thunk<'tailcall>(x: u32) -> tailcall::runtime::Thunk<'tailcall, bool> {
+                tailcall::runtime::Thunk::call_or_bounce(32, move || {
+                    if x == 0 {
+                        tailcall::runtime::Thunk::value(true)
+                    } else {
+                        tailcall::call! { is_odd(x - 1) }
+                    }
+                })
+            }
+        };
+
+        assert_expansion_eq(actual, expected);
+    }
+
+    #[test]
+    fn expands_trace_option_as_expected() {
+        let item_fn: syn::ItemFn = parse_quote! {
+            pub fn skip(rest: &[u8], skipped: usize) -> usize {
+                match rest {
+                    [b' ', tail @ ..] => tailcall::call! { skip(tail, skipped + 1) },
+                    _ => skipped,
+                }
+            }
+        };
+
+        let actual = apply_fn_tailcall_transform(
+            item_fn,
+            TailcallOptions::parse(quote!(trace)).expect("options should parse"),
+        );
+        let expected = quote! {
+            pub fn skip(rest: &[u8], skipped: usize) -> usize {
+                let mut rest = rest;
+                let mut skipped = skipped;
+                loop {
+                    let __tailcall_next = '__tailcall_body: {
+                        match rest {
+                            [b' ', tail @ ..] => {
+                                let __tailcall_next_0 = tail;
+                                let __tailcall_next_1 = skipped + 1;
+                                break '__tailcall_body (__tailcall_next_0, __tailcall_next_1,);
+                            },
+                            _ => return skipped,
+                        }
+                    };
+                    skipped = __tailcall_next.1;
+                    rest = __tailcall_next.0;
+                }
+            }
+
+            #[doc(hidden)]
+            #[allow(unused)]
+            #[inline(always)]
+            fn __tailcall_build_skip_thunk<'tailcall>(rest: &'tailcall [u8], skipped: usize) -> tailcall::runtime::Thunk<'tailcall, usize> {
+                tailcall::runtime::Thunk::value(skip(rest, skipped))
+            }
+
+            #[doc = "One step of [`skip`]: the arguments of a logical call, or the returned value."]
+            #[derive(Debug)]
+            pub enum SkipStep<'tailcall> {
+                /// A logical call with these arguments.
+                Call {
+                    #[doc = "The `rest` argument."]
+                    rest: &'tailcall [u8],
+                    #[doc = "The `skipped` argument."]
+                    skipped: usize
+                },
+                /// The value returned by the outermost call.
+                Done(usize),
+            }
+
+            #[doc = "Returns the arguments of every logical call [`skip`] makes for these arguments, followed by the value it returns."]
+            pub fn skip_steps<'tailcall>(rest: &'tailcall [u8], skipped: usize) -> impl ::core::iter::Iterator<Item = SkipStep<'tailcall> > {
+                let mut __tailcall_pending =
+                    ::core::option::Option::Some(SkipStep::Call { rest, skipped });
+
+                ::core::iter::from_fn(move || {
+                    let __tailcall_step = __tailcall_pending.take()?;
+
+                    if let SkipStep::Call { rest, skipped } = &__tailcall_step {
+                        let rest = ::core::clone::Clone::clone(rest);
+                        let skipped = ::core::clone::Clone::clone(skipped);
+                        __tailcall_pending = ::core::option::Option::Some('__tailcall_step: {
+                            match rest {
+                                [b' ', tail @ ..] => {
+                                    let __tailcall_next_0 = tail;
+                                    let __tailcall_next_1 = skipped + 1;
+                                    break '__tailcall_step SkipStep::Call {
+                                        rest: __tailcall_next_0,
+                                        skipped: __tailcall_next_1
+                                    };
+                                },
+                                _ => break '__tailcall_step SkipStep::Done(skipped),
+                            }
+                        });
+                    }
+
+                    ::core::option::Option::Some(__tailcall_step)
+                })
+            }
+        };
+
+        assert_expansion_eq(actual, expected);
+    }
+
+    #[test]
+    fn rejects_trace_option_where_steps_cannot_be_generated() {
+        let mutual: syn::ItemFn = parse_quote! {
+            fn is_even(x: u32) -> bool {
+                if x == 0 { true } else { tailcall::call! { is_odd(x - 1) } }
+            }
+        };
+        let method: syn::ImplItemFn = parse_quote! {
+            fn countdown(&self, n: u32) -> u32 {
+                if n == 0 { 0 } else { tailcall::call! { self.countdown(n - 1) } }
+            }
+        };
+        let options = || TailcallOptions::parse(quote!(trace)).expect("options should parse");
+
+        let mutual = apply_fn_tailcall_transform(mutual, options()).to_string();
+        let method = apply_method_tailcall_transform(method, options()).to_string();
+
+        assert!(mutual.contains("`trace` requires a function whose tail calls only call itself"));
+        assert!(method.contains("`trace` is only supported on free functions"));
+    }
 }
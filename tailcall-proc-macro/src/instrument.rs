@@ -0,0 +1,36 @@
+use syn::{ext::IdentExt, parse_quote, Ident, LitStr, Path, Stmt};
+
+// Statements spliced in front of every logical call of a transformed function (each loop
+// iteration or each bounce) by the optional instrumentation features:
+//
+// - `debug-trace` records the call in `tailcall::trace`
+// - `stats` increments a per-function counter reported by `tailcall::stats`
+//
+// `file!()` and `line!()` resolve to the `#[tailcall]` attribute, since the statements carry
+// call-site spans. The counter is a `static` inside the function body, so every monomorphized
+// copy of a generic function shares it.
+pub fn instrumentation_stmts(fn_ident: &Ident, crate_path: &Path) -> Vec<Stmt> {
+    let name = LitStr::new(&fn_ident.unraw().to_string(), fn_ident.span());
+    let mut stmts = Vec::new();
+
+    if cfg!(feature = "debug-trace") {
+        stmts.push(parse_quote! {
+            #crate_path::trace::record(concat!(module_path!(), "::", #name), file!(), line!());
+        });
+    }
+
+    if cfg!(feature = "stats") {
+        stmts.push(parse_quote! {
+            {
+                static __TAILCALL_COUNTER: #crate_path::stats::Counter = #crate_path::stats::Counter::new(
+                    concat!(module_path!(), "::", #name),
+                    file!(),
+                    line!(),
+                );
+                __TAILCALL_COUNTER.hit();
+            }
+        });
+    }
+
+    stmts
+}
@@ -19,13 +19,19 @@ extern crate proc_macro;
 mod analyze;
 mod call_syntax;
 mod expand;
+mod instrument;
+mod locals;
 mod loop_lower;
 mod naming;
+mod options;
+mod recover;
 mod rewrite;
 mod signature;
+mod steps;
 
+use options::TailcallOptions;
 use proc_macro::TokenStream;
-use syn::{parse_macro_input, ImplItemFn, ItemFn};
+use syn::{parse_macro_input, ImplItemFn, ItemFn, ItemImpl, ItemTrait};
 
 /// Transforms a [function definition] so that explicit tail-call sites can execute without
 /// growing the call stack.
@@ -41,6 +47,15 @@ use syn::{parse_macro_input, ImplItemFn, ItemFn};
 /// strategy. Some transformed functions compile down to a loop, while others bounce through the
 /// thunk runtime.
 ///
+/// Placed on an inherent `impl` block, the attribute expands every item inside it that is itself
+/// marked with `#[tailcall]`. This is required for associated functions without a receiver,
+/// whose hidden helpers have to be reached through `Self::`.
+///
+/// Placed on a trait, it declares an object-safe hidden helper next to every method marked with
+/// `#[tailcall]` and turns the method into a provided one that runs it, so tail calls can go
+/// through `dyn Trait`. The trait's impl blocks then need `#[tailcall]` too, which makes them
+/// define only the helpers for their marked methods.
+///
 /// For methods, the optimized path aliases the receiver once, reuses the non-receiver arguments
 /// as mutable loop state, and rewrites each direct self tail call into "compute the next
 /// arguments, assign them, and continue".
@@ -64,10 +79,133 @@ use syn::{parse_macro_input, ImplItemFn, ItemFn};
 /// }
 /// ```
 ///
+/// # Options
+///
+/// - `helper_attrs(name, ...)`: copies the named attributes from the item onto its hidden helper.
+///   `#[cfg]` is always copied, and `#[cfg_attr]` is copied with only the attributes that would be
+///   copied on their own.
+///
+/// ```ignore
+/// #[track_caller]
+/// #[tailcall(helper_attrs(track_caller))]
+/// fn countdown(input: u64) -> u64 {
+///     // ...
+/// }
+/// ```
+///
+/// - `check = path`: calls `path()` before every logical call, that is each loop iteration or each
+///   bounce. It returns `ControlFlow<R>` for the function's return type `R`; `Break(value)` stops
+///   the recursion and makes the outermost call return `value`.
+///
+/// ```ignore
+/// fn cancelled() -> ControlFlow<Option<u64>> {
+///     // e.g. check an atomic flag or a deadline
+/// }
+///
+/// #[tailcall(check = cancelled)]
+/// fn countdown(input: u64) -> Option<u64> {
+///     // ...
+/// }
+/// ```
+///
+/// - `runtime = "boxed"`: makes the hidden helper return `tailcall::runtime::BoxThunk` instead of
+///   `Thunk`, so each bounce is allocated and its arguments are not limited by the inline capture
+///   budget. This needs the `alloc` feature of `tailcall`. Functions that tail-call each other must
+///   use the same runtime. `runtime = "inline"` selects the default.
+/// - `runtime = path::To::Runtime`: makes the hidden helper return `Runtime<'tailcall, R>` for any
+///   type implementing `tailcall::runtime::Trampoline`, and builds and runs it through that trait.
+///
+/// ```ignore
+/// #[tailcall(runtime = "boxed")]
+/// fn fold(n: u32, lanes: [u64; 8]) -> u64 {
+///     // ...
+/// }
+///
+/// #[tailcall(runtime = my_runtime::CountedThunk)]
+/// fn countdown(input: u64) -> u64 {
+///     // ...
+/// }
+/// ```
+///
+/// - `arena = name`: bounces through `tailcall::runtime::Arena::bounce(name, ...)`, where `name`
+///   is an argument of type `&Arena`. Bounces whose arguments do not fit the inline capture budget
+///   are moved into the arena's buffer instead of panicking. Only works with the inline runtime.
+///
+/// ```ignore
+/// #[tailcall(arena = scratch)]
+/// fn parse(scratch: &Arena<'_>, state: ParserState) -> Token {
+///     // ...
+/// }
+/// ```
+///
+/// - `hybrid` or `hybrid = depth`: makes up to `depth` (64 by default) nested calls on the native
+///   stack before falling back to bounces, through `tailcall::runtime::Thunk::call_or_bounce`.
+///   Only works with the inline runtime and without `arena`.
+///
+/// ```ignore
+/// #[tailcall(hybrid = 128)]
+/// fn is_even(x: u32) -> bool {
+///     // ...
+/// }
+/// ```
+///
+/// - `crate = path`: names the `tailcall` crate in generated code, for crates that reach it through
+///   a renamed dependency or a facade that re-exports it. A facade's own `macro_rules!` can pass
+///   `crate = $crate::path::to::tailcall`.
+/// - `call_macro = path`: only treats invocations of exactly `path!` as tail-call sites. By
+///   default, any macro path ending in `call` is one, such as `call!`, `tailcall::call!`, or
+///   `facade::call!`. Naming the macro is needed for a renamed import, or when the function also
+///   uses an unrelated `call!` macro.
+///
+/// ```ignore
+/// use facade::call as tail;
+///
+/// #[facade::tailcall(crate = facade, call_macro = tail)]
+/// fn countdown(input: u64) -> u64 {
+///     if input == 0 {
+///         0
+///     } else {
+///         tail! { countdown(input - 1) }
+///     }
+/// }
+/// ```
+///
+/// - `trace`: for a free function that only tail-calls itself, also generates a `{Name}Step`
+///   enum and a `{name}_steps` function with the same arguments. The function returns an iterator
+///   over `Call { args }` for every logical call, followed by `Done(value)`. Argument types must
+///   implement `Clone` and `Debug`, and the return type `Debug`.
+///
+/// ```ignore
+/// #[tailcall(trace)]
+/// fn gcd(a: u64, b: u64) -> u64 {
+///     // ...
+/// }
+///
+/// for step in gcd_steps(12, 18) {
+///     println!("{step:?}"); // `Call { a: 12, b: 18 }`, ..., `Done(6)`
+/// }
+/// ```
+///
+/// - `expose_thunk`: also generates a `{name}_thunk` function with the item's visibility and
+///   arguments, which returns the call as a `tailcall::runtime::Thunk` instead of running it. A
+///   hand-written thunk builder can return it in tail position. Raw identifiers drop their `r#`,
+///   so `r#type` gets `type_thunk`.
+///
+/// ```ignore
+/// #[tailcall(expose_thunk)]
+/// pub fn is_even(n: u32) -> bool {
+///     // ...
+/// }
+///
+/// fn is_even_by_twos(n: u32) -> Thunk<'static, bool> {
+///     Thunk::bounce(move || if n < 2 { is_even_thunk(n) } else { is_even_by_twos(n - 2) })
+/// }
+/// ```
+///
 /// # Requirements
 ///
 /// - Tail-call sites must be written with `tailcall::call!` and left in [tail form]:
-///   `tailcall::call!` currently supports direct function paths and method syntax on `self`.
+///   `tailcall::call!` currently supports direct function paths and method syntax.
 ///
 /// ```compile_fail
 /// use tailcall::tailcall;
@@ -109,7 +247,26 @@ use syn::{parse_macro_input, ImplItemFn, ItemFn};
 /// }
 /// ```
 ///
-/// - Methods in `impl` blocks are supported, but trait methods are not:
+/// - Tail-call arguments cannot borrow the caller's locals or parameters, since those are dropped
+///   before the call runs:
+///
+/// ```compile_fail
+/// use tailcall::tailcall;
+///
+/// #[tailcall]
+/// fn shout(text: &str, times: u32) -> usize {
+///     let louder = format!("{text}!");
+///     if times == 0 {
+///         louder.len()
+///     } else {
+///         tailcall::call! { shout(&louder, times - 1) }
+/// //                          ^^^^^^^ `louder` is dropped before the call runs.
+///     }
+/// }
+/// ```
+///
+/// - Trait methods need `#[tailcall]` on the trait and on the impl block as well, which only
+///   supports methods taking `&self` or `&mut self`:
 ///
 /// ```compile_fail
 /// use tailcall::tailcall;
@@ -125,7 +282,7 @@ use syn::{parse_macro_input, ImplItemFn, ItemFn};
 /// impl Factorialable for u64 {
 ///     #[tailcall]
 ///     fn calc_factorial(self, accumulator: u64) -> u64 {
-/// //                    ^^^^ Trait methods are not supported yet.
+/// //                    ^^^^ Neither the trait nor the impl block is marked.
 ///         if self > 0 {
 ///             (self - 1).calc_factorial(self * accumulator)
 ///         } else {
@@ -165,39 +322,99 @@ use syn::{parse_macro_input, ImplItemFn, ItemFn};
 ///
 /// [tail form]: https://en.wikipedia.org/wiki/Tail_call
 #[proc_macro_attribute]
-pub fn tailcall(_attr: TokenStream, tokens: TokenStream) -> TokenStream {
+pub fn tailcall(attr: TokenStream, tokens: TokenStream) -> TokenStream {
+    if let Ok(item_impl) = syn::parse::<ItemImpl>(tokens.clone()) {
+        return with_container_options_error(
+            attr,
+            expand::apply_impl_tailcall_transform(item_impl),
+            "an impl block",
+        );
+    }
+
+    if let Ok(item_trait) = syn::parse::<ItemTrait>(tokens.clone()) {
+        return with_container_options_error(
+            attr,
+            expand::apply_trait_tailcall_transform(item_trait),
+            "a trait",
+        );
+    }
+
+    let options = TailcallOptions::parse(attr.into());
     let tokens_clone = tokens.clone();
 
     let output = match syn::parse::<ImplItemFn>(tokens) {
-        Ok(input) => {
-            if matches!(input.sig.inputs.first(), Some(syn::FnArg::Receiver(_))) {
-                expand::apply_method_tailcall_transform(input)
-            } else {
-                let input = parse_macro_input!(tokens_clone as ItemFn);
-                expand::apply_fn_tailcall_transform(input)
+        Ok(input) if matches!(input.sig.inputs.first(), Some(syn::FnArg::Receiver(_))) => {
+            match options {
+                Ok(options) => expand::apply_method_tailcall_transform(input, options),
+                Err(error) => expand::recover_method_tailcall_transform(input, error),
             }
         }
         _ => {
             let input = parse_macro_input!(tokens_clone as ItemFn);
-            expand::apply_fn_tailcall_transform(input)
+
+            match options {
+                Ok(options) => expand::apply_fn_tailcall_transform(input, options),
+                Err(error) => expand::recover_fn_tailcall_transform(input, error),
+            }
         }
     };
 
     TokenStream::from(output)
 }
 
+// Impl blocks and traits take their options on the items, so any given on the container itself are
+// reported next to its expansion.
+fn with_container_options_error(
+    attr: TokenStream,
+    output: proc_macro2::TokenStream,
+    container: &str,
+) -> TokenStream {
+    if attr.is_empty() {
+        return TokenStream::from(output);
+    }
+
+    let error = syn::Error::new_spanned(
+        proc_macro2::TokenStream::from(attr),
+        format!("#[tailcall] on {container} does not take options; put them on the items"),
+    )
+    .to_compile_error();
+
+    TokenStream::from(quote::quote! {
+        #error
+        #output
+    })
+}
+
 /// Marks an explicit stack-safe tail-call site inside a `#[tailcall]` function.
 ///
-/// The macro expects either a direct function call or a method call on `self`, such as:
+/// The macro expects either a direct function call or a method call, such as:
 ///
 /// ```ignore
 /// tailcall::call! { factorial_inner(acc * input, input - 1) }
+/// tailcall::call! { Self::restart(self, remaining) }
 /// tailcall::call! { self.is_odd(x - 1) }
 /// ```
 ///
 /// It expands to the hidden helper generated by the `#[tailcall]` attribute. Depending on the
 /// surrounding function, that helper may execute through the thunk runtime or be optimized away
 /// into direct loop lowering. The call site itself must remain in tail position.
+///
+/// Only a call to the surrounding function by its bare name, as `walk(..)`, `walk::<T>(..)` or
+/// `Self::walk(self, ..)`, counts as a self call that can be lowered to a loop. A turbofish has to
+/// repeat the function's own generic parameters: one with different generic arguments calls
+/// another instantiation, and paths such as `self::walk`, `crate::m::walk` or `super::walk` may
+/// name another function. Those calls run on the thunk runtime instead, without an error.
+///
+/// Either way, the arguments are evaluated first, then the caller's locals and parameters are
+/// dropped, and only then does the callee run, so arguments cannot borrow the caller's locals.
+///
+/// There are two exceptions, where the callee runs before the caller's locals and parameters are
+/// dropped:
+///
+/// - a `#[tailcall(hybrid)]` caller, while it is within its native call limit, calls the callee
+///   directly;
+/// - a callee whose only tail calls are to itself runs its loop as soon as it is called, so a call
+///   into it from another function completes before that function's locals are dropped.
 #[proc_macro]
 pub fn call(tokens: TokenStream) -> TokenStream {
     TokenStream::from(call_syntax::expand_call_macro(tokens.into()))
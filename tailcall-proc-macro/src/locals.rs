@@ -0,0 +1,462 @@
+use std::collections::HashSet;
+
+use syn::{
+    visit::{self, Visit},
+    Block, Error, Expr, ExprCall, ExprClosure, ExprField, ExprIndex, ExprMethodCall, ExprPath,
+    ExprReference, ExprUnary, FnArg, Ident, Item, Lifetime, Lit, Local, Pat, PatIdent, PatType,
+    Signature, Type, TypePtr, TypeReference, UnOp,
+};
+
+// Methods that return a borrow of their receiver. Other methods, including `as_*` conversions
+// such as `Duration::as_secs` that return an owned value, are left to the borrow checker.
+const LENDING_METHODS: &[&str] = &[
+    "as_bytes",
+    "as_deref",
+    "as_deref_mut",
+    "as_mut",
+    "as_mut_slice",
+    "as_mut_str",
+    "as_os_str",
+    "as_path",
+    "as_ref",
+    "as_slice",
+    "as_str",
+    "borrow",
+    "borrow_mut",
+    "deref",
+    "deref_mut",
+    "first",
+    "first_mut",
+    "get_mut",
+    "iter",
+    "iter_mut",
+    "last_mut",
+];
+
+// Methods whose result owns its value whatever their receiver holds.
+const OWNING_METHODS: &[&str] = &["into_owned", "to_owned", "to_string", "to_vec"];
+
+// The names bound by the function's parameters and by any pattern in its body. All of them are
+// dropped before a tail call transfers control, so none of them may be borrowed by its arguments.
+// `owned` holds the ones known to own their value, which cannot be borrowed through either: a
+// parameter or `let` whose type has no reference or lifetime in it, or a `let` initialized with a
+// fresh value. Anything else, such as a `match` binding, may be a reference and is left to the
+// borrow checker.
+#[derive(Clone, Default)]
+pub struct Locals {
+    bindings: HashSet<Ident>,
+    owned: HashSet<Ident>,
+}
+
+// Closures and nested items have their own scopes and are skipped.
+pub fn local_bindings(sig: &Signature, block: &Block) -> Locals {
+    let mut collector = BindingCollector::default();
+
+    for input in &sig.inputs {
+        match input {
+            FnArg::Receiver(receiver) => {
+                let self_ident = Ident::from(receiver.self_token);
+
+                collector.bind(self_ident, !holds_borrow(&receiver.ty));
+            }
+            FnArg::Typed(PatType { pat, ty, .. }) => {
+                collector.visit_binding(pat, !holds_borrow(ty))
+            }
+        }
+    }
+    collector.visit_block(block);
+
+    let BindingCollector {
+        bindings,
+        mut owned,
+        maybe_borrowed,
+        ..
+    } = collector;
+    owned.retain(|ident| !maybe_borrowed.contains(ident));
+
+    Locals { bindings, owned }
+}
+
+// A tail call runs after the caller's locals are dropped, like `become`, so an argument must not
+// carry a borrow of one into the call. The whole argument is searched for `&local`, for borrows of
+// a place rooted at an owned local (`&local.field`, `&local[..]`, `&*local`), for methods that
+// lend out an owned receiver (`local.as_str()`), and for any of these nested in tuples, arrays,
+// struct literals or tuple-struct constructors. Borrows that end before the call, as in
+// `len(&local)`, are left alone.
+pub fn reject_borrowed_locals<'a>(
+    args: impl IntoIterator<Item = &'a Expr>,
+    locals: &Locals,
+) -> Result<(), Error> {
+    let mut borrows = Vec::new();
+    for arg in args {
+        locals.find_borrows(arg, &mut borrows);
+    }
+
+    let mut errors = borrows.into_iter().map(|(expr, local)| {
+        Error::new_spanned(
+            expr,
+            format!(
+                "tail call argument borrows `{local}`, which is dropped before the call runs; pass an owned value instead"
+            ),
+        )
+    });
+
+    match errors.next() {
+        Some(mut error) => {
+            errors.for_each(|next| error.combine(next));
+            Err(error)
+        }
+        None => Ok(()),
+    }
+}
+
+impl Locals {
+    fn find_borrows<'a>(&self, expr: &'a Expr, borrows: &mut Vec<(&'a Expr, &'a Ident)>) {
+        match without_parens(expr) {
+            borrow @ Expr::Reference(ExprReference { expr: place, .. }) => {
+                match place_root(place) {
+                    Some((local, true)) if self.bindings.contains(local) => {
+                        borrows.push((borrow, local))
+                    }
+                    Some((local, false)) if self.owned.contains(local) => {
+                        borrows.push((borrow, local))
+                    }
+                    _ => self.find_borrows(place, borrows),
+                }
+            }
+            call @ Expr::MethodCall(ExprMethodCall {
+                receiver, method, ..
+            }) if lends_receiver(method) => match place_root(receiver) {
+                Some((local, _)) if self.owned.contains(local) => borrows.push((call, local)),
+                _ => self.find_borrows(receiver, borrows),
+            },
+            Expr::Tuple(tuple) => tuple
+                .elems
+                .iter()
+                .for_each(|elem| self.find_borrows(elem, borrows)),
+            Expr::Array(array) => array
+                .elems
+                .iter()
+                .for_each(|elem| self.find_borrows(elem, borrows)),
+            Expr::Struct(expr_struct) => expr_struct
+                .fields
+                .iter()
+                .for_each(|field| self.find_borrows(&field.expr, borrows)),
+            Expr::Call(ExprCall { func, args, .. }) if is_constructor(func) => {
+                args.iter().for_each(|arg| self.find_borrows(arg, borrows))
+            }
+            _ => {}
+        }
+    }
+}
+
+// The local a place expression starts from, and whether the place is that local itself.
+fn place_root(expr: &Expr) -> Option<(&Ident, bool)> {
+    match without_parens(expr) {
+        Expr::Path(ExprPath {
+            qself: None, path, ..
+        }) => path.get_ident().map(|ident| (ident, true)),
+        Expr::Field(ExprField { base: expr, .. })
+        | Expr::Index(ExprIndex { expr, .. })
+        | Expr::Unary(ExprUnary {
+            op: UnOp::Deref(_),
+            expr,
+            ..
+        }) => place_root(expr).map(|(ident, _)| (ident, false)),
+        _ => None,
+    }
+}
+
+fn lends_receiver(method: &Ident) -> bool {
+    LENDING_METHODS.contains(&method.to_string().as_str())
+}
+
+// Tuple structs and enum variants are named in `UpperCamelCase`, so `Some(..)` or `Pair(..)` is
+// taken to keep its arguments in its value, while an ordinary function call is not.
+fn is_constructor(func: &Expr) -> bool {
+    match without_parens(func) {
+        Expr::Path(ExprPath { path, .. }) => path.segments.last().is_some_and(|segment| {
+            segment
+                .ident
+                .to_string()
+                .starts_with(|first: char| first.is_ascii_uppercase())
+        }),
+        _ => false,
+    }
+}
+
+// Whether a value of this type may hold a borrow, through a reference, a raw pointer or a
+// lifetime parameter.
+fn holds_borrow(ty: &Type) -> bool {
+    #[derive(Default)]
+    struct BorrowFinder {
+        found: bool,
+    }
+
+    impl<'ast> Visit<'ast> for BorrowFinder {
+        fn visit_type_reference(&mut self, _: &'ast TypeReference) {
+            self.found = true;
+        }
+
+        fn visit_type_ptr(&mut self, _: &'ast TypePtr) {
+            self.found = true;
+        }
+
+        fn visit_lifetime(&mut self, _: &'ast Lifetime) {
+            self.found = true;
+        }
+    }
+
+    let mut finder = BorrowFinder::default();
+    finder.visit_type(ty);
+    finder.found
+}
+
+fn without_parens(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Paren(paren) => without_parens(&paren.expr),
+        Expr::Group(group) => without_parens(&group.expr),
+        expr => expr,
+    }
+}
+
+#[derive(Default)]
+struct BindingCollector {
+    bindings: HashSet<Ident>,
+    owned: HashSet<Ident>,
+    // Names bound at least once without being known to own their value.
+    maybe_borrowed: HashSet<Ident>,
+    // Whether the pattern being visited binds a single name known to own its value.
+    owned_pattern: bool,
+}
+
+impl BindingCollector {
+    fn bind(&mut self, ident: Ident, owned: bool) {
+        if owned {
+            self.owned.insert(ident.clone());
+        } else {
+            self.maybe_borrowed.insert(ident.clone());
+        }
+        self.bindings.insert(ident);
+    }
+
+    fn visit_binding(&mut self, pat: &Pat, owned: bool) {
+        self.owned_pattern = owned && matches!(pat, Pat::Ident(_));
+        self.visit_pat(pat);
+        self.owned_pattern = false;
+    }
+
+    fn is_owned(&self, ident: &Ident) -> bool {
+        self.owned.contains(ident) && !self.maybe_borrowed.contains(ident)
+    }
+
+    // A `let` without a type owns its value when it is initialized with a literal, an arithmetic
+    // result, a macro such as `format!`, a conversion to an owned type, or another owned local.
+    fn owns_value(&self, init: &Expr) -> bool {
+        match without_parens(init) {
+            Expr::Lit(lit) => !matches!(lit.lit, Lit::Str(_) | Lit::ByteStr(_) | Lit::CStr(_)),
+            Expr::Binary(_) | Expr::Macro(_) => true,
+            Expr::MethodCall(ExprMethodCall { method, .. }) => {
+                OWNING_METHODS.contains(&method.to_string().as_str())
+            }
+            Expr::Path(ExprPath {
+                qself: None, path, ..
+            }) => path.get_ident().is_some_and(|ident| self.is_owned(ident)),
+            _ => false,
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for BindingCollector {
+    fn visit_local(&mut self, local: &'ast Local) {
+        if let Some(init) = &local.init {
+            self.visit_local_init(init);
+        }
+
+        match &local.pat {
+            Pat::Type(PatType { pat, ty, .. }) => self.visit_binding(pat, !holds_borrow(ty)),
+            pat => {
+                let owned = local
+                    .init
+                    .as_ref()
+                    .is_some_and(|init| self.owns_value(&init.expr));
+
+                self.visit_binding(pat, owned);
+            }
+        }
+    }
+
+    fn visit_pat_ident(&mut self, pat_ident: &'ast PatIdent) {
+        let owned = std::mem::take(&mut self.owned_pattern) && pat_ident.by_ref.is_none();
+
+        self.bind(pat_ident.ident.clone(), owned);
+        visit::visit_pat_ident(self, pat_ident);
+    }
+
+    fn visit_expr_closure(&mut self, _: &'ast ExprClosure) {}
+
+    fn visit_item(&mut self, _: &'ast Item) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::{parse_quote, Expr};
+
+    use super::{local_bindings, reject_borrowed_locals};
+
+    fn check(args: Vec<Expr>) -> Result<(), syn::Error> {
+        let sig: syn::Signature =
+            parse_quote!(fn walk(node: Node, rest: &mut [u32], depth: u32) -> u32);
+        let block: syn::Block = parse_quote!({
+            let label = node.label();
+            let name = format!("{depth}");
+            match node.next {
+                Some(next) => tailcall::call! { walk(next, rest, depth + 1) },
+                None => depth,
+            }
+        });
+
+        reject_borrowed_locals(&args, &local_bindings(&sig, &block))
+    }
+
+    fn sorted(idents: &std::collections::HashSet<syn::Ident>) -> Vec<String> {
+        let mut names: Vec<String> = idents.iter().map(|ident| ident.to_string()).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn collects_parameters_and_pattern_bindings() {
+        let sig: syn::Signature = parse_quote!(fn walk(node: Node, depth: u32) -> u32);
+        let block: syn::Block = parse_quote!({
+            let (label, _) = node.label();
+            if let Some(next) = node.next {
+                let inner = |skipped: u32| skipped;
+                fn nested(hidden: u32) {}
+            }
+            depth
+        });
+
+        assert_eq!(
+            sorted(&local_bindings(&sig, &block).bindings),
+            ["depth", "inner", "label", "next", "node"]
+        );
+    }
+
+    #[test]
+    fn tells_owned_locals_from_ones_that_may_borrow() {
+        let sig: syn::Signature = parse_quote!(
+            fn walk(self: Box<Self>, node: Node, rest: &[u8], cursor: Cursor<'_>) -> u32
+        );
+        let block: syn::Block = parse_quote!({
+            let name = format!("{}", node.id);
+            let copy = name;
+            let count: usize = rest.len();
+            let label: &str = node.label();
+            let view = &copy;
+            let text = "static";
+            let shadowed = 1;
+            let (pair, _) = node.pair();
+            match node.next {
+                Some(shadowed) => shadowed,
+                None => count,
+            }
+        });
+
+        assert_eq!(
+            sorted(&local_bindings(&sig, &block).owned),
+            ["copy", "count", "name", "node", "self"]
+        );
+    }
+
+    #[test]
+    fn rejects_borrows_of_locals_and_parameters() {
+        let error = check(vec![
+            parse_quote!(&label),
+            parse_quote!((&mut depth)),
+            parse_quote!(&next),
+        ])
+        .expect_err("borrowed locals should be rejected");
+
+        assert_eq!(error.into_iter().count(), 3);
+        assert!(check(vec![parse_quote!(&node)])
+            .expect_err("borrowed parameters should be rejected")
+            .to_string()
+            .contains("borrows `node`"));
+    }
+
+    #[test]
+    fn rejects_field_and_index_borrows_of_owned_locals() {
+        let error = check(vec![
+            parse_quote!(&node.label),
+            parse_quote!(&mut node.items[1..]),
+            parse_quote!(&name[..]),
+            parse_quote!(&*name),
+        ])
+        .expect_err("borrows through owned locals should be rejected");
+
+        assert_eq!(error.into_iter().count(), 4);
+    }
+
+    #[test]
+    fn rejects_methods_that_lend_out_owned_receivers() {
+        let error = check(vec![
+            parse_quote!(name.as_str()),
+            parse_quote!(node.items.iter()),
+            parse_quote!(node.borrow()),
+            parse_quote!(node.items.as_slice().first()),
+        ])
+        .expect_err("receiver borrows of owned locals should be rejected");
+
+        assert_eq!(error.into_iter().count(), 4);
+    }
+
+    #[test]
+    fn rejects_borrows_nested_in_values() {
+        let error = check(vec![
+            parse_quote!((&name, depth)),
+            parse_quote!([&depth]),
+            parse_quote!(Pair {
+                left: &node.left,
+                right: 1
+            }),
+            parse_quote!(Some(name.as_str())),
+        ])
+        .expect_err("nested borrows of locals should be rejected");
+
+        assert_eq!(error.into_iter().count(), 4);
+    }
+
+    #[test]
+    fn accepts_methods_that_return_owned_values_from_owned_receivers() {
+        let sig: syn::Signature = parse_quote!(fn wait(d: Duration, n: u64) -> u64);
+        let block: syn::Block = parse_quote!({
+            tailcall::call! { wait_more(d.as_secs(), n - 1) }
+        });
+        let args: Vec<Expr> = vec![
+            parse_quote!(d.as_secs()),
+            parse_quote!(d.as_millis()),
+            parse_quote!(n - 1),
+        ];
+
+        assert!(reject_borrowed_locals(&args, &local_bindings(&sig, &block)).is_ok());
+    }
+
+    #[test]
+    fn accepts_owned_values_and_borrows_through_references() {
+        assert!(check(vec![
+            parse_quote!(label),
+            parse_quote!(&label.name),
+            parse_quote!(&next[1..]),
+            parse_quote!(&mut rest[1..]),
+            parse_quote!(rest.iter()),
+            parse_quote!(&GLOBAL),
+            parse_quote!(len(&label)),
+            parse_quote!(len(&name)),
+            parse_quote!(name.len()),
+            parse_quote!(node.items.iter().count()),
+            parse_quote!((name.clone(), depth)),
+        ])
+        .is_ok());
+    }
+}
@@ -3,54 +3,197 @@ use quote::quote;
 use syn::{
     fold::{self, Fold},
     parse2, parse_quote, Error, Expr, ExprBlock, ExprCall, ExprIf, ExprMacro, ExprMatch,
-    ExprMethodCall, ExprPath, ExprReturn, ExprTry, FnArg, Ident, ImplItemFn, Item, ItemFn, Pat,
-    PatIdent, PatType, Stmt, StmtMacro,
+    ExprMethodCall, ExprPath, ExprReturn, ExprTry, ExprUnsafe, FnArg, Generics, Ident, ImplItemFn,
+    Item, ItemFn, Lifetime, Pat, PatIdent, PatType, Receiver, Signature, Stmt, StmtMacro,
 };
 
-use crate::call_syntax::is_tailcall_macro;
+use crate::{
+    call_syntax::{is_self_function_path, is_self_method_path, CallMacro},
+    instrument::instrumentation_stmts,
+    locals::{local_bindings, reject_borrowed_locals, Locals},
+    options::TailcallOptions,
+};
+
+pub fn lower_self_tail_loop(
+    item_fn: &ItemFn,
+    options: &TailcallOptions,
+) -> Result<TokenStream, Error> {
+    lower_function_loop(item_fn, false, options)
+}
 
-pub fn lower_self_tail_loop(item_fn: &ItemFn) -> Result<TokenStream, Error> {
+pub fn lower_self_tail_associated_loop(
+    item_fn: &ItemFn,
+    options: &TailcallOptions,
+) -> Result<TokenStream, Error> {
+    lower_function_loop(item_fn, true, options)
+}
+
+fn lower_function_loop(
+    item_fn: &ItemFn,
+    associated: bool,
+    options: &TailcallOptions,
+) -> Result<TokenStream, Error> {
     let arg_idents = function_arg_idents(&item_fn.sig.inputs)?;
-    let mut lowerer = LoopLowerer::for_function(item_fn.sig.ident.clone(), arg_idents.clone());
-    let loop_block = lowerer.lower_tail_block(*item_fn.block.clone());
+    let mut lowerer = LoopLowerer::for_function(
+        item_fn.sig.ident.clone(),
+        item_fn.sig.generics.clone(),
+        associated,
+        arg_idents.clone(),
+        local_bindings(&item_fn.sig, &item_fn.block),
+        options.call_macro(),
+    );
+    let mut body_block = lowerer.lower_tail_block(*item_fn.block.clone());
+    body_block.stmts.splice(
+        0..0,
+        instrumentation_stmts(&item_fn.sig.ident, &options.crate_path())
+            .into_iter()
+            .chain(options.loop_check_stmt()),
+    );
     let rebinding_stmts: Vec<Stmt> = arg_idents
         .iter()
         .map(|ident| parse_quote! { let mut #ident = #ident; })
         .collect();
+    let loop_tokens = loop_tokens(body_block, &arg_idents, None);
 
     match lowerer.error {
         Some(error) => Err(error),
         None => Ok(quote! {
             #(#rebinding_stmts)*
-            loop #loop_block
+            #loop_tokens
         }),
     }
 }
 
-pub fn lower_self_tail_method_loop(method: &ImplItemFn) -> Result<TokenStream, Error> {
+// Every iteration runs the body in a labeled block that breaks with the next arguments, so the
+// body's locals are dropped once the arguments have been evaluated and before anything else
+// happens, like on a return. The previous parameter values are then replaced in reverse order of
+// declaration, which is the order a function drops its parameters in.
+fn loop_tokens(
+    body_block: syn::Block,
+    arg_idents: &[Ident],
+    receiver_alias: Option<&Ident>,
+) -> TokenStream {
+    let label = body_label();
+    let next = Ident::new("__tailcall_next", Span::call_site());
+    let targets: Vec<&Ident> = receiver_alias.into_iter().chain(arg_idents).collect();
+    let reassignments = targets.iter().enumerate().rev().map(|(index, ident)| {
+        let index = syn::Index::from(index);
+        quote! { #ident = #next.#index; }
+    });
+
+    quote! {
+        loop {
+            let #next = #label: #body_block;
+            #(#reassignments)*
+        }
+    }
+}
+
+fn body_label() -> Lifetime {
+    Lifetime::new("'__tailcall_body", Span::call_site())
+}
+
+pub fn lower_self_tail_method_loop(
+    method: &ImplItemFn,
+    options: &TailcallOptions,
+) -> Result<TokenStream, Error> {
     let arg_idents = function_arg_idents(&method.sig.inputs)?;
     let receiver_alias = Ident::new("__tailcall_self", Span::call_site());
-    let mut lowerer = LoopLowerer::for_method(
-        method.sig.ident.clone(),
-        arg_idents.clone(),
-        receiver_alias.clone(),
+    let locals = local_bindings(&method.sig, &method.block);
+    let lower = |next_receiver: bool| {
+        let mut lowerer = LoopLowerer::for_method(
+            method.sig.ident.clone(),
+            method.sig.generics.clone(),
+            arg_idents.clone(),
+            receiver_alias.clone(),
+            locals.clone(),
+            options.call_macro(),
+        );
+        lowerer.next_receiver = next_receiver;
+        let body_block = lowerer.lower_tail_block(method.block.clone());
+        (lowerer, body_block)
+    };
+    // Only `Self::method(receiver, ...)` tail calls can replace the receiver, but once one does,
+    // every tail call has to pass the next receiver along with the other arguments.
+    let (mut lowerer, mut body_block) = lower(false);
+    if lowerer.receiver_reassigned {
+        (lowerer, body_block) = lower(true);
+    }
+    body_block.stmts.splice(
+        0..0,
+        instrumentation_stmts(&method.sig.ident, &options.crate_path())
+            .into_iter()
+            .chain(options.loop_check_stmt()),
     );
-    let loop_block = lowerer.lower_tail_block(method.block.clone());
+    let receiver_binding: Stmt =
+        if lowerer.receiver_reassigned || receiver_is_mutable_binding(&method.sig) {
+            parse_quote! { let mut #receiver_alias = self; }
+        } else {
+            parse_quote! { let #receiver_alias = self; }
+        };
     let rebinding_stmts: Vec<Stmt> = arg_idents
         .iter()
         .map(|ident| parse_quote! { let mut #ident = #ident; })
         .collect();
+    let loop_tokens = loop_tokens(
+        body_block,
+        &arg_idents,
+        lowerer.next_receiver.then_some(&receiver_alias),
+    );
 
     match lowerer.error {
         Some(error) => Err(error),
         None => Ok(quote! {
-            let #receiver_alias = self;
+            #receiver_binding
             #(#rebinding_stmts)*
-            loop #loop_block
+            #loop_tokens
         }),
     }
 }
 
+// Lowers the body of a self tail-recursive function into a single step of its loop: a block
+// labeled `label` that breaks with `Call { args }` for the next iteration or `Done(value)` once the
+// function returns.
+pub fn lower_self_tail_steps(
+    item_fn: &ItemFn,
+    enum_ident: &Ident,
+    label: &Lifetime,
+    call_macro: &CallMacro,
+) -> Result<syn::Block, Error> {
+    let arg_idents = function_arg_idents(&item_fn.sig.inputs)?;
+    let mut lowerer = LoopLowerer::for_function(
+        item_fn.sig.ident.clone(),
+        item_fn.sig.generics.clone(),
+        false,
+        arg_idents,
+        local_bindings(&item_fn.sig, &item_fn.block),
+        call_macro.clone(),
+    );
+    lowerer.exit = LoopExit::Step {
+        enum_ident: enum_ident.clone(),
+        label: label.clone(),
+    };
+    let step_block = lowerer.lower_tail_block(*item_fn.block.clone());
+
+    match lowerer.error {
+        Some(error) => Err(error),
+        None => Ok(step_block),
+    }
+}
+
+// A by-value receiver declared as `mut self` (including typed receivers such as
+// `mut self: Box<Self>`) may be reassigned by the body, so its loop alias must be mutable too.
+fn receiver_is_mutable_binding(sig: &Signature) -> bool {
+    matches!(
+        sig.inputs.first(),
+        Some(FnArg::Receiver(Receiver {
+            reference: None,
+            mutability: Some(_),
+            ..
+        }))
+    )
+}
+
 fn function_arg_idents(
     inputs: &syn::punctuated::Punctuated<FnArg, syn::token::Comma>,
 ) -> Result<Vec<Ident>, Error> {
@@ -76,38 +219,86 @@ fn function_arg_idents(
 }
 
 enum LoopTarget {
-    Function(Ident),
+    Function {
+        fn_ident: Ident,
+        generics: Generics,
+        associated: bool,
+    },
     Method {
         method_ident: Ident,
+        generics: Generics,
         receiver_alias: Ident,
     },
 }
 
+// How a lowered tail position leaves the current iteration.
+enum LoopExit {
+    // Break out of the loop body with the next arguments, or `return` the value.
+    Loop,
+    // Break out of the labeled step block with the next step.
+    Step { enum_ident: Ident, label: Lifetime },
+}
+
 struct LoopLowerer {
     target: LoopTarget,
+    call_macro: CallMacro,
+    exit: LoopExit,
     arg_idents: Vec<Ident>,
+    locals: Locals,
     temp_counter: usize,
+    receiver_reassigned: bool,
+    // Whether the next receiver is broken out of the loop body together with the arguments.
+    next_receiver: bool,
     error: Option<Error>,
 }
 
 impl LoopLowerer {
-    fn for_function(fn_ident: Ident, arg_idents: Vec<Ident>) -> Self {
+    fn for_function(
+        fn_ident: Ident,
+        generics: Generics,
+        associated: bool,
+        arg_idents: Vec<Ident>,
+        locals: Locals,
+        call_macro: CallMacro,
+    ) -> Self {
         Self {
-            target: LoopTarget::Function(fn_ident),
+            target: LoopTarget::Function {
+                fn_ident,
+                generics,
+                associated,
+            },
+            call_macro,
+            exit: LoopExit::Loop,
             arg_idents,
+            locals,
             temp_counter: 0,
+            receiver_reassigned: false,
+            next_receiver: false,
             error: None,
         }
     }
 
-    fn for_method(method_ident: Ident, arg_idents: Vec<Ident>, receiver_alias: Ident) -> Self {
+    fn for_method(
+        method_ident: Ident,
+        generics: Generics,
+        arg_idents: Vec<Ident>,
+        receiver_alias: Ident,
+        locals: Locals,
+        call_macro: CallMacro,
+    ) -> Self {
         Self {
             target: LoopTarget::Method {
                 method_ident,
+                generics,
                 receiver_alias,
             },
+            call_macro,
+            exit: LoopExit::Loop,
             arg_idents,
+            locals,
             temp_counter: 0,
+            receiver_reassigned: false,
+            next_receiver: false,
             error: None,
         }
     }
@@ -186,19 +377,51 @@ impl LoopLowerer {
                     })
                     .collect(),
             }),
-            Expr::Macro(expr_macro) if is_tailcall_macro(&expr_macro.mac.path) => {
+            // Once its tail call turns into `continue`, an `unsafe` block may not contain any
+            // unsafe operations anymore.
+            Expr::Unsafe(ExprUnsafe {
+                attrs,
+                unsafe_token,
+                block,
+            }) => {
+                let expr_unsafe = ExprUnsafe {
+                    attrs,
+                    unsafe_token,
+                    block: self.lower_tail_block(block),
+                };
+                parse_quote! {
+                    {
+                        #[allow(unused_unsafe)]
+                        #expr_unsafe
+                    }
+                }
+            }
+            Expr::Macro(expr_macro) if self.call_macro.matches(&expr_macro.mac.path) => {
                 self.lower_self_tailcall(expr_macro)
             }
             expr => {
                 let expr = self.fold_expr(expr);
-                parse_quote! { return #expr }
+                self.lower_tail_value(expr)
+            }
+        }
+    }
+
+    fn lower_tail_value(&self, expr: Expr) -> Expr {
+        match &self.exit {
+            LoopExit::Loop => parse_quote! { return #expr },
+            LoopExit::Step { enum_ident, label } => {
+                parse_quote! { break #label #enum_ident::Done(#expr) }
             }
         }
     }
 
     fn lower_self_tailcall(&mut self, expr_macro: ExprMacro) -> Expr {
         match &self.target {
-            LoopTarget::Function(fn_ident) => {
+            LoopTarget::Function {
+                fn_ident,
+                generics,
+                associated,
+            } => {
                 let expr_call = match parse2::<ExprCall>(expr_macro.mac.tokens.clone()) {
                     Ok(expr_call) => expr_call,
                     Err(error) => {
@@ -208,7 +431,9 @@ impl LoopLowerer {
                 };
 
                 match &*expr_call.func {
-                    Expr::Path(ExprPath { path, .. }) if path.is_ident(fn_ident) => {}
+                    Expr::Path(ExprPath { path, .. })
+                        if (!*associated && is_self_function_path(path, fn_ident, generics))
+                            || (*associated && is_self_method_path(path, fn_ident, generics)) => {}
                     _ => {
                         self.reject(Error::new_spanned(
                             expr_call,
@@ -218,12 +443,39 @@ impl LoopLowerer {
                     }
                 }
 
-                self.lower_tailcall_args(expr_call.args.into_iter().collect())
+                let span = expr_call.paren_token.span.join();
+                self.lower_tailcall_args(span, None, expr_call.args.into_iter().collect())
             }
-            LoopTarget::Method { method_ident, .. } => {
-                let expr_method_call = match parse2::<ExprMethodCall>(expr_macro.mac.tokens.clone())
+            LoopTarget::Method {
+                method_ident,
+                generics,
+                ..
+            } => {
+                if let Ok(expr_method_call) =
+                    parse2::<ExprMethodCall>(expr_macro.mac.tokens.clone())
                 {
-                    Ok(expr_method_call) => expr_method_call,
+                    if !matches!(
+                        &*expr_method_call.receiver,
+                        Expr::Path(ExprPath { path, .. }) if path.is_ident("self")
+                    ) || expr_method_call.method != *method_ident
+                    {
+                        self.reject(Error::new_spanned(
+                            expr_method_call,
+                            "loop lowering only supports direct self tail calls on `self` or through `Self::method(receiver, ...)`",
+                        ));
+                        return parse_quote! { continue };
+                    }
+
+                    let span = expr_method_call.paren_token.span.join();
+                    return self.lower_tailcall_args(
+                        span,
+                        None,
+                        expr_method_call.args.into_iter().collect(),
+                    );
+                }
+
+                let expr_call = match parse2::<ExprCall>(expr_macro.mac.tokens.clone()) {
+                    Ok(expr_call) => expr_call,
                     Err(error) => {
                         self.reject(error);
                         return parse_quote! { continue };
@@ -231,61 +483,90 @@ impl LoopLowerer {
                 };
 
                 if !matches!(
-                    &*expr_method_call.receiver,
-                    Expr::Path(ExprPath { path, .. }) if path.is_ident("self")
-                ) || expr_method_call.method != *method_ident
+                    &*expr_call.func,
+                    Expr::Path(ExprPath { path, .. }) if is_self_method_path(path, method_ident, generics)
+                ) || expr_call.args.is_empty()
                 {
                     self.reject(Error::new_spanned(
-                        expr_method_call,
-                        "loop lowering only supports direct self tail calls on `self`",
+                        expr_call,
+                        "loop lowering only supports direct self tail calls on `self` or through `Self::method(receiver, ...)`",
                     ));
                     return parse_quote! { continue };
                 }
 
-                self.lower_tailcall_args(expr_method_call.args.into_iter().collect())
+                let span = expr_call.paren_token.span.join();
+                let mut args = expr_call.args.into_iter();
+                let receiver = args.next();
+                self.lower_tailcall_args(span, receiver, args.collect())
             }
         }
     }
 
-    fn lower_tailcall_args(&mut self, args: Vec<Expr>) -> Expr {
+    // `receiver` is only present for `Self::method(receiver, ...)` tail calls. A receiver that is
+    // anything other than `self` itself becomes the next value of the receiver alias. `span`
+    // covers the argument list.
+    fn lower_tailcall_args(&mut self, span: Span, receiver: Option<Expr>, args: Vec<Expr>) -> Expr {
         if args.len() != self.arg_idents.len() {
             self.reject(Error::new(
-                Span::call_site(),
+                span,
                 "tailcall::call! argument count must match the function signature",
             ));
             return parse_quote! { continue };
         }
 
-        let temp_idents: Vec<Ident> = (0..self.arg_idents.len())
-            .map(|_| {
-                let ident = Ident::new(
-                    &format!("__tailcall_next_{}", self.temp_counter),
-                    Span::call_site(),
-                );
-                self.temp_counter += 1;
-                ident
-            })
-            .collect();
+        if let Err(error) = reject_borrowed_locals(receiver.iter().chain(&args), &self.locals) {
+            self.reject(error);
+        }
 
-        let assignments: Vec<Stmt> = args
-            .into_iter()
-            .zip(temp_idents.iter())
-            .map(|(arg, temp_ident)| {
-                let arg = self.fold_expr(arg);
-                parse_quote! { let #temp_ident = #arg; }
-            })
-            .collect();
+        let receiver = receiver.filter(|receiver| {
+            !matches!(receiver, Expr::Path(ExprPath { path, .. }) if path.is_ident("self"))
+        });
+        let receiver_alias = match &self.target {
+            LoopTarget::Method { receiver_alias, .. } => Some(receiver_alias.clone()),
+            LoopTarget::Function { .. } => None,
+        };
 
-        let rebinds: Vec<Stmt> = self
-            .arg_idents
-            .iter()
-            .zip(temp_idents.iter())
-            .map(|(ident, temp_ident)| parse_quote! { #ident = #temp_ident; })
-            .collect();
+        let mut stmts: Vec<Stmt> = Vec::new();
+        let mut next_receiver = None;
+        let mut next_args: Vec<(Ident, Ident)> = Vec::new();
+
+        if let (Some(receiver), Some(_)) = (receiver, &receiver_alias) {
+            let receiver = self.fold_expr(receiver);
+            let temp_ident = self.next_temp_ident();
+
+            stmts.push(parse_quote! { let #temp_ident = #receiver; });
+            next_receiver = Some(temp_ident);
+            self.receiver_reassigned = true;
+        }
+
+        for (arg, ident) in args.into_iter().zip(self.arg_idents.clone()) {
+            let arg = self.fold_expr(arg);
+            let temp_ident = self.next_temp_ident();
 
-        let mut stmts = assignments;
-        stmts.extend(rebinds);
-        stmts.push(parse_quote! { continue; });
+            stmts.push(parse_quote! { let #temp_ident = #arg; });
+            next_args.push((ident, temp_ident));
+        }
+
+        match &self.exit {
+            LoopExit::Loop => {
+                let label = body_label();
+                let next_receiver = self
+                    .next_receiver
+                    .then(|| next_receiver.or(receiver_alias))
+                    .flatten()
+                    .into_iter();
+                let temp_idents = next_args.iter().map(|(_, temp_ident)| temp_ident);
+                stmts.push(parse_quote! {
+                    break #label (#(#next_receiver,)* #(#temp_idents,)*);
+                });
+            }
+            LoopExit::Step { enum_ident, label } => {
+                let (idents, temp_idents): (Vec<_>, Vec<_>) = next_args.into_iter().unzip();
+                stmts.push(parse_quote! {
+                    break #label #enum_ident::Call { #(#idents: #temp_idents),* };
+                });
+            }
+        }
 
         Expr::Block(ExprBlock {
             attrs: Vec::new(),
@@ -297,8 +578,17 @@ impl LoopLowerer {
         })
     }
 
+    fn next_temp_ident(&mut self) -> Ident {
+        let ident = Ident::new(
+            &format!("__tailcall_next_{}", self.temp_counter),
+            Span::call_site(),
+        );
+        self.temp_counter += 1;
+        ident
+    }
+
     fn lower_tail_stmt_macro(&mut self, stmt_macro: StmtMacro) -> Expr {
-        if is_tailcall_macro(&stmt_macro.mac.path) {
+        if self.call_macro.matches(&stmt_macro.mac.path) {
             return self.lower_self_tailcall(ExprMacro {
                 attrs: stmt_macro.attrs,
                 mac: stmt_macro.mac,
@@ -340,13 +630,21 @@ impl Fold for LoopLowerer {
                     question_token,
                 })
             }
-            Expr::Macro(expr_macro) if is_tailcall_macro(&expr_macro.mac.path) => {
+            Expr::Macro(expr_macro) if self.call_macro.matches(&expr_macro.mac.path) => {
                 self.reject(Error::new_spanned(
                     &expr_macro,
                     "tailcall::call! must be used in tail position",
                 ));
                 self.lower_self_tailcall(expr_macro)
             }
+            // An early `return` leaves the step block just like a returning tail position.
+            Expr::Return(ExprReturn { expr, .. }) if matches!(self.exit, LoopExit::Step { .. }) => {
+                let expr = match expr {
+                    Some(expr) => self.fold_expr(*expr),
+                    None => parse_quote! { () },
+                };
+                self.lower_tail_value(expr)
+            }
             expr => fold::fold_expr(self, expr),
         }
     }
@@ -368,6 +666,27 @@ impl Fold for LoopLowerer {
         fold::fold_expr_method_call(self, expr_method_call)
     }
 
+    fn fold_expr_call(&mut self, expr_call: ExprCall) -> ExprCall {
+        if matches!(
+            (&self.target, &*expr_call.func),
+            (
+                LoopTarget::Method {
+                    method_ident,
+                    generics,
+                    ..
+                },
+                Expr::Path(ExprPath { path, .. })
+            ) if is_self_method_path(path, method_ident, generics)
+        ) {
+            self.reject(Error::new_spanned(
+                &expr_call,
+                "tailcall::call! must be used in tail position",
+            ));
+        }
+
+        fold::fold_expr_call(self, expr_call)
+    }
+
     fn fold_expr_path(&mut self, mut expr_path: ExprPath) -> ExprPath {
         if let LoopTarget::Method { receiver_alias, .. } = &self.target {
             if expr_path.path.is_ident("self") {
@@ -392,6 +711,7 @@ mod tests {
     use syn::parse_quote;
 
     use super::{lower_self_tail_loop, lower_self_tail_method_loop};
+    use crate::options::TailcallOptions;
 
     #[test]
     fn lowers_simple_self_tail_recursion() {
@@ -405,7 +725,8 @@ mod tests {
             }
         };
 
-        lower_self_tail_loop(&item_fn).expect("loop lowering should succeed");
+        lower_self_tail_loop(&item_fn, &TailcallOptions::default())
+            .expect("loop lowering should succeed");
     }
 
     #[test]
@@ -420,7 +741,27 @@ mod tests {
             }
         };
 
-        lower_self_tail_loop(&item_fn).expect("loop lowering should succeed");
+        lower_self_tail_loop(&item_fn, &TailcallOptions::default())
+            .expect("loop lowering should succeed");
+    }
+
+    #[test]
+    fn rejects_tail_call_arguments_that_borrow_locals() {
+        let item_fn: syn::ItemFn = parse_quote! {
+            fn walk<'a>(path: &'a str, depth: u32) -> u32 {
+                let rest = path.trim_start_matches('/').to_owned();
+                if depth > 0 {
+                    tailcall::call! { walk(&rest, depth - 1) }
+                } else {
+                    depth
+                }
+            }
+        };
+
+        let error = lower_self_tail_loop(&item_fn, &TailcallOptions::default())
+            .expect_err("borrowing a local should be rejected");
+
+        assert!(error.to_string().contains("borrows `rest`"));
     }
 
     #[test]
@@ -435,6 +776,7 @@ mod tests {
             }
         };
 
-        lower_self_tail_method_loop(&method).expect("method loop lowering should succeed");
+        lower_self_tail_method_loop(&method, &TailcallOptions::default())
+            .expect("method loop lowering should succeed");
     }
 }
@@ -1,8 +1,66 @@
-use syn::Ident;
+use syn::{ext::IdentExt, Ident};
 
+// Generated names are built from the unraw name, since `r#type` cannot be part of a longer
+// identifier.
 pub fn helper_ident(fn_name: &Ident) -> Ident {
     Ident::new(
-        &format!("__tailcall_build_{}_thunk", fn_name),
+        &format!("__tailcall_build_{}_thunk", fn_name.unraw()),
         fn_name.span(),
     )
 }
+
+pub fn thunk_fn_ident(fn_name: &Ident) -> Ident {
+    Ident::new(&format!("{}_thunk", fn_name.unraw()), fn_name.span())
+}
+
+pub fn steps_fn_ident(fn_name: &Ident) -> Ident {
+    Ident::new(&format!("{}_steps", fn_name.unraw()), fn_name.span())
+}
+
+// `binary_search` becomes `BinarySearchStep`.
+pub fn steps_enum_ident(fn_name: &Ident) -> Ident {
+    let camel_case: String = fn_name
+        .unraw()
+        .to_string()
+        .split('_')
+        .filter(|word| !word.is_empty())
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .into_iter()
+                .flat_map(char::to_uppercase)
+                .chain(chars)
+        })
+        .collect();
+
+    Ident::new(&format!("{camel_case}Step"), fn_name.span())
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::format_ident;
+
+    use super::{helper_ident, steps_enum_ident, steps_fn_ident, thunk_fn_ident};
+
+    #[test]
+    fn names_step_items_after_the_function() {
+        assert_eq!(steps_fn_ident(&format_ident!("gcd")), "gcd_steps");
+        assert_eq!(steps_enum_ident(&format_ident!("gcd")), "GcdStep");
+        assert_eq!(
+            steps_enum_ident(&format_ident!("binary_search")),
+            "BinarySearchStep"
+        );
+        assert_eq!(steps_enum_ident(&format_ident!("_go__on")), "GoOnStep");
+    }
+
+    #[test]
+    fn names_generated_items_after_raw_identifiers() {
+        let fn_name: syn::Ident = syn::parse_quote!(r#type);
+
+        assert_eq!(helper_ident(&fn_name), "__tailcall_build_type_thunk");
+        assert_eq!(thunk_fn_ident(&fn_name), "type_thunk");
+        assert_eq!(steps_fn_ident(&fn_name), "type_steps");
+        assert_eq!(steps_enum_ident(&fn_name), "TypeStep");
+    }
+}
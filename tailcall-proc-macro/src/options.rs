@@ -0,0 +1,620 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    parse::Parser, parse_quote, punctuated::Punctuated, spanned::Spanned, token::Comma, Attribute,
+    Error, FnArg, Ident, LitInt, LitStr, Meta, Pat, PatType, Path, Signature, Stmt,
+};
+
+use crate::call_syntax::CallMacro;
+
+// Options accepted by `#[tailcall(...)]`.
+#[derive(Default)]
+pub struct TailcallOptions {
+    helper_attrs: Vec<Ident>,
+    check: Option<Path>,
+    trace: bool,
+    expose_thunk: bool,
+    runtime: Option<Runtime>,
+    arena: Option<Ident>,
+    hybrid: Option<LitInt>,
+    crate_path: Option<Path>,
+    call_macro: Option<Path>,
+}
+
+// How many nested calls `hybrid` helpers make natively when no depth is given.
+const DEFAULT_HYBRID_DEPTH: usize = 64;
+
+// The runtime type that generated helpers return and bounce through.
+enum Runtime {
+    Inline,
+    Boxed,
+    // Any type implementing `tailcall::runtime::Trampoline`, named without its generic arguments.
+    Custom(Path),
+}
+
+impl TailcallOptions {
+    pub fn parse(tokens: TokenStream) -> Result<Self, Error> {
+        let mut options = Self::default();
+
+        syn::meta::parser(|meta| {
+            if meta.path.is_ident("helper_attrs") {
+                return meta.parse_nested_meta(|nested| {
+                    let name = nested.path.require_ident()?;
+
+                    if name == "doc" || name == "inline" || name == "tailcall" {
+                        return Err(nested
+                            .error(format!("`{name}` cannot be copied to the generated helper")));
+                    }
+
+                    options.helper_attrs.push(name.clone());
+                    Ok(())
+                });
+            }
+
+            if meta.path.is_ident("trace") {
+                options.trace = true;
+                return Ok(());
+            }
+
+            if meta.path.is_ident("expose_thunk") {
+                options.expose_thunk = true;
+                return Ok(());
+            }
+
+            if meta.path.is_ident("check") {
+                if options.check.is_some() {
+                    return Err(meta.error("duplicate `check` option"));
+                }
+
+                options.check = Some(meta.value()?.parse()?);
+                return Ok(());
+            }
+
+            if meta.path.is_ident("arena") {
+                if options.arena.is_some() {
+                    return Err(meta.error("duplicate `arena` option"));
+                }
+
+                options.arena = Some(meta.value()?.parse()?);
+                return Ok(());
+            }
+
+            if meta.path.is_ident("hybrid") {
+                if options.hybrid.is_some() {
+                    return Err(meta.error("duplicate `hybrid` option"));
+                }
+
+                let depth = if meta.input.peek(syn::Token![=]) {
+                    let depth: LitInt = meta.value()?.parse()?;
+                    depth.base10_parse::<usize>()?;
+                    depth
+                } else {
+                    LitInt::new(&DEFAULT_HYBRID_DEPTH.to_string(), meta.path.span())
+                };
+
+                options.hybrid = Some(depth);
+                return Ok(());
+            }
+
+            if meta.path.is_ident("crate") {
+                if options.crate_path.is_some() {
+                    return Err(meta.error("duplicate `crate` option"));
+                }
+
+                options.crate_path = Some(meta.value()?.parse()?);
+                return Ok(());
+            }
+
+            if meta.path.is_ident("call_macro") {
+                if options.call_macro.is_some() {
+                    return Err(meta.error("duplicate `call_macro` option"));
+                }
+
+                options.call_macro = Some(meta.value()?.parse()?);
+                return Ok(());
+            }
+
+            if meta.path.is_ident("runtime") {
+                if options.runtime.is_some() {
+                    return Err(meta.error("duplicate `runtime` option"));
+                }
+
+                let value = meta.value()?;
+
+                if !value.peek(LitStr) {
+                    options.runtime = Some(Runtime::Custom(value.parse()?));
+                    return Ok(());
+                }
+
+                let runtime: LitStr = value.parse()?;
+                options.runtime = Some(match runtime.value().as_str() {
+                    "inline" => Runtime::Inline,
+                    "boxed" => Runtime::Boxed,
+                    _ => {
+                        return Err(Error::new_spanned(
+                            runtime,
+                            "unsupported runtime; expected \"inline\", \"boxed\", or a path to a `Trampoline` type",
+                        ))
+                    }
+                });
+                return Ok(());
+            }
+
+            Err(meta.error("unsupported #[tailcall] option"))
+        })
+        .parse2(tokens)?;
+
+        if let (Some(arena), Some(Runtime::Boxed | Runtime::Custom(_))) =
+            (&options.arena, &options.runtime)
+        {
+            return Err(Error::new_spanned(
+                arena,
+                "`arena` can only be combined with the inline runtime",
+            ));
+        }
+
+        if let Some(hybrid) = &options.hybrid {
+            if matches!(options.runtime, Some(Runtime::Boxed | Runtime::Custom(_))) {
+                return Err(Error::new_spanned(
+                    hybrid,
+                    "`hybrid` can only be combined with the inline runtime",
+                ));
+            }
+
+            if options.arena.is_some() {
+                return Err(Error::new_spanned(
+                    hybrid,
+                    "`hybrid` cannot be combined with `arena`",
+                ));
+            }
+        }
+
+        Ok(options)
+    }
+
+    // Inner `#[tailcall]` markers inside an `impl` block carry the same options as the attribute
+    // would on a standalone item.
+    pub fn from_attribute(attr: &Attribute) -> Result<Self, Error> {
+        match &attr.meta {
+            Meta::Path(_) => Ok(Self::default()),
+            Meta::List(meta_list) => Self::parse(meta_list.tokens.clone()),
+            Meta::NameValue(meta_name_value) => Err(Error::new_spanned(
+                meta_name_value,
+                "expected `#[tailcall]` or `#[tailcall(...)]`",
+            )),
+        }
+    }
+
+    // `trace` also generates a step enum and a `{name}_steps` iterator, see `steps.rs`.
+    pub fn trace(&self) -> bool {
+        self.trace
+    }
+
+    // `expose_thunk` also generates a public-facing `{name}_thunk` function, see `expand.rs`.
+    pub fn expose_thunk(&self) -> bool {
+        self.expose_thunk
+    }
+
+    // `crate = path` names the `tailcall` crate for generated code, for crates that only reach it
+    // through a renamed dependency or a re-export.
+    pub fn crate_path(&self) -> Path {
+        match &self.crate_path {
+            Some(path) => path.clone(),
+            None => parse_quote!(tailcall),
+        }
+    }
+
+    pub fn call_macro(&self) -> CallMacro {
+        CallMacro::new(self.call_macro.clone())
+    }
+
+    // `runtime = "boxed"` makes helpers return `BoxThunk`, which needs the `alloc` feature of
+    // `tailcall` but never runs out of inline capture space. `runtime = path` makes them return
+    // `path<'tailcall, R>`.
+    pub fn thunk_path(&self) -> Path {
+        let crate_path = self.crate_path();
+
+        match &self.runtime {
+            Some(Runtime::Boxed) => parse_quote!(#crate_path::runtime::BoxThunk),
+            Some(Runtime::Custom(path)) => path.clone(),
+            Some(Runtime::Inline) | None => parse_quote!(#crate_path::runtime::Thunk),
+        }
+    }
+
+    // The built-in runtimes are called through their inherent functions. A custom runtime is
+    // called through `Trampoline`, which does not have to be in scope, and the helper's return
+    // type picks the implementation.
+    pub fn value_fn(&self) -> Path {
+        self.runtime_fn(parse_quote!(value))
+    }
+
+    pub fn bounce_fn(&self) -> Path {
+        self.runtime_fn(parse_quote!(bounce))
+    }
+
+    fn runtime_fn(&self, name: Ident) -> Path {
+        match &self.runtime {
+            Some(Runtime::Custom(_)) => {
+                let crate_path = self.crate_path();
+                parse_quote!(#crate_path::runtime::Trampoline::#name)
+            }
+            _ => {
+                let thunk = self.thunk_path();
+                parse_quote!(#thunk::#name)
+            }
+        }
+    }
+
+    // Runs a helper's result to completion in the wrapper.
+    pub fn run_tokens(&self, thunk: TokenStream) -> TokenStream {
+        match &self.runtime {
+            Some(Runtime::Custom(_)) => {
+                let crate_path = self.crate_path();
+                quote! { #crate_path::runtime::Trampoline::run(#thunk) }
+            }
+            _ => quote! { #thunk.call() },
+        }
+    }
+
+    // Only the inline `Thunk` can store a plain `fn` pointer next to its state.
+    pub fn supports_bounce_with(&self) -> bool {
+        matches!(self.runtime, Some(Runtime::Inline) | None)
+    }
+
+    // `arena = name` bounces through `tailcall::runtime::Arena::bounce(name, ...)`, where `name`
+    // is an argument of type `&Arena`. Every tail call passes an arena along, so it is always in
+    // scope in the helper.
+    pub fn arena(&self) -> Option<&Ident> {
+        self.arena.as_ref()
+    }
+
+    // `hybrid` or `hybrid = depth` bounces through `tailcall::runtime::Thunk::call_or_bounce`,
+    // which makes up to `depth` nested calls on the native stack before falling back to bounces.
+    pub fn hybrid(&self) -> Option<&LitInt> {
+        self.hybrid.as_ref()
+    }
+
+    pub fn check_arena(&self, sig: &Signature) -> Result<(), Error> {
+        let Some(arena) = &self.arena else {
+            return Ok(());
+        };
+
+        let is_argument = sig.inputs.iter().any(|input| match input {
+            FnArg::Typed(PatType { pat, .. }) => {
+                matches!(&**pat, Pat::Ident(pat_ident) if pat_ident.ident == *arena)
+            }
+            FnArg::Receiver(_) => false,
+        });
+
+        if !is_argument {
+            return Err(Error::new_spanned(
+                arena,
+                "`arena` must name one of the function's arguments",
+            ));
+        }
+
+        Ok(())
+    }
+
+    // `check = path` calls `path()` before every logical call (each loop iteration or each
+    // bounce). It returns `ControlFlow<R>` for the function's return type `R`, and `Break(value)`
+    // makes the whole call return `value` without running the remaining calls.
+    pub fn loop_check_stmt(&self) -> Option<Stmt> {
+        let check = self.check.as_ref()?;
+
+        Some(parse_quote! {
+            if let ::core::ops::ControlFlow::Break(__tailcall_value) = #check() {
+                return __tailcall_value;
+            }
+        })
+    }
+
+    // Inside a bounce the check short-circuits the trampoline by resolving to the break value.
+    pub fn bounce_check_stmt(&self) -> Option<Stmt> {
+        let check = self.check.as_ref()?;
+        let value_fn = self.value_fn();
+
+        Some(parse_quote! {
+            if let ::core::ops::ControlFlow::Break(__tailcall_value) = #check() {
+                return #value_fn(__tailcall_value);
+            }
+        })
+    }
+
+    // The attributes copied from the wrapper onto the hidden helper. `#[cfg]` is always copied so
+    // the helper disappears together with the wrapper, and `#[cfg_attr]` is copied with only the
+    // attributes that would have been copied on their own.
+    pub fn helper_attributes(&self, attrs: &[Attribute]) -> Result<Vec<Attribute>, Error> {
+        let mut helper_attrs = Vec::new();
+
+        for attr in attrs {
+            if attr.path().is_ident("cfg_attr") {
+                if let Some(attr) = self.helper_cfg_attr(attr)? {
+                    helper_attrs.push(attr);
+                }
+            } else if self.copies(&attr.meta) {
+                helper_attrs.push(attr.clone());
+            }
+        }
+
+        Ok(helper_attrs)
+    }
+
+    fn helper_cfg_attr(&self, attr: &Attribute) -> Result<Option<Attribute>, Error> {
+        Ok(self
+            .helper_cfg_attr_meta(&attr.meta)?
+            .map(|meta| parse_quote! { #[#meta] }))
+    }
+
+    // Nested `cfg_attr`s are filtered the same way, and dropped once nothing in them is left.
+    fn helper_cfg_attr_meta(&self, meta: &Meta) -> Result<Option<Meta>, Error> {
+        let (predicate, metas) =
+            meta.require_list()?
+                .parse_args_with(|input: syn::parse::ParseStream| {
+                    let predicate: Meta = input.parse()?;
+                    input.parse::<Comma>()?;
+                    let metas = Punctuated::<Meta, Comma>::parse_terminated(input)?;
+                    Ok((predicate, metas))
+                })?;
+        let mut helper_metas = Vec::new();
+
+        for meta in metas {
+            if meta.path().is_ident("cfg_attr") {
+                helper_metas.extend(self.helper_cfg_attr_meta(&meta)?);
+            } else if self.copies(&meta) {
+                helper_metas.push(meta);
+            }
+        }
+
+        if helper_metas.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(
+            parse_quote! { cfg_attr(#predicate, #(#helper_metas),*) },
+        ))
+    }
+
+    fn copies(&self, meta: &Meta) -> bool {
+        meta.path().is_ident("cfg")
+            || self
+                .helper_attrs
+                .iter()
+                .any(|name| meta.path().is_ident(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::quote;
+    use syn::{parse_quote, Attribute};
+
+    use super::{TailcallOptions, DEFAULT_HYBRID_DEPTH};
+
+    fn helper_attributes(options: &TailcallOptions, attrs: &[Attribute]) -> String {
+        let attrs = options
+            .helper_attributes(attrs)
+            .expect("attributes should be valid");
+        quote!(#(#attrs)*).to_string()
+    }
+
+    #[test]
+    fn always_copies_cfg_attributes() {
+        let options = TailcallOptions::default();
+        let attrs: Vec<Attribute> = vec![
+            parse_quote!(#[cfg(feature = "x")]),
+            parse_quote!(#[track_caller]),
+            parse_quote!(#[cfg_attr(test, must_use)]),
+        ];
+
+        assert_eq!(
+            helper_attributes(&options, &attrs),
+            quote!(#[cfg(feature = "x")]).to_string()
+        );
+    }
+
+    #[test]
+    fn copies_named_attributes_and_filters_cfg_attr() {
+        let options = TailcallOptions::parse(quote!(helper_attrs(track_caller, deprecated)))
+            .expect("options should parse");
+        let attrs: Vec<Attribute> = vec![
+            parse_quote!(#[track_caller]),
+            parse_quote!(#[must_use]),
+            parse_quote!(#[cfg_attr(test, deprecated, must_use)]),
+        ];
+
+        assert_eq!(
+            helper_attributes(&options, &attrs),
+            quote!(#[track_caller] #[cfg_attr(test, deprecated)]).to_string()
+        );
+    }
+
+    #[test]
+    fn filters_nested_cfg_attr() {
+        let options =
+            TailcallOptions::parse(quote!(helper_attrs(deprecated))).expect("options should parse");
+        let attrs: Vec<Attribute> = vec![
+            parse_quote!(#[cfg_attr(test, cfg_attr(unix, inline))]),
+            parse_quote!(#[cfg_attr(test, must_use, cfg_attr(unix, inline, deprecated))]),
+        ];
+
+        assert_eq!(
+            helper_attributes(&options, &attrs),
+            quote!(#[cfg_attr(test, cfg_attr(unix, deprecated))]).to_string()
+        );
+    }
+
+    #[test]
+    fn rejects_attributes_the_helper_controls() {
+        let error = TailcallOptions::parse(quote!(helper_attrs(inline)))
+            .err()
+            .expect("inline should be rejected");
+
+        assert!(error.to_string().contains("cannot be copied"));
+    }
+
+    #[test]
+    fn parses_check_paths() {
+        let options =
+            TailcallOptions::parse(quote!(check = crate::deadline::check, helper_attrs(cold)))
+                .expect("options should parse");
+        let check = options.loop_check_stmt().expect("check should be set");
+
+        assert_eq!(
+            quote!(#check).to_string(),
+            quote! {
+                if let ::core::ops::ControlFlow::Break(__tailcall_value) = crate::deadline::check() {
+                    return __tailcall_value;
+                }
+            }
+            .to_string()
+        );
+        assert!(TailcallOptions::default().bounce_check_stmt().is_none());
+    }
+
+    #[test]
+    fn rejects_duplicate_checks() {
+        assert!(TailcallOptions::parse(quote!(check = a, check = b)).is_err());
+    }
+
+    #[test]
+    fn parses_runtimes() {
+        let thunk_path = |tokens| {
+            let path = TailcallOptions::parse(tokens)
+                .expect("options should parse")
+                .thunk_path();
+            quote!(#path).to_string()
+        };
+
+        assert_eq!(
+            thunk_path(quote!(runtime = "boxed")),
+            quote!(tailcall::runtime::BoxThunk).to_string()
+        );
+        assert_eq!(
+            thunk_path(quote!(runtime = "inline")),
+            quote!(tailcall::runtime::Thunk).to_string()
+        );
+        assert_eq!(
+            thunk_path(quote!()),
+            quote!(tailcall::runtime::Thunk).to_string()
+        );
+        assert_eq!(
+            thunk_path(quote!(runtime = crate::arena::ArenaThunk)),
+            quote!(crate::arena::ArenaThunk).to_string()
+        );
+    }
+
+    #[test]
+    fn calls_custom_runtimes_through_the_trampoline_trait() {
+        let options = TailcallOptions::parse(quote!(runtime = crate::arena::ArenaThunk))
+            .expect("options should parse");
+        let value_fn = options.value_fn();
+        let bounce_fn = options.bounce_fn();
+
+        assert_eq!(
+            quote!(#value_fn).to_string(),
+            quote!(tailcall::runtime::Trampoline::value).to_string()
+        );
+        assert_eq!(
+            quote!(#bounce_fn).to_string(),
+            quote!(tailcall::runtime::Trampoline::bounce).to_string()
+        );
+        assert_eq!(
+            options.run_tokens(quote!(helper(n))).to_string(),
+            quote!(tailcall::runtime::Trampoline::run(helper(n))).to_string()
+        );
+        assert!(!options.supports_bounce_with());
+    }
+
+    #[test]
+    fn prefixes_runtime_paths_with_the_crate_path() {
+        let options = TailcallOptions::parse(quote!(crate = ::facade::tailcall))
+            .expect("options should parse");
+        let thunk_path = options.thunk_path();
+        let custom = TailcallOptions::parse(quote!(crate = tc, runtime = my::Runtime))
+            .expect("options should parse");
+        let bounce_fn = custom.bounce_fn();
+
+        assert_eq!(
+            quote!(#thunk_path).to_string(),
+            quote!(::facade::tailcall::runtime::Thunk).to_string()
+        );
+        assert_eq!(
+            quote!(#bounce_fn).to_string(),
+            quote!(tc::runtime::Trampoline::bounce).to_string()
+        );
+        assert!(TailcallOptions::parse(quote!(crate = a, crate = b)).is_err());
+        assert!(TailcallOptions::parse(quote!(crate = "tailcall")).is_err());
+    }
+
+    #[test]
+    fn narrows_call_sites_to_the_call_macro_path() {
+        let options =
+            TailcallOptions::parse(quote!(call_macro = tail)).expect("options should parse");
+        let call_macro = options.call_macro();
+
+        assert!(call_macro.matches(&parse_quote!(tail)));
+        assert!(!call_macro.matches(&parse_quote!(call)));
+        assert!(!call_macro.matches(&parse_quote!(tailcall::call)));
+        assert!(TailcallOptions::parse(quote!(call_macro = a, call_macro = b)).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_and_duplicate_runtimes() {
+        assert!(TailcallOptions::parse(quote!(runtime = "arena")).is_err());
+        assert!(TailcallOptions::parse(quote!(runtime = "boxed", runtime = "boxed")).is_err());
+    }
+
+    #[test]
+    fn rejects_arenas_that_are_not_arguments_or_use_another_runtime() {
+        let sig: syn::Signature = parse_quote!(fn parse(scratch: &Arena, state: [u64; 4]) -> u64);
+        let arena = |tokens| {
+            TailcallOptions::parse(tokens)
+                .expect("options should parse")
+                .check_arena(&sig)
+        };
+
+        assert!(arena(quote!(arena = scratch)).is_ok());
+        assert!(arena(quote!(arena = state)).is_ok());
+        assert!(arena(quote!(arena = buffer)).is_err());
+        assert!(TailcallOptions::parse(quote!(arena = scratch, runtime = "boxed")).is_err());
+        assert!(TailcallOptions::parse(quote!(arena = a, arena = b)).is_err());
+    }
+
+    #[test]
+    fn parses_hybrid_depths() {
+        let depth = |tokens| {
+            TailcallOptions::parse(tokens)
+                .expect("options should parse")
+                .hybrid()
+                .map(|depth| {
+                    depth
+                        .base10_parse::<usize>()
+                        .expect("depth should be a usize")
+                })
+        };
+
+        assert_eq!(depth(quote!()), None);
+        assert_eq!(depth(quote!(hybrid)), Some(DEFAULT_HYBRID_DEPTH));
+        assert_eq!(depth(quote!(hybrid = 256)), Some(256));
+        assert_eq!(
+            depth(quote!(hybrid, runtime = "inline")),
+            Some(DEFAULT_HYBRID_DEPTH)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_hybrid_options() {
+        assert!(TailcallOptions::parse(quote!(hybrid = -1)).is_err());
+        assert!(TailcallOptions::parse(quote!(hybrid = "deep")).is_err());
+        assert!(TailcallOptions::parse(quote!(hybrid, hybrid = 8)).is_err());
+        assert!(TailcallOptions::parse(quote!(hybrid, runtime = "boxed")).is_err());
+        assert!(TailcallOptions::parse(quote!(hybrid, runtime = my::Runtime)).is_err());
+        assert!(TailcallOptions::parse(quote!(hybrid, arena = scratch)).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_options() {
+        assert!(TailcallOptions::parse(quote!(unknown)).is_err());
+    }
+}
@@ -0,0 +1,117 @@
+use syn::{
+    fold::{self, Fold},
+    parse2, Block, Expr, ExprMacro, Item, Stmt, StmtMacro,
+};
+
+use crate::call_syntax::CallMacro;
+
+// Turns every `call!` site in `block` back into the plain call it wraps, for an item that could not
+// be expanded. The item then still type-checks on its own, so the only error left is the one that
+// stopped the expansion. Nested items are left alone, as they carry their own attributes.
+pub fn plain_calls(block: Block, call_macro: &CallMacro) -> Block {
+    PlainCalls { call_macro }.fold_block(block)
+}
+
+struct PlainCalls<'a> {
+    call_macro: &'a CallMacro,
+}
+
+impl PlainCalls<'_> {
+    // Sites that are not a call are kept, so `call!` itself reports them.
+    fn plain_call(&self, expr_macro: &ExprMacro) -> Option<Expr> {
+        if !self.call_macro.matches(&expr_macro.mac.path) {
+            return None;
+        }
+
+        match parse2::<Expr>(expr_macro.mac.tokens.clone()) {
+            Ok(expr @ (Expr::Call(_) | Expr::MethodCall(_))) => Some(expr),
+            _ => None,
+        }
+    }
+}
+
+impl Fold for PlainCalls<'_> {
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        match expr {
+            Expr::Macro(expr_macro) => match self.plain_call(&expr_macro) {
+                Some(expr) => fold::fold_expr(self, expr),
+                None => Expr::Macro(expr_macro),
+            },
+            expr => fold::fold_expr(self, expr),
+        }
+    }
+
+    fn fold_stmt(&mut self, stmt: Stmt) -> Stmt {
+        match stmt {
+            Stmt::Macro(StmtMacro {
+                attrs,
+                mac,
+                semi_token,
+            }) => {
+                let expr_macro = ExprMacro { attrs, mac };
+
+                match self.plain_call(&expr_macro) {
+                    Some(expr) => Stmt::Expr(fold::fold_expr(self, expr), semi_token),
+                    None => Stmt::Macro(StmtMacro {
+                        attrs: expr_macro.attrs,
+                        mac: expr_macro.mac,
+                        semi_token,
+                    }),
+                }
+            }
+            stmt => fold::fold_stmt(self, stmt),
+        }
+    }
+
+    fn fold_item(&mut self, item: Item) -> Item {
+        item
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::quote;
+    use syn::{parse_quote, Block};
+
+    use super::plain_calls;
+    use crate::call_syntax::CallMacro;
+
+    #[test]
+    fn turns_call_sites_into_plain_calls() {
+        let block: Block = parse_quote!({
+            let next = tailcall::call! { step(n - 1) };
+            if n == 0 {
+                tailcall::call! { self.finish(tailcall::call! { total(n) }) }
+            } else {
+                tailcall::call! { walk(next) }
+            }
+        });
+
+        let actual = plain_calls(block, &CallMacro::default());
+        let expected = quote!({
+            let next = step(n - 1);
+            if n == 0 {
+                self.finish(total(n))
+            } else {
+                walk(next)
+            }
+        });
+
+        assert_eq!(quote!(#actual).to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn leaves_other_macros_and_nested_items_alone() {
+        let block: Block = parse_quote!({
+            fn inner(n: u32) -> u32 {
+                tailcall::call! { inner(n) }
+            }
+            println!("{}", n);
+            tailcall::call! { n + 1 }
+        });
+
+        let actual = plain_calls(block.clone(), &CallMacro::default());
+
+        assert_eq!(quote!(#actual).to_string(), quote!(#block).to_string());
+    }
+}
@@ -1,18 +1,38 @@
+use proc_macro2::TokenStream;
 use syn::{
     fold::{self, Fold},
     parse2, parse_quote, Error, Expr, ExprBlock, ExprIf, ExprMacro, ExprMatch, ExprReturn, ExprTry,
-    ItemFn, Stmt,
+    ExprUnsafe, ItemFn, Path, Signature, Stmt,
 };
 
-use crate::call_syntax::{expand_call_macro, is_tailcall_macro};
+use crate::{
+    call_syntax::{call_arguments, expand_call_macro, CallMacro},
+    locals::{local_bindings, reject_borrowed_locals, Locals},
+};
 
-pub struct TailPositionRewriter {
+pub struct TailPositionRewriter<'a> {
+    value_fn: &'a Path,
+    call_macro: &'a CallMacro,
+    locals: Locals,
     error: Option<Error>,
 }
 
-impl TailPositionRewriter {
-    pub fn rewrite(block: syn::Block) -> Result<syn::Block, Error> {
-        let mut rewriter = Self { error: None };
+impl<'a> TailPositionRewriter<'a> {
+    // Values in tail position are wrapped with `#value_fn(...)`. The body stays in the closure the
+    // helper bounces through, so its locals are dropped when a tail call returns the next thunk,
+    // before the trampoline runs it.
+    pub fn rewrite(
+        sig: &Signature,
+        block: syn::Block,
+        value_fn: &'a Path,
+        call_macro: &'a CallMacro,
+    ) -> Result<syn::Block, Error> {
+        let mut rewriter = Self {
+            value_fn,
+            call_macro,
+            locals: local_bindings(sig, &block),
+            error: None,
+        };
         let block = rewriter.rewrite_tail_block(block);
 
         match rewriter.error {
@@ -35,7 +55,12 @@ impl TailPositionRewriter {
                 Stmt::Expr(expr, semi) => Stmt::Expr(self.fold_expr(expr), semi),
                 Stmt::Local(local) => Stmt::Local(self.fold_local(local)),
                 Stmt::Item(item) => Stmt::Item(item),
-                Stmt::Macro(stmt_macro) => Stmt::Macro(stmt_macro),
+                Stmt::Macro(stmt_macro) => {
+                    if self.call_macro.matches(&stmt_macro.mac.path) {
+                        self.check_arguments(&stmt_macro.mac.tokens);
+                    }
+                    Stmt::Macro(stmt_macro)
+                }
             });
         }
 
@@ -90,16 +115,33 @@ impl TailPositionRewriter {
                     })
                     .collect(),
             }),
-            Expr::Macro(expr_macro) if is_tailcall_macro(&expr_macro.mac.path) => {
+            Expr::Unsafe(ExprUnsafe {
+                attrs,
+                unsafe_token,
+                block,
+            }) => Expr::Unsafe(ExprUnsafe {
+                attrs,
+                unsafe_token,
+                block: self.rewrite_tail_block(block),
+            }),
+            Expr::Macro(expr_macro) if self.call_macro.matches(&expr_macro.mac.path) => {
+                self.check_arguments(&expr_macro.mac.tokens);
                 expand_call_expr(expr_macro)
             }
             expr => {
                 let expr = self.fold_expr(expr);
-                parse_quote! { tailcall::runtime::Thunk::value(#expr) }
+                let value_fn = self.value_fn;
+                parse_quote! { #value_fn(#expr) }
             }
         }
     }
 
+    fn check_arguments(&mut self, tokens: &TokenStream) {
+        if let Err(error) = reject_borrowed_locals(&call_arguments(tokens), &self.locals) {
+            self.reject(error);
+        }
+    }
+
     fn reject(&mut self, error: Error) {
         if let Some(existing) = &mut self.error {
             existing.combine(error);
@@ -109,7 +151,7 @@ impl TailPositionRewriter {
     }
 }
 
-impl Fold for TailPositionRewriter {
+impl Fold for TailPositionRewriter<'_> {
     fn fold_expr(&mut self, expr: Expr) -> Expr {
         match expr {
             Expr::Return(ExprReturn {
@@ -137,7 +179,7 @@ impl Fold for TailPositionRewriter {
                     question_token,
                 })
             }
-            Expr::Macro(expr_macro) if is_tailcall_macro(&expr_macro.mac.path) => {
+            Expr::Macro(expr_macro) if self.call_macro.matches(&expr_macro.mac.path) => {
                 self.reject(Error::new_spanned(
                     &expr_macro,
                     "tailcall::call! must be used in tail position",
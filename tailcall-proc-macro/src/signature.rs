@@ -1,10 +1,12 @@
 use proc_macro2::Span;
+use quote::quote;
 use syn::{
-    parse_quote, Error, Expr, FnArg, GenericParam, Lifetime, Pat, PatIdent, PatType, Receiver,
-    ReturnType, Signature, Type, TypeReference,
+    parse_quote, Error, Expr, FnArg, GenericArgument, GenericParam, Generics, Lifetime, Pat,
+    PatIdent, PatType, Path, PathArguments, Receiver, ReturnType, Signature, Type, TypePath,
+    TypeReference, WherePredicate,
 };
 
-use crate::naming::helper_ident;
+use crate::naming::{helper_ident, steps_fn_ident};
 
 pub fn output_type(output: &ReturnType) -> Type {
     match output {
@@ -13,76 +15,170 @@ pub fn output_type(output: &ReturnType) -> Type {
     }
 }
 
-pub fn helper_signature(sig: &Signature) -> Signature {
+// Helpers only ever return a thunk to Rust callers, so they always use the Rust ABI, even when
+// the wrapper is `extern "C"`. They keep `unsafe`, since they run the same body with the same
+// preconditions.
+pub fn helper_signature(sig: &Signature, thunk: &Path) -> Signature {
     let mut helper_sig = sig.clone();
     let output_ty = output_type(&sig.output);
     let tailcall_lifetime = Lifetime::new("'tailcall", Span::call_site());
 
     helper_sig.ident = helper_ident(&sig.ident);
+    helper_sig.abi = None;
     helper_sig
         .generics
         .params
         .push(parse_quote!(#tailcall_lifetime));
     rewrite_elided_lifetimes_in_inputs(&mut helper_sig.inputs, &tailcall_lifetime);
-    helper_sig.output =
-        parse_quote! { -> tailcall::runtime::Thunk<#tailcall_lifetime, #output_ty> };
+    helper_sig.output = parse_quote! { -> #thunk<#tailcall_lifetime, #output_ty> };
 
-    let where_clause = helper_sig.generics.make_where_clause();
-    for generic_param in &sig.generics.params {
-        match generic_param {
-            GenericParam::Type(type_param) => {
-                let ident = &type_param.ident;
-                where_clause
-                    .predicates
-                    .push(parse_quote!(#ident: #tailcall_lifetime));
-            }
-            GenericParam::Lifetime(lifetime_def) => {
-                let lifetime = &lifetime_def.lifetime;
-                where_clause
-                    .predicates
-                    .push(parse_quote!(#lifetime: #tailcall_lifetime));
-            }
-            GenericParam::Const(_) => {}
-        }
-    }
+    bound_generics_by_lifetime(&mut helper_sig.generics, &tailcall_lifetime);
 
     helper_sig
 }
 
-pub fn method_helper_signature(sig: &Signature) -> Result<Signature, Error> {
+pub fn method_helper_signature(
+    sig: &Signature,
+    impl_generics: &Generics,
+    thunk: &Path,
+) -> Result<Signature, Error> {
     let mut helper_sig = sig.clone();
     let output_ty = output_type(&sig.output);
     let tailcall_lifetime = Lifetime::new("'tailcall", Span::call_site());
 
     helper_sig.ident = helper_ident(&sig.ident);
+    helper_sig.abi = None;
     helper_sig
         .generics
         .params
         .push(parse_quote!(#tailcall_lifetime));
     rewrite_method_inputs(&mut helper_sig.inputs, &tailcall_lifetime)?;
-    helper_sig.output =
-        parse_quote! { -> tailcall::runtime::Thunk<#tailcall_lifetime, #output_ty> };
+    helper_sig.output = parse_quote! { -> #thunk<#tailcall_lifetime, #output_ty> };
+
+    if receiver_is_captured_by_value(sig) {
+        helper_sig
+            .generics
+            .make_where_clause()
+            .predicates
+            .push(parse_quote!(Self: #tailcall_lifetime));
+    }
+    bound_impl_generics_by_lifetime(&mut helper_sig.generics, impl_generics, &tailcall_lifetime);
+    bound_generics_by_lifetime(&mut helper_sig.generics, &tailcall_lifetime);
+
+    Ok(helper_sig)
+}
 
-    let where_clause = helper_sig.generics.make_where_clause();
-    for generic_param in &sig.generics.params {
+// The signature of the `{name}_steps` function before its return type becomes the iterator. The
+// step enum stores the arguments and the returned value, so elided lifetimes in them need a name.
+// The `'tailcall` lifetime is only added when something was elided, since the enum would not use
+// it otherwise.
+pub fn steps_signature(sig: &Signature) -> Signature {
+    let mut steps_sig = wrapper_signature(sig);
+    let tailcall_lifetime = Lifetime::new("'tailcall", Span::call_site());
+    let mut output_ty = output_type(&sig.output);
+    let before = quote!(#steps_sig #output_ty).to_string();
+
+    rewrite_elided_lifetimes_in_inputs(&mut steps_sig.inputs, &tailcall_lifetime);
+    rewrite_elided_lifetimes_in_type(&mut output_ty, &tailcall_lifetime);
+
+    if quote!(#steps_sig #output_ty).to_string() != before {
+        steps_sig
+            .generics
+            .params
+            .push(parse_quote!(#tailcall_lifetime));
+    }
+
+    steps_sig.ident = steps_fn_ident(&sig.ident);
+    steps_sig.abi = None;
+    steps_sig.output = parse_quote! { -> #output_ty };
+
+    steps_sig
+}
+
+// Every generic parameter may be captured by the deferred closure, so each one must outlive the
+// thunk. The bound goes next to the parameter's existing inline bounds when it has any, and into
+// the where clause otherwise, so the helper never splits one parameter's bounds across both.
+fn bound_generics_by_lifetime(generics: &mut Generics, lifetime: &Lifetime) {
+    let mut where_predicates: Vec<WherePredicate> = Vec::new();
+
+    for generic_param in &mut generics.params {
         match generic_param {
-            GenericParam::Type(type_param) => {
+            GenericParam::Type(type_param) if type_param.bounds.is_empty() => {
                 let ident = &type_param.ident;
-                where_clause
-                    .predicates
-                    .push(parse_quote!(#ident: #tailcall_lifetime));
+                where_predicates.push(parse_quote!(#ident: #lifetime));
+            }
+            GenericParam::Type(type_param) => {
+                type_param.bounds.push(parse_quote!(#lifetime));
+            }
+            GenericParam::Lifetime(lifetime_def) if lifetime_def.lifetime == *lifetime => {}
+            GenericParam::Lifetime(lifetime_def) if lifetime_def.bounds.is_empty() => {
+                let param_lifetime = &lifetime_def.lifetime;
+                where_predicates.push(parse_quote!(#param_lifetime: #lifetime));
             }
             GenericParam::Lifetime(lifetime_def) => {
-                let lifetime = &lifetime_def.lifetime;
-                where_clause
-                    .predicates
-                    .push(parse_quote!(#lifetime: #tailcall_lifetime));
+                lifetime_def.bounds.push(lifetime.clone());
             }
             GenericParam::Const(_) => {}
         }
     }
 
-    Ok(helper_sig)
+    generics
+        .make_where_clause()
+        .predicates
+        .extend(where_predicates);
+}
+
+// The parameters of the surrounding `impl` block are not declared on the helper itself, so their
+// bounds go into its where clause. A receiver borrowed for `'tailcall` implies them already, but an
+// associated function without a receiver has nothing else tying them to the thunk.
+fn bound_impl_generics_by_lifetime(
+    generics: &mut Generics,
+    impl_generics: &Generics,
+    lifetime: &Lifetime,
+) {
+    let predicates = impl_generics
+        .params
+        .iter()
+        .filter_map(|generic_param| -> Option<WherePredicate> {
+            match generic_param {
+                GenericParam::Type(type_param) => {
+                    let ident = &type_param.ident;
+                    Some(parse_quote!(#ident: #lifetime))
+                }
+                GenericParam::Lifetime(lifetime_def) => {
+                    let param_lifetime = &lifetime_def.lifetime;
+                    Some(parse_quote!(#param_lifetime: #lifetime))
+                }
+                GenericParam::Const(_) => None,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if !predicates.is_empty() {
+        generics.make_where_clause().predicates.extend(predicates);
+    }
+}
+
+// The wrapper either forwards its parameters to the helper or immediately rebinds them as mutable
+// loop state, so keeping `mut` on its own bindings would only trigger `unused_mut`.
+pub fn wrapper_signature(sig: &Signature) -> Signature {
+    let mut wrapper_sig = sig.clone();
+
+    for input in &mut wrapper_sig.inputs {
+        match input {
+            FnArg::Receiver(receiver) if receiver.reference.is_none() => {
+                receiver.mutability = None;
+            }
+            FnArg::Receiver(_) => {}
+            FnArg::Typed(PatType { pat, .. }) => {
+                if let Pat::Ident(pat_ident) = pat.as_mut() {
+                    pat_ident.mutability = None;
+                }
+            }
+        }
+    }
+
+    wrapper_sig
 }
 
 pub fn function_argument_exprs(sig: &Signature) -> Result<Vec<Expr>, Error> {
@@ -130,6 +226,23 @@ fn rewrite_receiver(receiver: &mut Receiver, lifetime: &Lifetime) {
             *receiver_lifetime = Some(lifetime.clone());
         }
     }
+
+    // Shorthand receivers keep `ty` in sync with `reference`, while typed receivers such as
+    // `self: Pin<&mut Self>` only carry their lifetimes in `ty`.
+    rewrite_elided_lifetimes_in_type(receiver.ty.as_mut(), lifetime);
+}
+
+// `&self` and `&mut self` already imply `Self: 'tailcall` once their lifetime is rewritten, but
+// owned and typed receivers (`self`, `self: Box<Self>`, `self: Pin<&mut Self>`) need the bound
+// spelled out because the deferred closure captures them by value.
+fn receiver_is_captured_by_value(sig: &Signature) -> bool {
+    matches!(
+        sig.inputs.first(),
+        Some(FnArg::Receiver(Receiver {
+            reference: None,
+            ..
+        }))
+    )
 }
 
 fn rewrite_elided_lifetimes_in_inputs(
@@ -150,7 +263,10 @@ fn rewrite_elided_lifetimes_in_type(ty: &mut Type, lifetime: &Lifetime) {
             elem,
             ..
         }) => {
-            if ty_lifetime.is_none() {
+            if ty_lifetime
+                .as_ref()
+                .is_none_or(|ty_lifetime| ty_lifetime.ident == "_")
+            {
                 *ty_lifetime = Some(lifetime.clone());
             }
             rewrite_elided_lifetimes_in_type(elem.as_mut(), lifetime);
@@ -172,6 +288,29 @@ fn rewrite_elided_lifetimes_in_type(ty: &mut Type, lifetime: &Lifetime) {
         Type::Group(type_group) => {
             rewrite_elided_lifetimes_in_type(type_group.elem.as_mut(), lifetime)
         }
+        Type::Path(TypePath { qself, path }) => {
+            if let Some(qself) = qself {
+                rewrite_elided_lifetimes_in_type(qself.ty.as_mut(), lifetime);
+            }
+
+            for segment in &mut path.segments {
+                if let PathArguments::AngleBracketed(arguments) = &mut segment.arguments {
+                    for argument in &mut arguments.args {
+                        match argument {
+                            GenericArgument::Type(ty) => {
+                                rewrite_elided_lifetimes_in_type(ty, lifetime)
+                            }
+                            GenericArgument::Lifetime(arg_lifetime)
+                                if arg_lifetime.ident == "_" =>
+                            {
+                                *arg_lifetime = lifetime.clone();
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
         _ => {}
     }
 }
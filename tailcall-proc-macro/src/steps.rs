@@ -0,0 +1,102 @@
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{parse_quote, Error, FnArg, ItemFn, Lifetime, Pat, PatType, Type};
+
+use crate::{
+    call_syntax::CallMacro,
+    loop_lower::lower_self_tail_steps,
+    naming::steps_enum_ident,
+    signature::{output_type, steps_signature},
+};
+
+// With the `trace` option, a loop-lowered function also gets a `{Name}Step` enum and a
+// `{name}_steps` function returning an iterator over the arguments of each logical call followed
+// by the returned value. Each step runs one iteration of the same lowered loop body, inside a
+// labeled block that breaks with the next step instead of looping.
+pub fn steps_items(item_fn: &ItemFn, call_macro: &CallMacro) -> Result<TokenStream, Error> {
+    if let Some(unsafety) = &item_fn.sig.unsafety {
+        return Err(Error::new_spanned(
+            unsafety,
+            "`trace` does not support unsafe functions",
+        ));
+    }
+
+    let vis = &item_fn.vis;
+    let fn_ident = &item_fn.sig.ident;
+    let enum_ident = steps_enum_ident(fn_ident);
+    let label = Lifetime::new("'__tailcall_step", Span::call_site());
+    let step_block = lower_self_tail_steps(item_fn, &enum_ident, &label, call_macro)?;
+
+    let mut steps_sig = steps_signature(&item_fn.sig);
+    let done_ty = output_type(&steps_sig.output);
+    let (_, ty_generics, where_clause) = steps_sig.generics.split_for_impl();
+    let generics = &steps_sig.generics;
+    let (arg_idents, arg_tys): (Vec<_>, Vec<&Type>) = steps_sig
+        .inputs
+        .iter()
+        .filter_map(|input| match input {
+            FnArg::Typed(PatType { pat, ty, .. }) => match &**pat {
+                Pat::Ident(pat_ident) => Some((&pat_ident.ident, &**ty)),
+                _ => None,
+            },
+            FnArg::Receiver(_) => None,
+        })
+        .unzip();
+    // Each step starts from copies of the arguments, bound the way the function binds them.
+    let arg_pats: Vec<&Pat> = item_fn
+        .sig
+        .inputs
+        .iter()
+        .filter_map(|input| match input {
+            FnArg::Typed(PatType { pat, .. }) => Some(&**pat),
+            FnArg::Receiver(_) => None,
+        })
+        .collect();
+
+    let arg_docs: Vec<String> = arg_idents
+        .iter()
+        .map(|ident| format!("The `{ident}` argument."))
+        .collect();
+    let enum_doc = format!(
+        "One step of [`{fn_ident}`]: the arguments of a logical call, or the returned value."
+    );
+    let steps_doc = format!(
+        "Returns the arguments of every logical call [`{fn_ident}`] makes for these arguments, \
+         followed by the value it returns."
+    );
+    let enum_item = quote! {
+        #[doc = #enum_doc]
+        #[derive(Debug)]
+        #vis enum #enum_ident #generics #where_clause {
+            /// A logical call with these arguments.
+            Call { #(#[doc = #arg_docs] #arg_idents: #arg_tys),* },
+            /// The value returned by the outermost call.
+            Done(#done_ty),
+        }
+    };
+
+    steps_sig.output = parse_quote! {
+        -> impl ::core::iter::Iterator<Item = #enum_ident #ty_generics>
+    };
+
+    Ok(quote! {
+        #enum_item
+
+        #[doc = #steps_doc]
+        #vis #steps_sig {
+            let mut __tailcall_pending =
+                ::core::option::Option::Some(#enum_ident::Call { #(#arg_idents),* });
+
+            ::core::iter::from_fn(move || {
+                let __tailcall_step = __tailcall_pending.take()?;
+
+                if let #enum_ident::Call { #(#arg_idents),* } = &__tailcall_step {
+                    #(let #arg_pats = ::core::clone::Clone::clone(#arg_idents);)*
+                    __tailcall_pending = ::core::option::Option::Some(#label: #step_block);
+                }
+
+                ::core::option::Option::Some(__tailcall_step)
+            })
+        }
+    })
+}
@@ -101,6 +101,42 @@
 //! assert!(parity.is_even(1000));
 //! ```
 //!
+//! Free functions, associated functions, and methods may all tail-call each other. Associated
+//! functions without a receiver additionally need `#[tailcall]` on their `impl` block, because an
+//! item-level attribute cannot tell them apart from free functions:
+//!
+//! ```rust
+//! use tailcall::tailcall;
+//!
+//! struct Counter {
+//!     step: u32,
+//! }
+//!
+//! #[tailcall]
+//! impl Counter {
+//!     #[tailcall]
+//!     fn count(&self, remaining: u32) -> u32 {
+//!         if remaining == 0 {
+//!             self.step
+//!         } else {
+//!             tailcall::call! { Self::restart(self, remaining - 1) }
+//!         }
+//!     }
+//!
+//!     #[tailcall]
+//!     fn restart(counter: &Counter, remaining: u32) -> u32 {
+//!         tailcall::call! { advance(counter, remaining) }
+//!     }
+//! }
+//!
+//! #[tailcall]
+//! fn advance(counter: &Counter, remaining: u32) -> u32 {
+//!     tailcall::call! { counter.count(remaining) }
+//! }
+//!
+//! assert_eq!(Counter { step: 3 }.count(1000), 3);
+//! ```
+//!
 //! Mixed recursion is also allowed within a `#[tailcall]` function. A recursive call written with
 //! [`call!`] is handled by the tailcall transform, while a plain recursive call remains an
 //! ordinary Rust call:
@@ -155,6 +191,21 @@
 //! assert_eq!(weighted_countdown(8), 24);
 //! ```
 //!
+//! `#[tailcall]` only applies to functions. Recursive logic that needs to borrow local state can use
+//! [`fix`] instead, which turns a closure into a stack-safe recursive function backed by [`Thunk`]:
+//!
+//! ```rust
+//! use tailcall::{fix, Thunk};
+//!
+//! let input = b"  ,abc";
+//! let skip_separators = fix(|rec, i: usize| match input.get(i) {
+//!     Some(b' ' | b',') => rec.call(i + 1),
+//!     _ => Thunk::value(input.len() - i),
+//! });
+//!
+//! assert_eq!(skip_separators(0), 3);
+//! ```
+//!
 //! In practice, most users should stop here. The macro handles the trampoline machinery and lets
 //! you write recursive code directly, with [`call!`] marking the tail-recursive transitions.
 //!
@@ -180,12 +231,14 @@
 //! remains: if a closure exceeds the configured inline budget, construction panics.
 //! Pending [`Thunk`] values still preserve normal destructor-on-drop behavior for their captures.
 //!
-//! You can construct one in three ways:
+//! You can construct one in four ways:
 //!
 //! - [`Thunk::value`] wraps a value directly
 //! - [`Thunk::new`] wraps a closure that will produce the value
 //! - [`Thunk::bounce`] wraps a closure that will produce another [`Thunk`], which will then
 //!   provide the value
+//! - [`Thunk::bounce_with`] stores some state and a plain `fn` that turns it into another
+//!   [`Thunk`]
 //!
 //! The full computation is resolved with [`Thunk::call`].
 //!
@@ -287,7 +340,7 @@
 //! }
 //!
 //! fn __tailcall_build_gcd_thunk<'tailcall>(a: u64, b: u64) -> tailcall::runtime::Thunk<'tailcall, u64> {
-//!     tailcall::runtime::Thunk::bounce(move || {
+//!     tailcall::runtime::Thunk::bounce_with((a, b), |(a, b)| {
 //!         if b == 0 {
 //!             tailcall::runtime::Thunk::value(a)
 //!         } else {
@@ -300,11 +353,15 @@
 //! Limitations of the current macro:
 //!
 //! - tail-call sites must be written as `tailcall::call! { path(args...) }` or
-//!   `tailcall::call! { self.method(args...) }`
+//!   `tailcall::call! { receiver.method(args...) }`; a call on a receiver other than `self` is
+//!   only loop-lowered when it is spelled `Self::method(receiver, args...)`
 //! - argument patterns must be simple identifiers
 //! - `?` is not supported inside `#[tailcall]` functions on stable Rust; use `match` or explicit
 //!   early returns instead
-//! - trait methods are not supported yet
+//! - associated functions without a receiver need `#[tailcall]` on their `impl` block as well
+//! - trait methods need `#[tailcall]` on the trait and on each `impl` block, and must take `&self`
+//!   or `&mut self` without type or const parameters, so their helpers can be called through
+//!   `dyn Trait`
 //! - mixed recursion is allowed, but only `tailcall::call!` sites participate in the tailcall
 //!   transform; plain recursive calls still use the native call stack
 //! - each generated helper is backed by a [`Thunk`], so very large argument lists or captures can
@@ -313,6 +370,11 @@
 //! The runtime can also be used directly through [`Thunk`] when you want to build the state
 //! machine yourself, but most users should only need the macro API shown above.
 //!
+//! Because tail calls do not leave stack frames behind, the `debug-trace` feature (which requires
+//! `std`) records the most recent logical calls per thread; see the `trace` module. The `stats`
+//! feature instead counts logical calls per function, see the `stats` module, and
+//! [`Thunk::call_with_stats`] reports bounce counts and capture sizes for a single computation.
+//!
 #![no_std]
 #![deny(
     missing_docs,
@@ -324,7 +386,16 @@
     unused_qualifications
 )]
 
-pub use runtime::Thunk;
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+pub use runtime::{fix, Thunk};
 pub use tailcall_proc_macro::{call, tailcall};
 
 pub mod runtime;
+#[cfg(feature = "stats")]
+pub mod stats;
+#[cfg(feature = "debug-trace")]
+pub mod trace;
@@ -0,0 +1,253 @@
+// Scratch storage for closures that do not fit in the inline slot of a `Thunk`.
+
+use core::{
+    cell::Cell,
+    fmt,
+    marker::PhantomData,
+    mem::{align_of, size_of, ManuallyDrop, MaybeUninit},
+    ptr::{self, NonNull},
+};
+
+use super::{ErasedFnOnce, Thunk};
+
+/// A caller-supplied buffer that holds the captures of a [`Thunk`] when they are too large for its
+/// inline slot.
+///
+/// A trampoline only ever has one pending closure, since each bounce is taken out of its
+/// [`Thunk`] before it runs and builds the next one. [`Arena::bounce`] relies on that: an
+/// oversized closure is moved into the buffer, and moved back out right before it runs, so every
+/// bounce reuses the same region without a global allocator. Closures that fit inline are stored
+/// inline as usual and leave the buffer alone.
+///
+/// The arena has to be at hand when a bounce is built, since that is when the next call's
+/// arguments are captured. Recursive helpers therefore take it as an argument and pass it along.
+///
+/// ```rust
+/// use core::mem::MaybeUninit;
+/// use tailcall::runtime::{Arena, Thunk};
+///
+/// fn checksum<'a>(arena: &'a Arena<'_>, state: [u32; 8], n: u32) -> Thunk<'a, u32> {
+///     arena.bounce(move || {
+///         if n == 0 {
+///             Thunk::value(state.iter().fold(0, |acc, x| acc ^ x))
+///         } else {
+///             checksum(arena, state.map(|x| x.rotate_left(n)), n - 1)
+///         }
+///     })
+/// }
+///
+/// let mut scratch = [MaybeUninit::uninit(); 64];
+/// let arena = Arena::new(&mut scratch);
+///
+/// assert_eq!(checksum(&arena, [1; 8], 1_000).call(), 0);
+/// ```
+pub struct Arena<'buf> {
+    start: NonNull<u8>,
+    len: usize,
+    occupied: Cell<bool>,
+    _marker: PhantomData<&'buf mut [MaybeUninit<u8>]>,
+}
+
+impl<'buf> Arena<'buf> {
+    /// Creates an arena that stores oversized closures in `buffer`.
+    pub fn new(buffer: &'buf mut [MaybeUninit<u8>]) -> Self {
+        Self {
+            len: buffer.len(),
+            start: NonNull::from(buffer).cast(),
+            occupied: Cell::new(false),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Produces a pending [`Thunk`] from a `FnOnce`, like [`Thunk::bounce`], but moves the closure
+    /// into this arena when its captures exceed the inline budget.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the closure does not fit in the arena, or if another closure stored in the arena
+    /// has not run or been dropped yet.
+    pub fn bounce<'a, F, T>(&'a self, fn_once: F) -> Thunk<'a, T>
+    where
+        F: FnOnce() -> Thunk<'a, T> + 'a,
+    {
+        if ErasedFnOnce::<Thunk<'a, T>>::fits_inline::<F>() {
+            return Thunk::bounce(fn_once);
+        }
+
+        let stored = self.store(fn_once);
+        Thunk::bounce(move || stored.take()())
+    }
+
+    fn store<F>(&self, value: F) -> Stored<'_, 'buf, F> {
+        assert!(
+            !self.occupied.get(),
+            "tailcall arena already holds a pending closure; only one closure can be pending in an arena at a time",
+        );
+
+        let offset = self.start.as_ptr().align_offset(align_of::<F>());
+        assert!(
+            offset <= self.len && size_of::<F>() <= self.len - offset,
+            "tailcall arena is too small for this closure's captured state; pass a larger buffer to `Arena::new`",
+        );
+
+        // SAFETY: `offset + size_of::<F>()` is within the buffer, `offset` aligns the pointer for
+        // `F`, and nothing else is stored in the buffer.
+        let ptr = unsafe {
+            let ptr = self.start.add(offset).cast::<F>();
+            ptr.write(value);
+            ptr
+        };
+        self.occupied.set(true);
+
+        Stored { arena: self, ptr }
+    }
+}
+
+impl fmt::Debug for Arena<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Arena")
+            .field("len", &self.len)
+            .field("occupied", &self.occupied.get())
+            .finish()
+    }
+}
+
+// Owns the value stored in an arena until it is taken back out or dropped, either of which frees
+// the arena for the next value.
+struct Stored<'a, 'buf, F> {
+    arena: &'a Arena<'buf>,
+    ptr: NonNull<F>,
+}
+
+impl<F> Stored<'_, '_, F> {
+    fn take(self) -> F {
+        let this = ManuallyDrop::new(self);
+        this.arena.occupied.set(false);
+
+        // SAFETY: `ptr` was written in `Arena::store` and is read exactly once, since `this` will
+        // not be dropped.
+        unsafe { this.ptr.read() }
+    }
+}
+
+impl<F> Drop for Stored<'_, '_, F> {
+    fn drop(&mut self) {
+        // SAFETY: `ptr` was written in `Arena::store` and has not been taken out.
+        unsafe { ptr::drop_in_place(self.ptr.as_ptr()) };
+        self.arena.occupied.set(false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::Arena;
+    use crate::runtime::Thunk;
+    use core::mem::MaybeUninit;
+    use std::{cell::Cell, rc::Rc};
+
+    fn sum<'a>(arena: &'a Arena<'_>, values: [u64; 8], i: usize, acc: u64) -> Thunk<'a, u64> {
+        arena.bounce(move || {
+            if i == values.len() {
+                Thunk::value(acc)
+            } else {
+                sum(arena, values, i + 1, acc + values[i])
+            }
+        })
+    }
+
+    struct DropTracker(Rc<Cell<usize>>);
+
+    impl Drop for DropTracker {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn runs_oversized_closures_from_the_arena() {
+        let mut buffer = [MaybeUninit::uninit(); 128];
+        let arena = Arena::new(&mut buffer);
+
+        assert_eq!(sum(&arena, [2; 8], 0, 0).call(), 16);
+        assert!(!arena.occupied.get());
+    }
+
+    #[test]
+    fn reuses_the_arena_for_every_bounce() {
+        fn countdown<'a>(arena: &'a Arena<'_>, state: [u64; 4], n: u32) -> Thunk<'a, u64> {
+            arena.bounce(move || {
+                if n == 0 {
+                    Thunk::value(state[0])
+                } else {
+                    countdown(arena, [state[0] + 1, 0, 0, 0], n - 1)
+                }
+            })
+        }
+
+        let mut buffer = [MaybeUninit::uninit(); 64];
+        let arena = Arena::new(&mut buffer);
+
+        assert_eq!(countdown(&arena, [0; 4], 100_000).call(), 100_000);
+    }
+
+    #[test]
+    fn leaves_closures_that_fit_inline_out_of_the_arena() {
+        let mut buffer = [];
+        let arena = Arena::new(&mut buffer);
+        let n = 3_u32;
+
+        let thunk = arena.bounce(move || Thunk::value(n));
+
+        assert!(!arena.occupied.get());
+        assert_eq!(thunk.call(), 3);
+    }
+
+    #[test]
+    fn dropping_a_pending_bounce_drops_its_captures_and_frees_the_arena() {
+        let drops = Rc::new(Cell::new(0));
+        let tracker = DropTracker(Rc::clone(&drops));
+        let padding = [0_u64; 8];
+        let mut buffer = [MaybeUninit::uninit(); 128];
+        let arena = Arena::new(&mut buffer);
+
+        let thunk = arena.bounce(move || {
+            let _tracker = tracker;
+            Thunk::value(padding.len())
+        });
+        assert!(arena.occupied.get());
+
+        drop(thunk);
+
+        assert_eq!(drops.get(), 1);
+        assert!(!arena.occupied.get());
+    }
+
+    #[test]
+    fn aligns_closures_within_the_buffer() {
+        let mut buffer = [MaybeUninit::uninit(); 128];
+        let arena = Arena::new(&mut buffer[1..]);
+
+        assert_eq!(sum(&arena, [1; 8], 0, 0).call(), 8);
+    }
+
+    #[test]
+    #[should_panic(expected = "tailcall arena is too small")]
+    fn rejects_closures_that_do_not_fit_the_arena() {
+        let mut buffer = [MaybeUninit::uninit(); 16];
+        let arena = Arena::new(&mut buffer);
+
+        let _ = sum(&arena, [1; 8], 0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "already holds a pending closure")]
+    fn rejects_a_second_pending_closure() {
+        let mut buffer = [MaybeUninit::uninit(); 256];
+        let arena = Arena::new(&mut buffer);
+
+        let _first = sum(&arena, [1; 8], 0, 0);
+        let _second = sum(&arena, [1; 8], 0, 0);
+    }
+}
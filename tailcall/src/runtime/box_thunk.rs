@@ -0,0 +1,209 @@
+// Private implementation details for the public `crate::runtime::BoxThunk` type.
+
+use alloc::boxed::Box;
+use core::{any::type_name, fmt};
+
+use super::Thunk;
+
+/// A deferred value whose pending closures are stored on the heap.
+///
+/// [`BoxThunk`] has the same shape as [`Thunk`], but allocates each pending closure instead of
+/// storing it inline, so construction never panics no matter how much state a closure captures.
+/// It trades one allocation per bounce for that.
+///
+/// Values of this type are created with [`BoxThunk::new`], [`BoxThunk::value`], and
+/// [`BoxThunk::bounce`], then consumed by [`BoxThunk::call`]. They convert to and from [`Thunk`]
+/// with [`From`], so both runtimes can be mixed in one computation.
+///
+/// Dropping a pending [`BoxThunk`] drops its captured values, just like a pending [`Thunk`].
+///
+/// ```rust
+/// use tailcall::runtime::BoxThunk;
+///
+/// fn sum(values: [u64; 8], i: usize, acc: u64) -> BoxThunk<'static, u64> {
+///     BoxThunk::bounce(move || {
+///         if i == values.len() {
+///             BoxThunk::value(acc)
+///         } else {
+///             sum(values, i + 1, acc + values[i])
+///         }
+///     })
+/// }
+///
+/// assert_eq!(sum([1, 2, 3, 4, 5, 6, 7, 8], 0, 0).call(), 36);
+/// ```
+pub struct BoxThunk<'a, T>(BoxThunkKind<'a, T>);
+
+enum BoxThunkKind<'a, T> {
+    Done(T),
+    Bounce(Box<dyn FnOnce() -> BoxThunk<'a, T> + 'a>),
+}
+
+impl<'a, T> BoxThunk<'a, T> {
+    /// Produces a pending [`BoxThunk`] from a `FnOnce` that resolves directly to a value.
+    pub fn new<F>(fn_once: F) -> Self
+    where
+        F: FnOnce() -> T + 'a,
+    {
+        Self::bounce(move || Self::value(fn_once()))
+    }
+
+    /// Produces a [`BoxThunk`] that resolves directly to a value.
+    pub const fn value(value: T) -> Self {
+        Self(BoxThunkKind::Done(value))
+    }
+
+    /// Produces a pending [`BoxThunk`] from a `FnOnce`.
+    ///
+    /// The closure must return the next [`BoxThunk`] in the computation.
+    pub fn bounce<F>(fn_once: F) -> Self
+    where
+        F: FnOnce() -> Self + 'a,
+    {
+        Self(BoxThunkKind::Bounce(Box::new(fn_once)))
+    }
+
+    /// Resolves the deferred computation to a final value.
+    #[inline(always)]
+    pub fn call(mut self) -> T {
+        loop {
+            match self.0 {
+                BoxThunkKind::Bounce(fn_once) => self = fn_once(),
+                BoxThunkKind::Done(value) => return value,
+            }
+        }
+    }
+}
+
+// Each pending `Thunk` is moved into one allocation, and the conversion repeats after every
+// bounce, since the closure inside returns another `Thunk`.
+impl<'a, T: 'a> From<Thunk<'a, T>> for BoxThunk<'a, T> {
+    fn from(thunk: Thunk<'a, T>) -> Self {
+        match thunk.into_pending() {
+            Ok(pending) => Self::bounce(move || Self::from(pending.call())),
+            Err(value) => Self::value(value),
+        }
+    }
+}
+
+// A boxed closure is two pointers wide, which always fits in the inline slot of a `Thunk`.
+impl<'a, T: 'a> From<BoxThunk<'a, T>> for Thunk<'a, T> {
+    fn from(thunk: BoxThunk<'a, T>) -> Self {
+        match thunk.0 {
+            BoxThunkKind::Bounce(fn_once) => Thunk::bounce(move || Thunk::from(fn_once())),
+            BoxThunkKind::Done(value) => Thunk::value(value),
+        }
+    }
+}
+
+impl<T> fmt::Debug for BoxThunk<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "BoxThunk -> {}", type_name::<T>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::BoxThunk;
+    use crate::runtime::Thunk;
+    use std::{cell::Cell, rc::Rc};
+
+    fn sum(values: [u64; 16], i: usize, acc: u64) -> BoxThunk<'static, u64> {
+        BoxThunk::bounce(move || {
+            if i == values.len() {
+                BoxThunk::value(acc)
+            } else {
+                sum(values, i + 1, acc + values[i])
+            }
+        })
+    }
+
+    fn countdown(n: u32) -> Thunk<'static, u32> {
+        Thunk::bounce(move || {
+            if n == 0 {
+                Thunk::value(0)
+            } else {
+                countdown(n - 1)
+            }
+        })
+    }
+
+    struct DropTracker(Rc<Cell<usize>>);
+
+    impl Drop for DropTracker {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn accepts_captures_beyond_the_inline_budget() {
+        assert_eq!(sum([3; 16], 0, 0).call(), 48);
+    }
+
+    #[test]
+    fn runs_deep_recursion_without_growing_the_stack() {
+        fn countdown(n: u32) -> BoxThunk<'static, u32> {
+            BoxThunk::bounce(move || {
+                if n == 0 {
+                    BoxThunk::value(0)
+                } else {
+                    countdown(n - 1)
+                }
+            })
+        }
+
+        assert_eq!(countdown(100_000).call(), 0);
+    }
+
+    #[test]
+    fn dropping_a_pending_bounce_drops_its_captures_once() {
+        let drops = Rc::new(Cell::new(0));
+        let tracker = DropTracker(Rc::clone(&drops));
+
+        drop(BoxThunk::new(move || {
+            let _tracker = tracker;
+        }));
+
+        assert_eq!(drops.get(), 1);
+    }
+
+    #[test]
+    fn calling_drops_captures_once() {
+        let drops = Rc::new(Cell::new(0));
+        let tracker = DropTracker(Rc::clone(&drops));
+
+        BoxThunk::new(move || {
+            let _tracker = tracker;
+        })
+        .call();
+
+        assert_eq!(drops.get(), 1);
+    }
+
+    #[test]
+    fn converts_from_a_thunk() {
+        assert_eq!(BoxThunk::from(countdown(10)).call(), 0);
+        assert_eq!(BoxThunk::from(Thunk::value(7)).call(), 7);
+    }
+
+    #[test]
+    fn converts_into_a_thunk() {
+        assert_eq!(Thunk::from(sum([1; 16], 0, 0)).call(), 16);
+        assert_eq!(Thunk::from(BoxThunk::value(7)).call(), 7);
+    }
+
+    #[test]
+    fn dropping_a_converted_thunk_drops_its_captures_once() {
+        let drops = Rc::new(Cell::new(0));
+        let tracker = DropTracker(Rc::clone(&drops));
+
+        drop(BoxThunk::from(Thunk::new(move || {
+            let _tracker = tracker;
+        })));
+
+        assert_eq!(drops.get(), 1);
+    }
+}
@@ -2,13 +2,16 @@
 //!
 //! An [`ErasedFnOnce`] stores the captured data for a single `FnOnce` in a fixed-size stack slot
 //! together with the function pointers needed to either call it or drop it in place.
+//!
+//! It can also store a plain `fn(S) -> T` together with its argument state, in which case the
+//! function pointer lives in a context word next to the slot and the table only depends on `S`.
 
 use core::{
     any::type_name,
     fmt,
     marker::PhantomData,
-    mem::{align_of, size_of, ManuallyDrop},
-    ptr::{drop_in_place, read, NonNull},
+    mem::{size_of, transmute, ManuallyDrop},
+    ptr::{self, read, NonNull},
 };
 
 use super::slot::Slot;
@@ -29,17 +32,58 @@ const MAX_CLOSURE_DATA_SIZE: usize = 16;
 // On 64-bit targets, the closure slot is chosen so the overall `Thunk` lands on one of the
 // useful size plateaus instead of wasting the extra aligned bytes between them.
 type ErasedFnOnceSlot = Slot<MAX_CLOSURE_DATA_SIZE>;
-type CallFn<T> = unsafe fn(ErasedFnOnceSlot) -> T;
+type CallFn<T> = unsafe fn(ErasedFnOnceSlot, *const ()) -> T;
 type DropInPlaceFn = unsafe fn(*mut ErasedFnOnceSlot);
 
 struct ErasedFnOnceVtable<T> {
     call_impl: CallFn<T>,
     drop_in_place_impl: DropInPlaceFn,
+    captured_size: usize,
+}
+
+// Going through an associated const lets the table be promoted to a `'static` even though it
+// depends on `size_of::<F>()`.
+struct VtableFor<F, T>(PhantomData<(F, T)>);
+
+impl<F: FnOnce() -> T, T> VtableFor<F, T> {
+    const VTABLE: ErasedFnOnceVtable<T> = ErasedFnOnceVtable {
+        call_impl: |slot, _context| {
+            // SAFETY: `slot` is initialized in `ErasedFnOnce::new` with `F`.
+            unsafe { slot.into_value::<F>()() }
+        },
+        drop_in_place_impl: |slot_ptr| {
+            // SAFETY: `slot` is initialized in `ErasedFnOnce::new` with `F`.
+            unsafe { Slot::drop_value::<F>(slot_ptr) };
+        },
+        captured_size: size_of::<F>(),
+    };
+}
+
+// The table for a `fn(S) -> T` is shared by every function with the same state and result types.
+struct StateVtableFor<S, T>(PhantomData<(S, T)>);
+
+impl<S, T> StateVtableFor<S, T> {
+    const VTABLE: ErasedFnOnceVtable<T> = ErasedFnOnceVtable {
+        call_impl: |slot, context| {
+            // SAFETY: `context` is the `fn(S) -> T` stored in `ErasedFnOnce::with_state`.
+            let step = unsafe { transmute::<*const (), fn(S) -> T>(context) };
+            // SAFETY: `slot` is initialized in `ErasedFnOnce::with_state` with `S`.
+            step(unsafe { slot.into_value::<S>() })
+        },
+        drop_in_place_impl: |slot_ptr| {
+            // SAFETY: `slot` is initialized in `ErasedFnOnce::with_state` with `S`.
+            unsafe { Slot::drop_value::<S>(slot_ptr) };
+        },
+        captured_size: size_of::<S>(),
+    };
 }
 
 pub(crate) struct ErasedFnOnce<'a, T = ()> {
     slot: ErasedFnOnceSlot,
     vtable: NonNull<ErasedFnOnceVtable<T>>,
+    // Extra data for `call_impl`. On 64-bit targets it fits in what would otherwise be padding
+    // after `vtable`, so it does not change the size of a `Thunk`.
+    context: *const (),
     _marker: PhantomData<dyn FnOnce() -> T + 'a>,
 }
 
@@ -47,49 +91,75 @@ impl<'a, T> ErasedFnOnce<'a, T> {
     /// Creates a new erased thunk from a `FnOnce`.
     ///
     /// The closure's captured state is stored inline in a fixed-size slot. Construction will panic
-    /// if the closure's size or alignment exceeds the slot budget chosen by the runtime.
+    /// if the closure's size exceeds the slot budget chosen by the runtime. Captures that are more
+    /// aligned than the slot are accepted as long as they fit, but since a type's size is a
+    /// multiple of its alignment, a non-empty capture aligned above the budget never does.
     pub(crate) const fn new<F>(fn_once: F) -> Self
     where
         F: FnOnce() -> T + 'a,
     {
-        assert!(
-            align_of::<F>() <= align_of::<ErasedFnOnceSlot>(),
-            "tailcall runtime cannot store this closure inline because its alignment exceeds the thunk slot alignment; reduce what the closure captures or move large/over-aligned state behind a pointer",
-        );
-
         assert!(
             size_of::<F>() <= MAX_CLOSURE_DATA_SIZE,
-            "tailcall runtime cannot store this closure inline because its captured state exceeds the configured thunk slot capacity; reduce captures, pass state as function arguments, box large captured values, or enable a larger thunk size feature",
+            "tailcall runtime cannot store this closure inline because its captured state exceeds the configured thunk slot capacity, which also limits over-aligned captures since their size is a multiple of their alignment; reduce captures, pass state as function arguments, box large or over-aligned captured values, bounce through `Thunk::bounce_or_box` or an `Arena`, or enable a larger thunk size feature",
         );
 
         Self {
             slot: Slot::new(fn_once),
             vtable: {
-                let vtable: *const ErasedFnOnceVtable<T> = &ErasedFnOnceVtable {
-                    call_impl: |slot| {
-                        // SAFETY: `slot` is initialized above with `F`.
-                        unsafe { slot.into_value::<F>()() }
-                    },
-                    drop_in_place_impl: |slot_ptr| {
-                        // SAFETY: `slot` is initialized above with `F`.
-                        unsafe { drop_in_place(slot_ptr.cast::<F>()) };
-                    },
-                };
+                let vtable: *const ErasedFnOnceVtable<T> = &VtableFor::<F, T>::VTABLE;
 
                 // SAFETY: `vtable` points at the static per-closure-type table above and is
                 // therefore never null.
                 unsafe { NonNull::new_unchecked(vtable.cast_mut()) }
             },
+            context: ptr::null(),
             _marker: PhantomData,
         }
     }
 
+    /// Creates a new erased thunk that calls `step` with `state`.
+    ///
+    /// `state` is stored inline under the same budget as a closure's captured state.
+    pub(crate) const fn with_state<S>(state: S, step: fn(S) -> T) -> Self
+    where
+        S: 'a,
+    {
+        assert!(
+            size_of::<S>() <= MAX_CLOSURE_DATA_SIZE,
+            "tailcall runtime cannot store this state inline because it exceeds the configured thunk slot capacity, which also limits over-aligned state since its size is a multiple of its alignment; reduce the state, box large or over-aligned values, or enable a larger thunk size feature",
+        );
+
+        Self {
+            slot: Slot::new(state),
+            vtable: {
+                let vtable: *const ErasedFnOnceVtable<T> = &StateVtableFor::<S, T>::VTABLE;
+
+                // SAFETY: `vtable` points at the static per-state-type table above and is
+                // therefore never null.
+                unsafe { NonNull::new_unchecked(vtable.cast_mut()) }
+            },
+            context: step as *const (),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns whether an `S` can be stored inline by [`ErasedFnOnce::new`] or
+    /// [`ErasedFnOnce::with_state`] without panicking.
+    pub(crate) const fn fits_inline<S>() -> bool {
+        size_of::<S>() <= MAX_CLOSURE_DATA_SIZE
+    }
+
     #[inline(always)]
     fn vtable(&self) -> &ErasedFnOnceVtable<T> {
         // SAFETY: `vtable` always points at the static per-closure-type table created in `new`.
         unsafe { self.vtable.as_ref() }
     }
 
+    /// Returns the size of the state captured by the stored `FnOnce`.
+    pub(crate) fn captured_size(&self) -> usize {
+        self.vtable().captured_size
+    }
+
     #[inline(always)]
     /// Calls the stored `FnOnce`, consuming the erased thunk in the process.
     pub(crate) fn call(self) -> T {
@@ -100,8 +170,8 @@ impl<'a, T> ErasedFnOnce<'a, T> {
         let slot = unsafe { read(&this.slot) };
         let vtable = this.vtable();
 
-        // SAFETY: This is the exact `call_impl` for the slot created above.
-        unsafe { (vtable.call_impl)(slot) }
+        // SAFETY: This is the exact `call_impl` for the slot and context created above.
+        unsafe { (vtable.call_impl)(slot, this.context) }
     }
 }
 
@@ -163,6 +233,17 @@ mod tests {
         assert!(message.contains("pass state as function arguments"));
     }
 
+    #[test]
+    fn reports_captured_size() {
+        let x: u64 = 1;
+        let y: u32 = 2;
+
+        let thunk = ErasedFnOnce::new(move || x + u64::from(y));
+
+        assert_eq!(thunk.captured_size(), 16);
+        assert_eq!(ErasedFnOnce::new(|| 42).captured_size(), 0);
+    }
+
     #[test]
     fn dropping_without_call_runs_destructor_once() {
         let drops = std::rc::Rc::new(std::cell::Cell::new(0));
@@ -8,19 +8,28 @@
 //! produce the value.
 //!
 //! On 64-bit targets, the default runtime keeps [`Thunk`] at 32 bytes. Optional crate features
-//! can opt into larger [`Thunk`] sizes to support larger inline captures. If a closure still
-//! exceeds the configured inline budget, [`Thunk`] construction panics.
+//! can opt into larger [`Thunk`] sizes to support larger inline captures, or into an 8- or 4-byte
+//! aligned inline slot. Captures that are more aligned than the slot are still accepted. If a
+//! closure exceeds the configured inline budget, [`Thunk`] construction panics.
 //!
 //! Pending [`Thunk`] values still preserve normal destructor-on-drop behavior for anything they
 //! capture.
 //!
-//! You can construct one in three ways:
+//! You can construct one in five ways:
 //!
 //! - [`Thunk::value`] wraps a value directly
 //! - [`Thunk::new`] wraps a closure that will produce the value
 //! - [`Thunk::bounce`] wraps a closure that will produce another [`Thunk`]
+//! - [`Thunk::bounce_with`] stores some state and a plain `fn` that turns it into another
+//!   [`Thunk`]
+//! - [`Thunk::call_or_bounce`] calls a closure that produces another [`Thunk`] right away while
+//!   the native call depth allows it, and wraps it like [`Thunk::bounce`] otherwise
 //!
-//! The full computation is resolved with [`Thunk::call`].
+//! The full computation is resolved with [`Thunk::call`]. [`Thunk::call_with_stats`] resolves it
+//! the same way and also returns [`Stats`] about the bounces it took, while
+//! [`Thunk::call_with_hook`] periodically runs a hook that can stop the computation and hand back
+//! the pending [`Thunk`]. [`Thunk::into_steps`] turns the computation into an iterator that runs
+//! one bounce per step.
 //!
 //! A direct runtime implementation usually consists of:
 //!
@@ -76,10 +85,32 @@
 //!
 //! assert_eq!(skip_leading_separators("  ,abc"), 3);
 //! ```
+//!
+//! With the `alloc` feature, `BoxThunk` is a variant of [`Thunk`] that stores each pending
+//! closure on the heap instead, so it has no capture budget. Without an allocator, [`Arena`]
+//! moves oversized closures into a caller-supplied buffer.
+//!
+//! [`Thunk`] and `BoxThunk` both implement [`Trampoline`], the interface that
+//! `#[tailcall(runtime = path)]` generates code against, so the macro can also target runtimes
+//! defined outside this crate.
+//!
+//! [`fix`] builds a stack-safe recursive closure on top of [`Thunk`]. The closure may borrow its
+//! environment, and each bounce only captures a [`Recurse`] pointer and the next arguments.
 
+mod arena;
+#[cfg(feature = "alloc")]
+mod box_thunk;
 mod erased_fn_once;
+mod fix;
+mod native_depth;
 mod slot;
 mod thunk;
+mod trampoline;
 
+pub use arena::Arena;
+#[cfg(feature = "alloc")]
+pub use box_thunk::BoxThunk;
 use erased_fn_once::ErasedFnOnce;
-pub use thunk::Thunk;
+pub use fix::{fix, Recurse};
+pub use thunk::{Stats, Step, Steps, Thunk};
+pub use trampoline::Trampoline;
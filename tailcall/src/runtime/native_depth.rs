@@ -0,0 +1,53 @@
+// Tracks how many calls `Thunk::call_or_bounce` has made natively that have not returned yet.
+//
+// With `std` the count is kept per thread. Without it, one count is shared by every thread, which
+// can only make a thread switch to bounces sooner than it would on its own.
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    static DEPTH: core::cell::Cell<usize> = const { core::cell::Cell::new(0) };
+}
+
+#[cfg(not(feature = "std"))]
+static DEPTH: core::sync::atomic::AtomicUsize = core::sync::atomic::AtomicUsize::new(0);
+
+// A native call that counts towards the depth until it is dropped, including while unwinding.
+pub(crate) struct NativeCall(());
+
+impl NativeCall {
+    // Enters a native call if fewer than `limit` are in progress.
+    #[inline(always)]
+    pub(crate) fn enter(limit: usize) -> Option<Self> {
+        #[cfg(feature = "std")]
+        let entered = DEPTH.with(|depth| {
+            let current = depth.get();
+            let entered = current < limit;
+            if entered {
+                depth.set(current + 1);
+            }
+            entered
+        });
+
+        #[cfg(not(feature = "std"))]
+        let entered = DEPTH
+            .fetch_update(
+                core::sync::atomic::Ordering::Relaxed,
+                core::sync::atomic::Ordering::Relaxed,
+                |current| (current < limit).then_some(current + 1),
+            )
+            .is_ok();
+
+        entered.then(|| Self(()))
+    }
+}
+
+impl Drop for NativeCall {
+    #[inline(always)]
+    fn drop(&mut self) {
+        #[cfg(feature = "std")]
+        DEPTH.with(|depth| depth.set(depth.get() - 1));
+
+        #[cfg(not(feature = "std"))]
+        DEPTH.fetch_sub(1, core::sync::atomic::Ordering::Relaxed);
+    }
+}
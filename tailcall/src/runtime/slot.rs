@@ -1,6 +1,20 @@
-use core::mem::{align_of, size_of, ManuallyDrop, MaybeUninit};
+use core::{
+    mem::{align_of, size_of, ManuallyDrop, MaybeUninit},
+    ptr::{drop_in_place, read_unaligned},
+};
 
-#[repr(C, align(16))]
+// Slots are 16-byte aligned unless a `slot-align-*` feature asks for less. When several are
+// enabled the largest alignment wins.
+#[repr(C)]
+#[cfg_attr(
+    not(any(feature = "slot-align-8", feature = "slot-align-4")),
+    repr(align(16))
+)]
+#[cfg_attr(feature = "slot-align-8", repr(align(8)))]
+#[cfg_attr(
+    all(feature = "slot-align-4", not(feature = "slot-align-8")),
+    repr(align(4))
+)]
 pub(crate) struct Slot<const SIZE: usize> {
     bytes: MaybeUninit<[u8; SIZE]>,
 }
@@ -14,14 +28,17 @@ union SlotView<T, const SIZE: usize> {
 impl<const SIZE: usize> Slot<SIZE> {
     // `Slot<SIZE>` can store any `T` that fits within the declared byte capacity. Any tail
     // padding introduced by the alignment on `Slot` itself is not treated as usable storage.
+    //
+    // A `T` that is more aligned than the slot is stored at offset zero anyway. Moving it in and
+    // out goes through `SlotView`, which is aligned for both, so only dropping it needs to care.
+    // It still has to fit: its size is a multiple of its alignment, so `Slot<16>` only takes
+    // over-aligned values that are zero-sized.
     pub(crate) const fn new<T>(value: T) -> Self {
         assert!(
-            align_of::<T>() <= align_of::<Self>(),
-            "unsupported value alignment",
+            size_of::<T>() <= SIZE,
+            "value size exceeds slot capacity; over-aligned values take at least their alignment in bytes"
         );
 
-        assert!(size_of::<T>() <= SIZE, "value size exceeds slot capacity");
-
         SlotView::of_value(value).into_slot()
     }
 
@@ -29,6 +46,19 @@ impl<const SIZE: usize> Slot<SIZE> {
     pub(crate) const unsafe fn into_value<T>(self) -> T {
         unsafe { SlotView::of_slot(self).into_value() }
     }
+
+    // SAFETY: The caller must ensure that `slot` points at a slot containing a valid `T`, which
+    // must not be used afterwards.
+    //
+    // A `T` that is more aligned than the slot may be misaligned where it sits, so it is moved
+    // into an aligned temporary and dropped there instead.
+    pub(crate) unsafe fn drop_value<T>(slot: *mut Self) {
+        if align_of::<T>() <= align_of::<Self>() {
+            unsafe { drop_in_place(slot.cast::<T>()) }
+        } else {
+            drop(unsafe { read_unaligned(slot.cast::<T>()) })
+        }
+    }
 }
 
 impl<T, const SIZE: usize> SlotView<T, SIZE> {
@@ -60,10 +90,21 @@ mod tests {
     extern crate std;
 
     use super::Slot;
-    use core::mem::size_of;
+    use core::mem::{align_of, size_of};
 
+    #[derive(Clone, Copy, Debug, PartialEq)]
     #[repr(align(32))]
-    struct OverAligned;
+    struct OverAligned([u8; 32]);
+
+    #[repr(align(64))]
+    struct DropCounter<'a>(&'a std::cell::Cell<usize>);
+
+    impl Drop for DropCounter<'_> {
+        fn drop(&mut self) {
+            assert_eq!(core::ptr::from_mut(self).align_offset(64), 0);
+            self.0.set(self.0.get() + 1);
+        }
+    }
 
     #[test]
     fn round_trips_stored_value() {
@@ -74,12 +115,32 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(any(feature = "slot-align-8", feature = "slot-align-4")))]
     fn slot_layout_includes_alignment_padding() {
+        assert_eq!(align_of::<Slot<1>>(), 16);
         assert_eq!(size_of::<Slot<1>>(), 16);
         assert_eq!(size_of::<Slot<16>>(), 16);
         assert_eq!(size_of::<Slot<17>>(), 32);
     }
 
+    #[test]
+    #[cfg(feature = "slot-align-8")]
+    fn slot_layout_includes_alignment_padding_with_8_byte_alignment() {
+        assert_eq!(align_of::<Slot<1>>(), 8);
+        assert_eq!(size_of::<Slot<1>>(), 8);
+        assert_eq!(size_of::<Slot<16>>(), 16);
+        assert_eq!(size_of::<Slot<17>>(), 24);
+    }
+
+    #[test]
+    #[cfg(all(feature = "slot-align-4", not(feature = "slot-align-8")))]
+    fn slot_layout_includes_alignment_padding_with_4_byte_alignment() {
+        assert_eq!(align_of::<Slot<1>>(), 4);
+        assert_eq!(size_of::<Slot<1>>(), 4);
+        assert_eq!(size_of::<Slot<16>>(), 16);
+        assert_eq!(size_of::<Slot<17>>(), 20);
+    }
+
     #[test]
     fn round_trips_value_up_to_declared_capacity() {
         let value = [7_u8; 16];
@@ -96,8 +157,21 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "unsupported value alignment")]
-    fn rejects_values_with_unsupported_alignment() {
-        let _ = Slot::<32>::new(OverAligned);
+    fn round_trips_values_aligned_beyond_the_slot() {
+        let value = OverAligned([3; 32]);
+        let slot = Slot::<32>::new(value);
+        let round_trip = unsafe { slot.into_value::<OverAligned>() };
+
+        assert_eq!(round_trip, value);
+    }
+
+    #[test]
+    fn drops_values_aligned_beyond_the_slot() {
+        let drops = std::cell::Cell::new(0);
+        let mut slot = Slot::<64>::new(DropCounter(&drops));
+
+        unsafe { Slot::drop_value::<DropCounter<'_>>(&mut slot) };
+
+        assert_eq!(drops.get(), 1);
     }
 }
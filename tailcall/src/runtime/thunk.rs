@@ -1,8 +1,10 @@
 // Private implementation details for the public `crate::runtime::Thunk` type.
 
-use core::{any::type_name, fmt};
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+use core::{any::type_name, fmt, iter::FusedIterator, ops::ControlFlow};
 
-use super::ErasedFnOnce;
+use super::{native_depth::NativeCall, ErasedFnOnce};
 
 /// A fixed-size deferred value in the thunk runtime.
 ///
@@ -10,15 +12,18 @@ use super::ErasedFnOnce;
 /// type-erased closure that will eventually produce the value.
 ///
 /// On 64-bit targets, the default runtime representation keeps [`Thunk`] at 32 bytes. Optional
-/// crate features can trade a larger [`Thunk`] for a larger inline closure budget. As a result,
+/// crate features can trade a larger [`Thunk`] for a larger inline closure budget, or lower the
+/// 16-byte alignment of the inline slot for small targets. As a result,
 /// manual [`Thunk`] values and macro-generated helpers can only capture a limited amount of data
 /// before construction panics.
 ///
 /// Dropping a pending [`Thunk`] still preserves normal destructor semantics for its captured
 /// values.
 ///
-/// Values of this type are created with [`Thunk::new`], [`Thunk::value`], and [`Thunk::bounce`],
-/// then consumed by [`Thunk::call`].
+/// Values of this type are created with [`Thunk::new`], [`Thunk::value`], [`Thunk::bounce`], and
+/// [`Thunk::bounce_with`],
+/// then consumed by [`Thunk::call`], [`Thunk::call_with_stats`], [`Thunk::call_with_hook`], or
+/// [`Thunk::into_steps`].
 pub struct Thunk<'a, T>(ThunkKind<'a, T>);
 
 enum ThunkKind<'a, T> {
@@ -43,6 +48,14 @@ impl<'a, T> Thunk<'a, T> {
     /// Produces a pending [`Thunk`] from a `FnOnce`.
     ///
     /// The closure must return the next [`Thunk`] in the computation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the closure's captures do not fit the inline slot. A type's size is a multiple of
+    /// its alignment, so with the default budget this rejects every non-empty capture aligned to
+    /// 32 bytes or more, such as SIMD vectors or cache-line-padded state. Bouncing those is opt-in,
+    /// through `Thunk::bounce_or_box` with the `alloc` feature or
+    /// [`Arena::bounce`](super::Arena::bounce).
     pub const fn bounce<F>(fn_once: F) -> Self
     where
         F: FnOnce() -> Self + 'a,
@@ -50,6 +63,119 @@ impl<'a, T> Thunk<'a, T> {
         Self(ThunkKind::Bounce(ErasedFnOnce::new(fn_once)))
     }
 
+    /// Produces a pending [`Thunk`] from a `FnOnce`, like [`Thunk::bounce`], but moves the closure
+    /// to the heap when its captures are too large for the inline slot.
+    ///
+    /// Closures that fit inline are stored inline as usual, so only the oversized bounces, such as
+    /// ones capturing `#[repr(align(64))]` state, pay for an allocation.
+    ///
+    /// ```rust
+    /// use tailcall::runtime::Thunk;
+    ///
+    /// #[derive(Clone, Copy)]
+    /// #[repr(align(32))]
+    /// struct Lanes([f32; 8]);
+    ///
+    /// fn scale(lanes: Lanes, n: u32) -> Thunk<'static, f32> {
+    ///     Thunk::bounce_or_box(move || {
+    ///         if n == 0 {
+    ///             Thunk::value(lanes.0.iter().sum())
+    ///         } else {
+    ///             scale(Lanes(lanes.0.map(|lane| lane * 0.5)), n - 1)
+    ///         }
+    ///     })
+    /// }
+    ///
+    /// assert_eq!(scale(Lanes([4.0; 8]), 2).call(), 8.0);
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn bounce_or_box<F>(fn_once: F) -> Self
+    where
+        F: FnOnce() -> Self + 'a,
+    {
+        if ErasedFnOnce::<Self>::fits_inline::<F>() {
+            return Self::bounce(fn_once);
+        }
+
+        let boxed = Box::new(fn_once);
+        Self::bounce(boxed)
+    }
+
+    /// Produces a pending [`Thunk`] that calls `step` with `state`.
+    ///
+    /// This is equivalent to `Thunk::bounce(move || step(state))`, but stores the function
+    /// pointer next to the state instead of building a new closure type. Every builder with the
+    /// same state and result types shares the same dispatch code, which is what the
+    /// [`crate::tailcall`] macro uses for its generated helpers.
+    ///
+    /// `state` is stored inline under the same budget as a closure's captures, so several
+    /// arguments are passed as a tuple:
+    ///
+    /// ```rust
+    /// use tailcall::runtime::Thunk;
+    ///
+    /// fn gcd(a: u32, b: u32) -> Thunk<'static, u32> {
+    ///     Thunk::bounce_with((a, b), |(a, b)| {
+    ///         if b == 0 {
+    ///             Thunk::value(a)
+    ///         } else {
+    ///             gcd(b, a % b)
+    ///         }
+    ///     })
+    /// }
+    ///
+    /// assert_eq!(gcd(12, 18).call(), 6);
+    /// ```
+    pub const fn bounce_with<S>(state: S, step: fn(S) -> Self) -> Self
+    where
+        S: 'a,
+    {
+        Self(ThunkKind::Bounce(ErasedFnOnce::with_state(state, step)))
+    }
+
+    /// Calls `fn_once` right away while fewer than `limit` calls made this way are still running
+    /// on the current thread, and defers it like [`Thunk::bounce`] otherwise.
+    ///
+    /// Shallow recursions then run as plain native calls, and only the part deeper than `limit`
+    /// pays for a bounce, once every `limit` calls. `limit` bounds how much native stack the
+    /// recursion can use. This is what `#[tailcall(hybrid)]` helpers use.
+    ///
+    /// ```rust
+    /// use tailcall::runtime::Thunk;
+    ///
+    /// fn is_even(n: u32) -> Thunk<'static, bool> {
+    ///     Thunk::call_or_bounce(64, move || if n == 0 { Thunk::value(true) } else { is_odd(n - 1) })
+    /// }
+    ///
+    /// fn is_odd(n: u32) -> Thunk<'static, bool> {
+    ///     Thunk::call_or_bounce(64, move || if n == 0 { Thunk::value(false) } else { is_even(n - 1) })
+    /// }
+    ///
+    /// assert!(is_even(1_000_000).call());
+    /// ```
+    ///
+    /// Without the `std` feature the count is shared by all threads, so a thread may switch to
+    /// bouncing sooner than `limit`, but never later.
+    #[inline(always)]
+    pub fn call_or_bounce<F>(limit: usize, fn_once: F) -> Self
+    where
+        F: FnOnce() -> Self + 'a,
+    {
+        match NativeCall::enter(limit) {
+            Some(_native_call) => fn_once(),
+            None => Self::bounce(fn_once),
+        }
+    }
+
+    // Splits a pending `Thunk` into its next bounce without running it.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn into_pending(self) -> Result<ErasedFnOnce<'a, Self>, T> {
+        match self.0 {
+            ThunkKind::Bounce(erased_fn_once) => Ok(erased_fn_once),
+            ThunkKind::Done(value) => Err(value),
+        }
+    }
+
     /// Resolves the deferred computation to a final value.
     #[inline(always)]
     pub fn call(mut self) -> T {
@@ -60,6 +186,180 @@ impl<'a, T> Thunk<'a, T> {
             }
         }
     }
+
+    /// Resolves the deferred computation like [`Thunk::call`], but runs `hook` every `every`
+    /// bounces so a long computation can be cancelled or paused from outside.
+    ///
+    /// When `hook` returns [`ControlFlow::Break`], the rest of the computation is returned as
+    /// `Err` without running another bounce. It can be resumed later by calling any of the
+    /// resolving methods on it, or dropped to cancel it.
+    ///
+    /// ```rust
+    /// use core::ops::ControlFlow;
+    /// use tailcall::runtime::Thunk;
+    ///
+    /// fn countdown(n: u32) -> Thunk<'static, u32> {
+    ///     Thunk::bounce(move || if n == 0 { Thunk::value(0) } else { countdown(n - 1) })
+    /// }
+    ///
+    /// let mut budget = 3;
+    /// let paused = countdown(1_000)
+    ///     .call_with_hook(100, || {
+    ///         budget -= 1;
+    ///         if budget == 0 {
+    ///             ControlFlow::Break(())
+    ///         } else {
+    ///             ControlFlow::Continue(())
+    ///         }
+    ///     })
+    ///     .unwrap_err();
+    ///
+    /// assert_eq!(paused.call(), 0);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `every` is zero.
+    pub fn call_with_hook<H>(mut self, every: usize, mut hook: H) -> Result<T, Self>
+    where
+        H: FnMut() -> ControlFlow<()>,
+    {
+        assert!(every > 0, "`every` must be greater than zero");
+        let mut until_hook = every;
+
+        loop {
+            match self.0 {
+                ThunkKind::Bounce(erased_fn_once) => {
+                    if until_hook == 0 {
+                        until_hook = every;
+
+                        if hook().is_break() {
+                            return Err(Self(ThunkKind::Bounce(erased_fn_once)));
+                        }
+                    }
+
+                    until_hook -= 1;
+                    self = erased_fn_once.call();
+                }
+                ThunkKind::Done(value) => return Ok(value),
+            }
+        }
+    }
+
+    /// Returns an iterator that resolves the deferred computation one bounce at a time.
+    ///
+    /// Each call to [`Iterator::next`] runs at most one deferred closure and yields
+    /// [`Step::Bounce`], until the final [`Step::Done`]. The remaining computation can be taken
+    /// back out with [`Steps::into_thunk`] at any point.
+    ///
+    /// ```rust
+    /// use tailcall::runtime::{Step, Thunk};
+    ///
+    /// fn countdown(n: u32) -> Thunk<'static, u32> {
+    ///     Thunk::bounce(move || if n == 0 { Thunk::value(0) } else { countdown(n - 1) })
+    /// }
+    ///
+    /// let steps: Vec<_> = countdown(2).into_steps().collect();
+    ///
+    /// assert_eq!(steps, [Step::Bounce, Step::Bounce, Step::Bounce, Step::Done(0)]);
+    /// ```
+    pub fn into_steps(self) -> Steps<'a, T> {
+        Steps { thunk: Some(self) }
+    }
+
+    /// Resolves the deferred computation like [`Thunk::call`], also reporting how many bounces it
+    /// took and how much state the largest one captured.
+    ///
+    /// ```rust
+    /// use tailcall::runtime::{Stats, Thunk};
+    ///
+    /// fn countdown(n: u32) -> Thunk<'static, u32> {
+    ///     Thunk::bounce(move || if n == 0 { Thunk::value(0) } else { countdown(n - 1) })
+    /// }
+    ///
+    /// let (value, stats) = countdown(3).call_with_stats();
+    ///
+    /// assert_eq!(value, 0);
+    /// assert_eq!(
+    ///     stats,
+    ///     Stats {
+    ///         bounces: 4,
+    ///         max_captured_size: 4,
+    ///     }
+    /// );
+    /// ```
+    pub fn call_with_stats(mut self) -> (T, Stats) {
+        let mut stats = Stats::default();
+
+        loop {
+            match self.0 {
+                ThunkKind::Bounce(erased_fn_once) => {
+                    stats.bounces += 1;
+                    stats.max_captured_size =
+                        stats.max_captured_size.max(erased_fn_once.captured_size());
+                    self = erased_fn_once.call();
+                }
+                ThunkKind::Done(value) => return (value, stats),
+            }
+        }
+    }
+}
+
+/// Counters collected while resolving a [`Thunk`] with [`Thunk::call_with_stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// The number of deferred closures that were called.
+    pub bounces: usize,
+    /// The largest captured state, in bytes, among the called closures.
+    ///
+    /// A value close to the inline capture budget means a small change to the captured
+    /// arguments may start panicking at construction.
+    pub max_captured_size: usize,
+}
+
+/// A single step yielded by [`Steps`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step<T> {
+    /// A deferred closure ran and the computation continues.
+    Bounce,
+    /// The computation resolved to this value.
+    Done(T),
+}
+
+/// An iterator that resolves a [`Thunk`] one bounce at a time.
+///
+/// Created by [`Thunk::into_steps`].
+pub struct Steps<'a, T> {
+    thunk: Option<Thunk<'a, T>>,
+}
+
+impl<'a, T> Steps<'a, T> {
+    /// Returns the rest of the computation, or `None` once it has resolved.
+    pub fn into_thunk(self) -> Option<Thunk<'a, T>> {
+        self.thunk
+    }
+}
+
+impl<T> Iterator for Steps<'_, T> {
+    type Item = Step<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.thunk.take()?.0 {
+            ThunkKind::Bounce(erased_fn_once) => {
+                self.thunk = Some(erased_fn_once.call());
+                Some(Step::Bounce)
+            }
+            ThunkKind::Done(value) => Some(Step::Done(value)),
+        }
+    }
+}
+
+impl<T> FusedIterator for Steps<'_, T> {}
+
+impl<T> fmt::Debug for Steps<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Steps").field("thunk", &self.thunk).finish()
+    }
 }
 
 impl<T> fmt::Debug for Thunk<'_, T> {
@@ -70,9 +370,422 @@ impl<T> fmt::Debug for Thunk<'_, T> {
 
 #[cfg(test)]
 mod tests {
-    use super::Thunk;
-    use core::mem::size_of;
+    use super::{Stats, Step, Thunk};
+    use crate::runtime::Arena;
+    use core::{
+        cell::Cell,
+        mem::{align_of, size_of, MaybeUninit},
+        ops::ControlFlow,
+    };
+
+    fn sum_to(n: u64, acc: u64) -> Thunk<'static, u64> {
+        Thunk::bounce(move || {
+            if n == 0 {
+                Thunk::new(move || acc)
+            } else {
+                sum_to(n - 1, acc + n)
+            }
+        })
+    }
+
+    #[test]
+    fn call_with_stats_counts_bounces_and_largest_capture() {
+        let (value, stats) = sum_to(3, 0).call_with_stats();
+
+        assert_eq!(value, 6);
+        assert_eq!(
+            stats,
+            Stats {
+                bounces: 5,
+                max_captured_size: 16,
+            }
+        );
+    }
+
+    #[test]
+    fn call_with_stats_reports_nothing_for_values() {
+        assert_eq!(Thunk::value(7).call_with_stats(), (7, Stats::default()));
+    }
+
+    fn countdown(n: u32, ran: &Cell<u32>) -> Thunk<'_, u32> {
+        Thunk::bounce(move || {
+            ran.set(ran.get() + 1);
+            if n == 0 {
+                Thunk::value(0)
+            } else {
+                countdown(n - 1, ran)
+            }
+        })
+    }
+
+    #[test]
+    fn call_with_hook_runs_the_hook_every_n_bounces() {
+        let ran = Cell::new(0);
+        let mut hooks = 0;
+
+        let result = countdown(9, &ran).call_with_hook(3, || {
+            hooks += 1;
+            ControlFlow::Continue(())
+        });
+
+        assert_eq!(result.ok(), Some(0));
+        assert_eq!(ran.get(), 10);
+        assert_eq!(hooks, 3);
+    }
+
+    #[test]
+    fn call_with_hook_returns_the_pending_thunk_on_break() {
+        let ran = Cell::new(0);
+
+        let paused = countdown(9, &ran)
+            .call_with_hook(4, || ControlFlow::Break(()))
+            .expect_err("the hook should stop the computation");
+
+        assert_eq!(ran.get(), 4);
+        assert_eq!(paused.call(), 0);
+        assert_eq!(ran.get(), 10);
+    }
+
+    #[test]
+    fn call_with_hook_skips_the_hook_for_values() {
+        let result = Thunk::value(7).call_with_hook(1, || ControlFlow::Break(()));
+
+        assert_eq!(result.ok(), Some(7));
+    }
+
+    fn count_down_with(n: u32, ran: &Cell<u32>) -> Thunk<'_, u32> {
+        Thunk::bounce_with((n, ran), |(n, ran)| {
+            ran.set(ran.get() + 1);
+            if n == 0 {
+                Thunk::value(0)
+            } else {
+                count_down_with(n - 1, ran)
+            }
+        })
+    }
+
+    #[test]
+    fn bounce_with_calls_the_function_with_its_state() {
+        let ran = Cell::new(0);
+
+        assert_eq!(count_down_with(100_000, &ran).call(), 0);
+        assert_eq!(ran.get(), 100_001);
+    }
+
+    #[test]
+    fn bounce_with_reports_the_state_size() {
+        let ran = Cell::new(0);
+        let (_, stats) = count_down_with(3, &ran).call_with_stats();
+
+        assert_eq!(stats.bounces, 4);
+        assert_eq!(stats.max_captured_size, size_of::<(u32, &Cell<u32>)>());
+    }
+
+    #[test]
+    fn dropping_a_pending_bounce_with_drops_its_state() {
+        extern crate std;
+        use std::rc::Rc;
+
+        let state = Rc::new(());
+        let thunk = Thunk::bounce_with(Rc::clone(&state), |_| Thunk::value(()));
+
+        assert_eq!(Rc::strong_count(&state), 2);
+        drop(thunk);
+        assert_eq!(Rc::strong_count(&state), 1);
+    }
+
+    fn hybrid_countdown(n: u32, limit: usize) -> Thunk<'static, u32> {
+        Thunk::call_or_bounce(limit, move || {
+            if n == 0 {
+                Thunk::value(0)
+            } else {
+                hybrid_countdown(n - 1, limit)
+            }
+        })
+    }
+
+    #[test]
+    fn call_or_bounce_calls_natively_within_the_limit() {
+        let ran = Cell::new(false);
+        let thunk = Thunk::call_or_bounce(usize::MAX, || {
+            ran.set(true);
+            Thunk::value(1)
+        });
+
+        assert!(ran.get());
+        assert_eq!(thunk.call_with_stats().1.bounces, 0);
+    }
+
+    #[test]
+    fn call_or_bounce_defers_past_the_limit() {
+        let ran = Cell::new(false);
+        let thunk = Thunk::call_or_bounce(0, || {
+            ran.set(true);
+            Thunk::value(1)
+        });
+
+        assert!(!ran.get());
+        assert_eq!(thunk.call(), 1);
+        assert!(ran.get());
+    }
+
+    #[test]
+    fn call_or_bounce_handles_deep_recursion() {
+        let (value, stats) = hybrid_countdown(100_000, 16).call_with_stats();
+
+        assert_eq!(value, 0);
+        assert!(stats.bounces > 0);
+    }
+
+    #[test]
+    fn into_steps_runs_one_bounce_per_step() {
+        let ran = Cell::new(0);
+        let mut steps = countdown(2, &ran).into_steps();
+
+        assert_eq!(steps.next(), Some(Step::Bounce));
+        assert_eq!(ran.get(), 1);
+        assert_eq!(steps.next(), Some(Step::Bounce));
+        assert_eq!(ran.get(), 2);
+
+        let rest = steps.into_thunk().expect("one bounce should remain");
+        assert_eq!(rest.call(), 0);
+        assert_eq!(ran.get(), 3);
+    }
+
+    #[test]
+    fn into_steps_ends_after_the_value() {
+        let mut steps = Thunk::value(7).into_steps();
+
+        assert_eq!(steps.next(), Some(Step::Done(7)));
+        assert_eq!(steps.next(), None);
+        assert!(steps.into_thunk().is_none());
+    }
+
+    #[derive(Clone, Copy)]
+    #[repr(C, align(32))]
+    struct Lanes([f32; 8]);
+
+    #[derive(Clone, Copy)]
+    #[repr(C, align(64))]
+    struct CacheLine([u64; 8]);
+
+    #[repr(align(64))]
+    struct AlignedDrop<'a>(&'a Cell<u32>);
+
+    impl Drop for AlignedDrop<'_> {
+        fn drop(&mut self) {
+            assert_eq!(core::ptr::from_mut(self).align_offset(64), 0);
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    fn halve_lanes<'a>(arena: &'a Arena<'_>, lanes: Lanes, n: u32) -> Thunk<'a, f32> {
+        arena.bounce(move || {
+            assert_eq!(core::ptr::from_ref(&lanes).align_offset(32), 0);
+            if n == 0 {
+                Thunk::value(lanes.0.iter().sum())
+            } else {
+                halve_lanes(arena, Lanes(lanes.0.map(|lane| lane * 0.5)), n - 1)
+            }
+        })
+    }
+
+    fn fill_line<'a>(arena: &'a Arena<'_>, line: CacheLine, n: u32) -> Thunk<'a, u64> {
+        arena.bounce(move || {
+            assert_eq!(core::ptr::from_ref(&line).align_offset(64), 0);
+            if n == 0 {
+                Thunk::value(line.0.iter().sum())
+            } else {
+                let mut next = line;
+                next.0[n as usize % 8] += 1;
+                fill_line(arena, next, n - 1)
+            }
+        })
+    }
+
+    #[test]
+    #[should_panic(expected = "also limits over-aligned captures")]
+    fn bounce_rejects_non_empty_captures_aligned_beyond_the_inline_budget() {
+        let line = CacheLine([1; 8]);
+
+        let _ = Thunk::bounce(move || Thunk::value(line.0[0]));
+    }
+
+    #[test]
+    fn arena_bounces_captures_aligned_to_32_and_64_bytes() {
+        let mut buffer = [MaybeUninit::uninit(); 256];
+        let arena = Arena::new(&mut buffer);
+
+        assert_eq!(halve_lanes(&arena, Lanes([4.0; 8]), 2).call(), 8.0);
+        assert_eq!(fill_line(&arena, CacheLine([0; 8]), 100).call(), 100);
+    }
+
+    #[test]
+    fn arena_drops_pending_over_aligned_captures_once() {
+        let drops = Cell::new(0);
+        let padding = [0_u64; 8];
+        let mut buffer = [MaybeUninit::uninit(); 256];
+        let arena = Arena::new(&mut buffer);
+
+        let guard = AlignedDrop(&drops);
+        drop(arena.bounce(move || {
+            let _guard = guard;
+            Thunk::value(padding.len())
+        }));
+
+        assert_eq!(drops.get(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn bounce_or_box_bounces_captures_aligned_to_32_and_64_bytes() {
+        fn halve_lanes(lanes: Lanes, n: u32) -> Thunk<'static, f32> {
+            Thunk::bounce_or_box(move || {
+                assert_eq!(core::ptr::from_ref(&lanes).align_offset(32), 0);
+                if n == 0 {
+                    Thunk::value(lanes.0.iter().sum())
+                } else {
+                    halve_lanes(Lanes(lanes.0.map(|lane| lane * 0.5)), n - 1)
+                }
+            })
+        }
+
+        fn fill_line(line: CacheLine, n: u32) -> Thunk<'static, u64> {
+            Thunk::bounce_or_box(move || {
+                assert_eq!(core::ptr::from_ref(&line).align_offset(64), 0);
+                if n == 0 {
+                    Thunk::value(line.0.iter().sum())
+                } else {
+                    let mut next = line;
+                    next.0[n as usize % 8] += 1;
+                    fill_line(next, n - 1)
+                }
+            })
+        }
+
+        assert_eq!(halve_lanes(Lanes([4.0; 8]), 2).call(), 8.0);
+        assert_eq!(fill_line(CacheLine([0; 8]), 100).call(), 100);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn bounce_or_box_runs_and_drops_over_aligned_payloads_once() {
+        #[repr(C, align(32))]
+        struct Tracked<'a> {
+            lanes: [f32; 7],
+            drops: &'a Cell<u32>,
+        }
+
+        impl Drop for Tracked<'_> {
+            fn drop(&mut self) {
+                assert_eq!(core::ptr::from_mut(self).align_offset(32), 0);
+                self.drops.set(self.drops.get() + 1);
+            }
+        }
+
+        let drops = Cell::new(0);
+
+        let tracked = Tracked {
+            lanes: [1.0; 7],
+            drops: &drops,
+        };
+        let pending = Thunk::bounce_or_box(move || {
+            let tracked = tracked;
+            Thunk::value(tracked.lanes.iter().sum::<f32>())
+        });
+        assert_eq!(pending.call(), 7.0);
+        assert_eq!(drops.get(), 1);
+
+        let tracked = Tracked {
+            lanes: [1.0; 7],
+            drops: &drops,
+        };
+        drop(Thunk::bounce_or_box(move || {
+            let tracked = tracked;
+            Thunk::value(tracked.lanes[0])
+        }));
+        assert_eq!(drops.get(), 2);
+
+        let guard = AlignedDrop(&drops);
+        drop(Thunk::bounce_or_box(move || {
+            let _guard = guard;
+            Thunk::value(0.0)
+        }));
+        assert_eq!(drops.get(), 3);
+    }
+
+    #[test]
+    #[cfg(feature = "at-least-inline-captures-32")]
+    fn accepts_over_aligned_captures_up_to_the_inline_budget() {
+        #[derive(Clone, Copy)]
+        #[repr(C, align(32))]
+        struct Lanes([f32; 8]);
+
+        let lanes = Lanes([1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0]);
+        let thunk = Thunk::new(move || {
+            let lanes = lanes;
+            assert_eq!(core::ptr::from_ref(&lanes).align_offset(32), 0);
+            lanes.0.iter().sum::<f32>()
+        });
+
+        assert_eq!(thunk.call(), 36.0);
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "slot-align-8", feature = "slot-align-4")))]
+    fn thunk_layout_is_16_byte_aligned_by_default() {
+        assert_eq!(align_of::<Thunk<'static, ()>>(), 16);
+        assert_eq!(align_of::<Thunk<'static, bool>>(), 16);
+    }
+
+    // With a smaller slot alignment, the vtable and context pointers decide the alignment.
+    #[test]
+    #[cfg(feature = "slot-align-8")]
+    fn thunk_layout_is_8_byte_aligned_with_slot_align_8() {
+        assert_eq!(align_of::<Thunk<'static, ()>>(), align_of::<usize>().max(8));
+        assert_eq!(
+            align_of::<Thunk<'static, bool>>(),
+            align_of::<usize>().max(8)
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "slot-align-4", not(feature = "slot-align-8")))]
+    fn thunk_layout_is_pointer_aligned_with_slot_align_4() {
+        assert_eq!(align_of::<Thunk<'static, ()>>(), align_of::<usize>().max(4));
+        assert_eq!(
+            align_of::<Thunk<'static, bool>>(),
+            align_of::<usize>().max(4)
+        );
+    }
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    #[cfg(not(any(
+        feature = "at-least-inline-captures-32",
+        feature = "slot-align-8",
+        feature = "slot-align-4"
+    )))]
+    fn thunk_is_32_bytes_on_32_bit_targets() {
+        assert_eq!(size_of::<Thunk<'static, ()>>(), 32);
+        assert_eq!(size_of::<Thunk<'static, bool>>(), 32);
+        assert_eq!(size_of::<Thunk<'static, u32>>(), 32);
+    }
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    #[cfg(all(
+        not(feature = "at-least-inline-captures-32"),
+        any(feature = "slot-align-8", feature = "slot-align-4")
+    ))]
+    fn thunk_is_24_bytes_on_32_bit_targets_with_a_smaller_slot_alignment() {
+        assert_eq!(size_of::<Thunk<'static, ()>>(), 24);
+        assert_eq!(size_of::<Thunk<'static, bool>>(), 24);
+        assert_eq!(size_of::<Thunk<'static, u32>>(), 24);
+    }
 
+    // On 64-bit targets the slot alignment only changes the alignment of a `Thunk`: the slot and
+    // both pointers already add up to a multiple of 16 bytes.
     #[cfg(target_pointer_width = "64")]
     #[test]
     #[cfg(not(any(
@@ -0,0 +1,133 @@
+// The interface `#[tailcall(runtime = path)]` generates code against.
+
+use super::Thunk;
+
+/// A deferred computation that can be driven to completion without growing the stack.
+///
+/// [`Thunk`] and `BoxThunk` implement this trait. Other runtimes can implement it too, for example
+/// to allocate bounces from an arena, count them, or make the computation `Send`, and then be
+/// used by the macro with `#[tailcall(runtime = path::To::Runtime)]`. The macro names the type as
+/// `Runtime<'tailcall, R>` for a function returning `R`, so it must take a lifetime and the output
+/// type as its generic parameters, like [`Thunk`].
+///
+/// ```rust
+/// use tailcall::runtime::{Thunk, Trampoline};
+///
+/// // A runtime that just forwards to `Thunk`.
+/// struct Wrapped<'a, T>(Thunk<'a, T>);
+///
+/// impl<'a, T> Trampoline<'a> for Wrapped<'a, T> {
+///     type Output = T;
+///
+///     fn value(value: T) -> Self {
+///         Wrapped(Thunk::value(value))
+///     }
+///
+///     fn bounce<F>(fn_once: F) -> Self
+///     where
+///         F: FnOnce() -> Self + 'a,
+///     {
+///         Wrapped(Thunk::bounce(move || fn_once().0))
+///     }
+///
+///     fn run(self) -> T {
+///         self.0.call()
+///     }
+/// }
+///
+/// fn countdown(n: u32) -> Wrapped<'static, u32> {
+///     Wrapped::bounce(move || if n == 0 { Wrapped::value(0) } else { countdown(n - 1) })
+/// }
+///
+/// assert_eq!(countdown(1_000).run(), 0);
+/// ```
+pub trait Trampoline<'a>: Sized {
+    /// The value the computation resolves to.
+    type Output;
+
+    /// Produces a computation that resolves directly to `value`.
+    fn value(value: Self::Output) -> Self;
+
+    /// Produces a pending computation that continues with the one returned by `fn_once`.
+    fn bounce<F>(fn_once: F) -> Self
+    where
+        F: FnOnce() -> Self + 'a;
+
+    /// Resolves the computation to its final value.
+    fn run(self) -> Self::Output;
+}
+
+impl<'a, T> Trampoline<'a> for Thunk<'a, T> {
+    type Output = T;
+
+    #[inline(always)]
+    fn value(value: T) -> Self {
+        Thunk::value(value)
+    }
+
+    #[inline(always)]
+    fn bounce<F>(fn_once: F) -> Self
+    where
+        F: FnOnce() -> Self + 'a,
+    {
+        Thunk::bounce(fn_once)
+    }
+
+    #[inline(always)]
+    fn run(self) -> T {
+        self.call()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, T> Trampoline<'a> for super::BoxThunk<'a, T> {
+    type Output = T;
+
+    #[inline(always)]
+    fn value(value: T) -> Self {
+        super::BoxThunk::value(value)
+    }
+
+    #[inline(always)]
+    fn bounce<F>(fn_once: F) -> Self
+    where
+        F: FnOnce() -> Self + 'a,
+    {
+        super::BoxThunk::bounce(fn_once)
+    }
+
+    #[inline(always)]
+    fn run(self) -> T {
+        self.call()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Trampoline;
+    use crate::runtime::Thunk;
+
+    fn countdown<'a, R: Trampoline<'a, Output = u32> + 'a>(n: u32) -> R {
+        R::bounce(move || {
+            if n == 0 {
+                R::value(0)
+            } else {
+                countdown(n - 1)
+            }
+        })
+    }
+
+    #[test]
+    fn thunk_runs_through_the_trait() {
+        assert_eq!(countdown::<Thunk<'_, u32>>(100_000).run(), 0);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn box_thunk_runs_through_the_trait() {
+        assert_eq!(
+            countdown::<crate::runtime::BoxThunk<'_, u32>>(100_000).run(),
+            0
+        );
+    }
+}
@@ -0,0 +1,210 @@
+//! Per-function call counters for profiling trampolined code.
+//!
+//! With the `stats` feature enabled, every logical call of a `#[tailcall]` function (each loop
+//! iteration or each bounce) increments a static counter owned by that function. [`report`]
+//! walks the counters of every function that has run at least once, so hot trampolines can be
+//! found without a profiler that understands them.
+//!
+//! ```rust
+//! use tailcall::tailcall;
+//!
+//! #[tailcall]
+//! fn countdown(n: u32) -> u32 {
+//!     if n == 0 {
+//!         0
+//!     } else {
+//!         tailcall::call! { countdown(n - 1) }
+//!     }
+//! }
+//!
+//! countdown(3);
+//!
+//! let countdown_stats = tailcall::stats::report()
+//!     .find(|stats| stats.function.ends_with("::countdown"))
+//!     .unwrap();
+//! assert!(countdown_stats.calls >= 4);
+//! ```
+//!
+//! Counters are process-wide and only use atomics, so this feature does not require `std`.
+
+use core::{
+    fmt, ptr,
+    sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering},
+};
+
+/// The number of logical calls recorded for one `#[tailcall]` function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FunctionStats {
+    /// The module-qualified name of the function.
+    pub function: &'static str,
+    /// The file containing the function's `#[tailcall]` attribute.
+    pub file: &'static str,
+    /// The line of the function's `#[tailcall]` attribute.
+    pub line: u32,
+    /// The number of logical calls since the program started or [`reset`] was last called.
+    pub calls: usize,
+}
+
+impl fmt::Display for FunctionStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at {}:{}: {} calls",
+            self.function, self.file, self.line, self.calls
+        )
+    }
+}
+
+/// A per-function counter. Generated code declares one `static` per function; it is not meant to
+/// be used directly.
+#[doc(hidden)]
+pub struct Counter {
+    function: &'static str,
+    file: &'static str,
+    line: u32,
+    calls: AtomicUsize,
+    registered: AtomicBool,
+    next: AtomicPtr<Counter>,
+}
+
+// Counters link themselves into this list the first time they are hit, newest first. Entries are
+// `'static` and never unlinked, so a snapshot of `next` is always safe to follow.
+static COUNTERS: AtomicPtr<Counter> = AtomicPtr::new(ptr::null_mut());
+
+impl Counter {
+    #[doc(hidden)]
+    pub const fn new(function: &'static str, file: &'static str, line: u32) -> Self {
+        Self {
+            function,
+            file,
+            line,
+            calls: AtomicUsize::new(0),
+            registered: AtomicBool::new(false),
+            next: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    /// Records a logical call.
+    #[doc(hidden)]
+    #[inline]
+    pub fn hit(&'static self) {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+
+        if !self.registered.load(Ordering::Relaxed) && !self.registered.swap(true, Ordering::AcqRel)
+        {
+            self.register();
+        }
+    }
+
+    #[cold]
+    fn register(&'static self) {
+        let this = ptr::from_ref(self).cast_mut();
+        let mut head = COUNTERS.load(Ordering::Acquire);
+
+        loop {
+            self.next.store(head, Ordering::Relaxed);
+
+            match COUNTERS.compare_exchange_weak(head, this, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => return,
+                Err(current) => head = current,
+            }
+        }
+    }
+
+    fn snapshot(&self) -> FunctionStats {
+        FunctionStats {
+            function: self.function,
+            file: self.file,
+            line: self.line,
+            calls: self.calls.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// An iterator over the counters of every `#[tailcall]` function that has run at least once.
+///
+/// Created by [`report`].
+#[derive(Clone, Debug)]
+pub struct Report {
+    next: *const Counter,
+}
+
+impl Iterator for Report {
+    type Item = FunctionStats;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // SAFETY: every pointer in the list comes from a `&'static Counter`.
+        let counter = unsafe { self.next.as_ref() }?;
+        self.next = counter.next.load(Ordering::Acquire);
+        Some(counter.snapshot())
+    }
+}
+
+/// Returns the call counts of every `#[tailcall]` function that has run at least once, most
+/// recently first-run function first.
+pub fn report() -> Report {
+    Report {
+        next: COUNTERS.load(Ordering::Acquire),
+    }
+}
+
+/// Resets every call count to zero.
+pub fn reset() {
+    let mut next = COUNTERS.load(Ordering::Acquire);
+
+    // SAFETY: every pointer in the list comes from a `&'static Counter`.
+    while let Some(counter) = unsafe { next.as_ref() } {
+        counter.calls.store(0, Ordering::Relaxed);
+        next = counter.next.load(Ordering::Acquire);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::{report, Counter, FunctionStats};
+    use std::format;
+
+    static FIRST: Counter = Counter::new("tests::first", "stats.rs", 1);
+    static SECOND: Counter = Counter::new("tests::second", "stats.rs", 2);
+
+    fn calls(function: &str) -> Option<usize> {
+        report()
+            .find(|stats| stats.function == function)
+            .map(|stats| stats.calls)
+    }
+
+    #[test]
+    fn counters_appear_once_hit() {
+        assert_eq!(calls("tests::second"), None);
+
+        SECOND.hit();
+        SECOND.hit();
+        FIRST.hit();
+
+        assert_eq!(calls("tests::first"), Some(1));
+        assert_eq!(calls("tests::second"), Some(2));
+        assert_eq!(
+            report()
+                .filter(|stats| stats.function == "tests::second")
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn displays_the_function_location_and_count() {
+        let stats = FunctionStats {
+            function: "demo::countdown",
+            file: "src/lib.rs",
+            line: 3,
+            calls: 10,
+        };
+
+        assert_eq!(
+            format!("{stats}"),
+            "demo::countdown at src/lib.rs:3: 10 calls"
+        );
+    }
+}
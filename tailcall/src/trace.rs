@@ -0,0 +1,175 @@
+//! Logical call-chain recording for debugging trampolined code.
+//!
+//! A function transformed by [`crate::tailcall`] runs either as an inline loop or through
+//! [`crate::Thunk::call`], so a backtrace taken inside it shows a single frame no matter how many
+//! tail calls led there. With the `debug-trace` feature enabled, every logical call of a
+//! `#[tailcall]` function is recorded into a bounded, thread-local ring buffer instead.
+//!
+//! ```rust
+//! use tailcall::tailcall;
+//!
+//! #[tailcall]
+//! fn countdown(n: u32) -> u32 {
+//!     if n == 0 {
+//!         0
+//!     } else {
+//!         tailcall::call! { countdown(n - 1) }
+//!     }
+//! }
+//!
+//! countdown(3);
+//!
+//! let calls = tailcall::trace::last_calls();
+//! assert_eq!(calls.len(), 4);
+//! assert!(calls.iter().all(|call| call.function.ends_with("countdown")));
+//! ```
+//!
+//! [`install_panic_hook`] prints the recorded calls whenever a thread panics.
+
+use core::{cell::RefCell, fmt};
+use std::{panic, thread_local, vec::Vec};
+
+/// The number of logical calls kept per thread. Older calls are overwritten.
+pub const CAPACITY: usize = 64;
+
+/// A single logical call of a `#[tailcall]` function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// The module-qualified name of the called function.
+    pub function: &'static str,
+    /// The file containing the function's `#[tailcall]` attribute.
+    pub file: &'static str,
+    /// The line of the function's `#[tailcall]` attribute.
+    pub line: u32,
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}:{}", self.function, self.file, self.line)
+    }
+}
+
+struct RingBuffer {
+    entries: [Option<TraceEntry>; CAPACITY],
+    next: usize,
+}
+
+impl RingBuffer {
+    const fn new() -> Self {
+        Self {
+            entries: [None; CAPACITY],
+            next: 0,
+        }
+    }
+
+    fn push(&mut self, entry: TraceEntry) {
+        self.entries[self.next] = Some(entry);
+        self.next = (self.next + 1) % CAPACITY;
+    }
+
+    fn to_vec(&self) -> Vec<TraceEntry> {
+        self.entries[self.next..]
+            .iter()
+            .chain(&self.entries[..self.next])
+            .flatten()
+            .copied()
+            .collect()
+    }
+
+    fn clear(&mut self) {
+        *self = Self::new();
+    }
+}
+
+thread_local! {
+    static CALLS: RefCell<RingBuffer> = const { RefCell::new(RingBuffer::new()) };
+}
+
+/// Records a logical call. Generated code calls this; it is not meant to be called directly.
+#[doc(hidden)]
+pub fn record(function: &'static str, file: &'static str, line: u32) {
+    let entry = TraceEntry {
+        function,
+        file,
+        line,
+    };
+
+    // Recording is best-effort: a thread that is being torn down has no buffer left.
+    let _ = CALLS.try_with(|calls| calls.borrow_mut().push(entry));
+}
+
+/// Returns up to [`CAPACITY`] of the most recent logical calls on this thread, oldest first.
+pub fn last_calls() -> Vec<TraceEntry> {
+    CALLS
+        .try_with(|calls| calls.borrow().to_vec())
+        .unwrap_or_default()
+}
+
+/// Forgets every call recorded on this thread so far.
+pub fn clear() {
+    let _ = CALLS.try_with(|calls| calls.borrow_mut().clear());
+}
+
+/// Installs a panic hook that prints the panicking thread's recorded calls to standard error
+/// before running the previously installed hook.
+pub fn install_panic_hook() {
+    let previous = panic::take_hook();
+
+    panic::set_hook(std::boxed::Box::new(move |info| {
+        let calls = last_calls();
+
+        if !calls.is_empty() {
+            std::eprintln!("last {} tail calls (oldest first):", calls.len());
+            for call in &calls {
+                std::eprintln!("    {call}");
+            }
+        }
+
+        previous(info);
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{clear, last_calls, record, TraceEntry, CAPACITY};
+
+    #[test]
+    fn keeps_calls_in_order() {
+        clear();
+        record("first", "lib.rs", 1);
+        record("second", "lib.rs", 2);
+
+        assert_eq!(
+            last_calls(),
+            [
+                TraceEntry {
+                    function: "first",
+                    file: "lib.rs",
+                    line: 1,
+                },
+                TraceEntry {
+                    function: "second",
+                    file: "lib.rs",
+                    line: 2,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn overwrites_the_oldest_calls_once_full() {
+        clear();
+        for line in 0..(CAPACITY as u32 + 3) {
+            record("call", "lib.rs", line);
+        }
+
+        let calls = last_calls();
+
+        assert_eq!(calls.len(), CAPACITY);
+        assert_eq!(calls.first().map(|call| call.line), Some(3));
+        assert_eq!(
+            calls.last().map(|call| call.line),
+            Some(CAPACITY as u32 + 2)
+        );
+    }
+}